@@ -0,0 +1,131 @@
+use crate::{InputFileName, ScoringError};
+use crate::registry::{InputFile, RegistryError};
+
+/// The canonical id of an input case and the other names a submission may refer to it by
+pub struct CaseNames {
+    pub id: &'static str,
+    pub aliases: &'static [&'static str],
+}
+
+struct InputCase<C> {
+    id: InputFileName,
+    aliases: Vec<String>,
+    case: C,
+}
+
+impl<C> InputCase<C> {
+    fn is_called(&self, name: &str) -> bool {
+        self.id.0 == name || self.aliases.iter().any(|alias| alias == name)
+    }
+}
+
+/// The parsed input cases of a challenge, looked up by their exact id or alias
+pub struct InputCases<C> {
+    cases: Vec<InputCase<C>>,
+}
+
+impl<C> InputCases<C> {
+    /// Parses every input file, naming each case after the `known` entry that lists its file
+    /// name or stem. Files missing from `known` are named by their stem, with the file name
+    /// as the only alias.
+    pub fn load<P>(input_files: Vec<InputFile>, known: &[CaseNames], parse: P) -> Result<Self, RegistryError>
+        where P: Fn(&str) -> Result<C, ScoringError>
+    {
+        let cases = input_files.iter()
+            .map(|input_file| {
+                let case = parse(&input_file.contents).map_err(|e| input_file.malformed(e))?;
+                let file_name = input_file.file_name();
+                let stem = input_file.stem();
+
+                let (id, aliases) = match known.iter().find(|names| names.id == stem
+                    || names.aliases.contains(&stem)
+                    || names.aliases.contains(&file_name)) {
+                    Some(names) => (names.id.into(), names.aliases.iter().map(|&alias| alias.to_owned()).collect()),
+                    None => (stem.into(), vec![file_name.to_owned()]),
+                };
+
+                Ok(InputCase { id, aliases, case })
+            })
+            .collect::<Result<_, RegistryError>>()?;
+
+        Ok(Self { cases })
+    }
+
+    pub fn ids(&self) -> Vec<InputFileName> {
+        self.cases.iter().map(|input_case| input_case.id.clone()).collect()
+    }
+
+    /// The id of the single case called `name`, failing on unknown or ambiguous names
+    pub fn resolve(&self, name: &InputFileName) -> Result<&InputFileName, ScoringError> {
+        self.find(name).map(|input_case| &input_case.id)
+    }
+
+    /// The id and parsed case of the single case called `name`
+    pub fn get(&self, name: &InputFileName) -> Result<(&InputFileName, &C), ScoringError> {
+        self.find(name).map(|input_case| (&input_case.id, &input_case.case))
+    }
+
+    fn find(&self, name: &InputFileName) -> Result<&InputCase<C>, ScoringError> {
+        let mut matching = self.cases.iter().filter(|input_case| input_case.is_called(&name.0));
+        match (matching.next(), matching.next()) {
+            (Some(input_case), None) => Ok(input_case),
+            _ => Err(ScoringError::UnknownInputCase(name.clone(), self.ids())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CaseNames, InputCases};
+    use crate::registry::InputFile;
+    use crate::{InputFileName, ScoringError};
+
+    const NAMES: &[CaseNames] = &[
+        CaseNames { id: "a", aliases: &["a_example", "a_example.txt"] },
+        CaseNames { id: "b", aliases: &["b_read_on", "b_read_on.txt"] },
+    ];
+
+    fn cases(file_names: &[&str]) -> InputCases<String> {
+        let input_files = file_names.iter()
+            .map(|file_name| InputFile { path: file_name.into(), contents: file_name.to_string() })
+            .collect();
+        InputCases::load(input_files, NAMES, |contents| Ok(contents.to_owned())).expect("Should load")
+    }
+
+    #[test]
+    fn resolves_id_and_aliases() {
+        let cases = cases(&["a_example.txt", "b_read_on.txt"]);
+        assert_eq!(cases.ids(), vec!["a".into(), "b".into()]);
+        for name in &["a", "a_example", "a_example.txt"] {
+            let (id, contents) = cases.get(&(*name).into()).expect("known alias");
+            assert_eq!(id, &InputFileName::from("a"));
+            assert_eq!(contents, "a_example.txt");
+        }
+    }
+
+    #[test]
+    fn prefix_is_not_a_match() {
+        let cases = cases(&["a_example.txt", "b_read_on.txt"]);
+        match cases.get(&"apple".into()) {
+            Err(ScoringError::UnknownInputCase(name, valid)) => {
+                assert_eq!(name, InputFileName::from("apple"));
+                assert_eq!(valid, vec!["a".into(), "b".into()]);
+            },
+            _ => panic!("apple shouldn't be scored against case a"),
+        }
+    }
+
+    #[test]
+    fn unlisted_file_is_named_by_its_stem() {
+        let cases = cases(&["busy_day.in", "busy_day_v2.in"]);
+        assert_eq!(cases.get(&"busy_day".into()).expect("known").1, "busy_day.in");
+        assert_eq!(cases.get(&"busy_day_v2.in".into()).expect("known").1, "busy_day_v2.in");
+    }
+
+    #[test]
+    fn ambiguous_name_is_rejected() {
+        // Two files claiming the same case id
+        let cases = cases(&["a_example.txt", "a.txt"]);
+        assert!(matches!(cases.get(&"a".into()), Err(ScoringError::UnknownInputCase(..))));
+    }
+}
@@ -1,31 +1,52 @@
-#[macro_use]
-extern crate lazy_static;
 pub mod qual2020;
 pub mod qual2016;
+pub mod qual2017;
+pub mod qual2018;
+pub mod qual2019;
+pub mod qual2021;
+pub mod qual2022;
+pub mod registry;
+pub mod input_cases;
+mod span;
+mod parse;
+
+pub use registry::{ChallengeRegistry, RegistryError};
+pub use span::Span;
 
 use thiserror::Error;
+use std::collections::BTreeMap;
 use std::fmt::{self, Debug, Display};
 use serde_derive::{Serialize, Deserialize};
 
 #[derive(Error, Debug)]
 pub enum ScoringError {
-    #[error("Missing line")]
-    MissingLine,
-    #[error("Expected a number")]
-    ExpectedANumber,
-    #[error("Doesn't have input case with name {0}")]
-    UnknownInputCase(InputFileName),
+    #[error("Missing line at {0}")]
+    MissingLine(Span),
+    #[error("Expected a number at {0}")]
+    ExpectedANumber(Span),
+    #[error("Doesn't have input case with name {0}, valid names are: {}", display_names(.1))]
+    UnknownInputCase(InputFileName, Vec<InputFileName>),
     #[error("Challenge Specific: {0}")]
     ChallengeSpecific(Box<dyn std::error::Error + std::marker::Sync + std::marker::Send>),
-    #[error("Error parsing the input file: {0}")]
-    InputFileError(Box<dyn std::error::Error + std::marker::Sync + std::marker::Send>),
-    #[error("Error parsing the submission file: {0}")]
-    SubmissionFileError(Box<dyn std::error::Error + std::marker::Sync + std::marker::Send>)
+    #[error("Error parsing the input file at {span}: {source}")]
+    InputFileError{ span: Span, source: Box<dyn std::error::Error + std::marker::Sync + std::marker::Send> },
+    #[error("Error parsing the submission file at {span}: {source}")]
+    SubmissionFileError{ span: Span, source: Box<dyn std::error::Error + std::marker::Sync + std::marker::Send> }
+}
+
+fn display_names(names: &[InputFileName]) -> String {
+    names.iter().map(|name| name.0.as_str()).collect::<Vec<_>>().join(", ")
 }
 
 #[derive(Ord, PartialOrd, Eq, PartialEq, Debug, Clone, Hash, Serialize, Deserialize)]
 pub struct InputFileName(pub(crate) String);
 
+impl InputFileName {
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
 impl Display for InputFileName {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
         write!(f, "InputFileName: {}", self.0)
@@ -56,44 +77,80 @@ impl Display for ChallengeDate {
     }
 }
 
+impl ChallengeDate {
+    /// The short id used in urls and on the command line, e.g. `qual2020`
+    pub fn id(&self) -> String {
+        match self {
+            ChallengeDate::Qualification(y) => format!("qual{}", y),
+            ChallengeDate::Final(y) => format!("final{}", y)
+        }
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("Unknown challenge id '{0}', expected something like qual2020 or final2019")]
+pub struct UnknownChallengeId(pub String);
+
+impl std::str::FromStr for ChallengeDate {
+    type Err = UnknownChallengeId;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_year = |year: &str| year.parse::<Year>().map_err(|_| UnknownChallengeId(s.to_owned()));
+        if let Some(year) = s.strip_prefix("qual") {
+            parse_year(year).map(ChallengeDate::Qualification)
+        } else if let Some(year) = s.strip_prefix("final") {
+            parse_year(year).map(ChallengeDate::Final)
+        } else {
+            Err(UnknownChallengeId(s.to_owned()))
+        }
+    }
+}
+
+/// A detailed breakdown of how a submission scored on a single input case
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScoreReport {
+    pub case: InputFileName,
+    pub score: Score,
+    /// Challenge specific statistics, e.g. the number of completed orders
+    pub details: BTreeMap<String, u64>,
+}
+
+pub trait ChallengeScorer: Send + Sync {
+    /// The input cases a submission can be scored against
+    fn input_cases(&self) -> Vec<InputFileName>;
+
+    /// The canonical id of the input case a submission names, accepting the case aliases
+    fn resolve_case(&self, case: &InputFileName) -> Result<InputFileName, ScoringError>;
+
+    /// Parses an input file, failing if it isn't a valid case of this challenge
+    fn validate_input(&self, input: &str) -> Result<(), ScoringError>;
+
+    fn report(&self, submission: &str, case: &InputFileName) -> Result<ScoreReport, ScoringError>;
+
+    fn score(&self, submission: &str, case: &InputFileName) -> Result<Score, ScoringError> {
+        self.report(submission, case).map(|report| report.score)
+    }
+}
+
 pub struct Challenge {
-    pub input_file_names: Vec<InputFileName>,
-    pub score_function: Box<dyn Fn(&str, &InputFileName) -> Result<Score, ScoringError> + 'static + Send + Sync>,
+    pub scorer: Box<dyn ChallengeScorer>,
     pub date: ChallengeDate,
 }
 
+impl Challenge {
+    pub fn input_file_names(&self) -> Vec<InputFileName> {
+        self.scorer.input_cases()
+    }
+}
+
 impl fmt::Debug for Challenge {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        write!(f, "date: {:?}, input_file_names: {:?}", self.date, self.input_file_names)
+        write!(f, "date: {:?}, input_file_names: {:?}", self.date, self.input_file_names())
     }
 }
 
-pub fn get_challenges() -> Vec<Challenge> {
-    vec![
-        Challenge{
-            input_file_names: vec![
-                "a".into(),
-                "b".into(),
-                "c".into(),
-                "d".into(),
-                "e".into(),
-                "f".into()
-            ],
-            score_function: Box::new(crate::qual2020::score),
-            date: ChallengeDate::Qualification(2020)
-        },
-
-
-        Challenge{
-            input_file_names: vec![
-                "example".into(),
-                "busy_day".into(),
-                "mother_of_all_warehouses".into(),
-                "redundancy".into(),
-            ],
-            score_function: Box::new(crate::qual2016::score),
-            date: ChallengeDate::Qualification(2016)
-        }
-    ]
+/// Loads every challenge found in the default data directory, see [`registry::default_data_dir`]
+pub fn get_challenges() -> Result<Vec<Challenge>, RegistryError> {
+    ChallengeRegistry::load(registry::default_data_dir())
+        .map(ChallengeRegistry::into_challenges)
 }
-
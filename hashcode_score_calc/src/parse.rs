@@ -0,0 +1,14 @@
+use std::str::FromStr;
+use crate::{ScoringError, Span};
+
+/// Parses `token`, a slice of `text`, pointing at it when it isn't a number
+pub(crate) fn parse_number<N: FromStr>(text: &str, token: &str) -> Result<N, ScoringError> {
+    token.parse().map_err(|_| ScoringError::ExpectedANumber(Span::at(text, token)))
+}
+
+/// Every whitespace separated number on `line`, a slice of `text`
+pub(crate) fn parse_numbers<N: FromStr>(text: &str, line: &str) -> Result<Vec<N>, ScoringError> {
+    line.split_whitespace()
+        .map(|token| parse_number(text, token))
+        .collect()
+}
@@ -3,7 +3,7 @@ use crate::ScoringError;
 
 use thiserror::Error;
 
-#[derive(Error, Debug, PartialEq, Eq)]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
 pub enum Qual2016ScoringError {
     #[error("Tried to access location row: {row}, col: {col} which is out of bounds")]
     LocationOutOfMap { row: Row, col: Col },
@@ -13,6 +13,8 @@ pub enum Qual2016ScoringError {
     DronePassedWeightLimit { drone_id: DroneID },
     #[error("You're trying to take too much from warehouse number {warehouse_id}")]
     OverTakingWarehouse { warehouse_id: WarehouseID },
+    #[error("Warehouse {warehouse_id} can't hold that many items of product {product_id}")]
+    OverFillingWarehouse { warehouse_id: WarehouseID, product_id: ProductID },
     #[error("You're trying to unload more then you have of product {product_id} from drone number {drone_id}")]
     OverTakingDrone { drone_id: DroneID, product_id: ProductID },
     #[error("Trying to fly to an unknown warehouse {warehouse_id}")]
@@ -37,3 +39,22 @@ impl From<Qual2016ScoringError> for ScoringError {
         ScoringError::ChallengeSpecific(Box::new(e))
     }
 }
+
+/// Why a 2016 input or submission file couldn't be parsed, its position is kept alongside
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Qual2016SyntaxError {
+    #[error("Expected a number")]
+    ExpectedANumber,
+    #[error("The number is too big")]
+    NumberOutOfRange,
+    #[error("Expected a command, one of L, U, D or W")]
+    ExpectedACommand,
+    #[error("Expected '{0}'")]
+    ExpectedChar(char),
+    #[error("Expected {0}")]
+    ExpectedContext(&'static str),
+    #[error("The file ended unexpectedly")]
+    UnexpectedEndOfFile,
+    #[error("Unexpected token ({0:?})")]
+    Unexpected(nom::error::ErrorKind),
+}
@@ -2,7 +2,9 @@ mod parsing;
 mod error;
 
 use error::Qual2016ScoringError;
-use crate::{ScoringError, InputFileName, Score};
+use crate::{ScoringError, InputFileName, Score, ChallengeScorer, ScoreReport};
+use crate::registry::{InputFile, LoadScorer, RegistryError};
+use crate::input_cases::{CaseNames, InputCases};
 
 
 type Row = u16;
@@ -144,8 +146,12 @@ impl Warehouse {
         }
     }
 
-    pub fn insert_product(&mut self, prod_id: ProductID, number_of_products: WarehouseProductInventory) {
-        self.inventory[prod_id as usize] += number_of_products;
+    pub fn insert_product(&mut self, product_id: ProductID, number_of_products: WarehouseProductInventory) -> Result<(), Qual2016ScoringError> {
+        let inv = self.inventory.get_mut(product_id as usize)
+            .ok_or(Qual2016ScoringError::UnknownProduct {product_id})?;
+        *inv = inv.checked_add(number_of_products)
+            .ok_or(Qual2016ScoringError::OverFillingWarehouse {warehouse_id: self.id, product_id})?;
+        Ok(())
     }
 }
 
@@ -160,10 +166,13 @@ pub struct Case {
 }
 
 impl Case {
-    pub fn parse(input: &'static str) -> Result<Self, ScoringError> {
+    pub fn parse(input: &str) -> Result<Self, ScoringError> {
         parsing::parse_input_file(input)
             .map(|(_input, case)| case)
-            .map_err(|e| ScoringError::InputFileError(Box::new(e)))
+            .map_err(|e| {
+                let (span, syntax_error) = parsing::locate_error(input, e);
+                ScoringError::InputFileError { span, source: Box::new(syntax_error) }
+            })
     }
 
     pub fn get_earth_bound(&self) -> Earth {
@@ -204,17 +213,6 @@ impl Earth {
     }
 }
 
-lazy_static!{
-    static ref CASE_EXAMPLE: Case = Case::parse(include_str!("../../assets/2016qual/inputs/example.in")).
-                                        unwrap();
-    static ref CASE_BUSY_DAY: Case = Case::parse(include_str!("../../assets/2016qual/inputs/busy_day.in")).
-                                        unwrap();
-    static ref CASE_MOTHER_OF_ALL_WAREHOUSES: Case = Case::parse(include_str!("../../assets/2016qual/inputs/mother_of_all_warehouses.in")).
-                                        unwrap();
-    static ref CASE_REDUNDANCY: Case = Case::parse(include_str!("../../assets/2016qual/inputs/redundancy.in")).
-                                        unwrap();
-}
-
 #[derive(Debug)]
 struct ExecutedCommand<'drone, 'case> {
     command: Command,
@@ -230,7 +228,7 @@ impl ExecutedCommand<'_, '_> {
     }
 }
 
-use std::collections::{VecDeque, HashMap};
+use std::collections::{VecDeque, HashMap, BTreeMap};
 use itertools::Itertools;
 use serde::export::fmt::Debug;
 
@@ -251,13 +249,15 @@ impl DroneEarthInteraction<'_, '_, '_> {
             return Err(Qual2016ScoringError::DroneNotInWarehouse {drone_id: self.drone.id, warehouse_id});
         }
 
-        if self.drone.room_left()? < (product.weight * amount) {
-            return Err(Qual2016ScoringError::DronePassedWeightLimit { drone_id: self.drone.id });
+        let too_heavy = Qual2016ScoringError::DronePassedWeightLimit { drone_id: self.drone.id };
+        let load_weight = product.weight.checked_mul(amount).ok_or(too_heavy.clone())?;
+        if self.drone.room_left()? < load_weight {
+            return Err(too_heavy);
         }
 
         warehouse.take_out_product(product_id, amount)?;
         let drone_product_slot = self.drone.carrying.entry(product_id).or_insert(0);
-        *drone_product_slot += amount;
+        *drone_product_slot = drone_product_slot.checked_add(amount).ok_or(too_heavy)?;
 
         self.drone.time_for_next_command += 1;
 
@@ -277,7 +277,8 @@ impl DroneEarthInteraction<'_, '_, '_> {
         if *drone_product_slot < amount {
             Err(Qual2016ScoringError::OverTakingDrone { drone_id: self.drone.id, product_id })
         } else {
-            warehouse.insert_product(product_id, amount);
+            warehouse.insert_product(product_id, amount)?;
+            *drone_product_slot -= amount;
 
             self.drone.time_for_next_command += 1;
 
@@ -371,26 +372,27 @@ impl<'case> Drone<'case> {
     }
 
     pub fn fly_to(&mut self, location: Location) {
-        self.time_for_next_command += self.location.flight_time(&location);
+        // Commands after the last turn are never executed, so saturating is as good as the real time
+        self.time_for_next_command = self.time_for_next_command.saturating_add(self.location.flight_time(&location));
         self.location = location;
     }
 
     pub fn carried_weight(&self) -> Result<Weight, Qual2016ScoringError> {
+        let too_heavy = Qual2016ScoringError::DronePassedWeightLimit { drone_id: self.id };
         self.carrying.iter()
-            .map(|(product_id,amount): (&ProductID, &u16) | {
-                self.case.products
+            .try_fold(0 as Weight, |carried, (product_id, amount): (&ProductID, &u16)| {
+                let product = self.case.products
                     .get(*product_id as usize)
-                    .ok_or(Qual2016ScoringError::UnknownProduct {product_id: *product_id})
-                    .map(|product: &Product| product.weight * (*amount))
+                    .ok_or(Qual2016ScoringError::UnknownProduct {product_id: *product_id})?;
+                product.weight.checked_mul(*amount)
+                    .and_then(|weight| carried.checked_add(weight))
+                    .ok_or(too_heavy.clone())
             })
-            .sum::<Result<Weight, _>>()
     }
 
     pub fn room_left(&self) -> Result<Weight, Qual2016ScoringError> {
-        // Should always have room left (or zero), else this method will underflow
-        let weight_limit = self.case.max_payload;
-        let carried_weight = self.carried_weight()?;
-        Ok(weight_limit - carried_weight)
+        self.case.max_payload.checked_sub(self.carried_weight()?)
+            .ok_or(Qual2016ScoringError::DronePassedWeightLimit { drone_id: self.id })
     }
 
     pub fn on_earth<'drone, 'earth>(&'drone mut self, earth: &'earth mut Earth) -> DroneEarthInteraction<'case, 'drone, 'earth> {
@@ -412,7 +414,7 @@ impl<'case> Drone<'case> {
     }
 
     pub fn wait(&mut self, turns_to_wait: Turn) {
-        self.time_for_next_command += turns_to_wait;
+        self.time_for_next_command = self.time_for_next_command.saturating_add(turns_to_wait);
     }
 }
 
@@ -446,22 +448,58 @@ pub enum Command {
     }
 }
 
-pub fn score(submission: &str, case: &InputFileName) -> Result<Score, ScoringError> {
-    let case: &Case = match case {
-        InputFileName(ref s) if s.starts_with("example") => &*CASE_EXAMPLE,
-        InputFileName(ref s) if s.starts_with("busy_day") => &*CASE_BUSY_DAY,
-        InputFileName(ref s) if s.starts_with("mother_of_all_warehouses") => &*CASE_MOTHER_OF_ALL_WAREHOUSES,
-        InputFileName(ref s) if s.starts_with("redundancy") => &*CASE_REDUNDANCY,
-        input_case @ InputFileName(_) => return Err(ScoringError::UnknownInputCase(input_case.clone()))
-    };
+const CASE_NAMES: &[CaseNames] = &[
+    CaseNames { id: "example", aliases: &["example.in"] },
+    CaseNames { id: "busy_day", aliases: &["busy_day.in"] },
+    CaseNames { id: "mother_of_all_warehouses", aliases: &["mother_of_all_warehouses.in"] },
+    CaseNames { id: "redundancy", aliases: &["redundancy.in"] },
+];
+
+/// Flies the drone commands of a submission over a 2016 case, scoring each order by how early it's completed
+pub struct Qual2016 {
+    cases: InputCases<Case>
+}
+
+impl LoadScorer for Qual2016 {
+    fn load(input_files: Vec<InputFile>) -> Result<Self, RegistryError> {
+        InputCases::load(input_files, CASE_NAMES, Case::parse)
+            .map(|cases| Self { cases })
+    }
+}
+
+impl ChallengeScorer for Qual2016 {
+    fn input_cases(&self) -> Vec<InputFileName> {
+        self.cases.ids()
+    }
+
+    fn resolve_case(&self, case: &InputFileName) -> Result<InputFileName, ScoringError> {
+        self.cases.resolve(case).cloned()
+    }
+
+    fn validate_input(&self, input: &str) -> Result<(), ScoringError> {
+        Case::parse(input).map(|_case| ())
+    }
+
+    fn report(&self, submission: &str, case_name: &InputFileName) -> Result<ScoreReport, ScoringError> {
+        let (case_id, case) = self.cases.get(case_name)?;
+        report(submission, case_id, case)
+    }
+}
+
+fn report(submission: &str, case_name: &InputFileName, case: &Case) -> Result<ScoreReport, ScoringError> {
 
+    let first_warehouse = case.warehouses.first()
+        .ok_or(Qual2016ScoringError::UnknownWarehouse {warehouse_id: 0})?;
     let mut drones: Vec<Drone> = (0..case.number_of_drones).into_iter()
-        .map(|i| Drone::new(i, case.warehouses[0].location, case))
+        .map(|i| Drone::new(i, first_warehouse.location, case))
         .collect();
 
     let commands: Vec<Command> = parsing::parse_submission(submission)
         .map(|(_submission, commands)| commands)
-        .map_err(|e| ScoringError::SubmissionFileError(Box::new(e.to_owned())))?;
+        .map_err(|e| {
+            let (span, syntax_error) = parsing::locate_error(submission, e);
+            ScoringError::SubmissionFileError { span, source: Box::new(syntax_error) }
+        })?;
 
     // The right place to check that would be the constructor or the parser, but...
     for command in &commands {
@@ -475,6 +513,7 @@ pub fn score(submission: &str, case: &InputFileName) -> Result<Score, ScoringErr
     }
 
     let mut earth = case.get_earth_bound();
+    let number_of_commands = commands.len() as u64;
 
     for command in commands {
         let drone_id = command.get_drone_id();
@@ -485,6 +524,7 @@ pub fn score(submission: &str, case: &InputFileName) -> Result<Score, ScoringErr
 
 
     let mut submission_score: Score = 0;
+    let mut completed_orders = 0;
     for t in 0..case.total_turns{
         let commands_to_execute_unload_first = {
             let commands_to_execute = drones.iter_mut()
@@ -522,6 +562,7 @@ pub fn score(submission: &str, case: &InputFileName) -> Result<Score, ScoringErr
                         let should_round_up = (((case.total_turns as Score - t as Score) * 100) % case.total_turns as Score) != 0;
 
                         submission_score += added_score + if should_round_up {1} else {0};
+                        completed_orders += 1;
                     }
                 },
                 Command::Wait { turns, ..} => {
@@ -531,5 +572,10 @@ pub fn score(submission: &str, case: &InputFileName) -> Result<Score, ScoringErr
         }
 
     }
-    Ok(submission_score)
+
+    let mut details = BTreeMap::new();
+    details.insert("commands".to_owned(), number_of_commands);
+    details.insert("completed_orders".to_owned(), completed_orders);
+
+    Ok(ScoreReport { case: case_name.clone(), score: submission_score, details })
 }
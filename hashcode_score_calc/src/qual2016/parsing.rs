@@ -1,4 +1,5 @@
 use nom::{IResult, combinator::map_res, character::complete::digit1};
+use nom::error::{ErrorKind, VerboseError, VerboseErrorKind};
 use nom::sequence::tuple;
 use nom::multi::many_m_n;
 use nom::character::complete::multispace0;
@@ -7,21 +8,50 @@ use nom::sequence::terminated;
 use super::{Row, Col, DroneID, Turn, Weight, ProductID, Product,
             Warehouse, WarehouseID, Location, WarehouseProductInventory,
             Order, OrderID, MapSize};
+use super::error::Qual2016SyntaxError;
+use crate::Span;
 use std::str::FromStr;
 
-fn decimal_number<N: FromStr>(input: &str) -> IResult<&str, N> {
+type ParseResult<'a, O> = IResult<&'a str, O, VerboseError<&'a str>>;
+
+/// The position and cause of the innermost failing parser, which is the most precise one
+pub(crate) fn locate_error(text: &str, e: nom::Err<VerboseError<&str>>) -> (Span, Qual2016SyntaxError) {
+    let innermost = match e {
+        nom::Err::Error(e) | nom::Err::Failure(e) => e.errors.into_iter().next(),
+        nom::Err::Incomplete(_) => None,
+    };
+
+    match innermost {
+        Some((rest, kind)) => {
+            let span = Span::at(text, rest);
+            let syntax_error = match kind {
+                _ if span.token.is_empty() => Qual2016SyntaxError::UnexpectedEndOfFile,
+                VerboseErrorKind::Nom(ErrorKind::Digit) => Qual2016SyntaxError::ExpectedANumber,
+                VerboseErrorKind::Nom(ErrorKind::MapRes) => Qual2016SyntaxError::NumberOutOfRange,
+                VerboseErrorKind::Nom(ErrorKind::OneOf) => Qual2016SyntaxError::ExpectedACommand,
+                VerboseErrorKind::Nom(kind) => Qual2016SyntaxError::Unexpected(kind),
+                VerboseErrorKind::Char(c) => Qual2016SyntaxError::ExpectedChar(c),
+                VerboseErrorKind::Context(context) => Qual2016SyntaxError::ExpectedContext(context),
+            };
+            (span, syntax_error)
+        },
+        None => (Span::end_of(text), Qual2016SyntaxError::UnexpectedEndOfFile),
+    }
+}
+
+fn decimal_number<N: FromStr>(input: &str) -> ParseResult<'_, N> {
     map_res(digit1, |s: &str| s.parse::<N>())(input)
 }
 
-fn decimal_number_ms<N: FromStr>(input: &str) -> IResult<&str, N> {
+fn decimal_number_ms<N: FromStr>(input: &str) -> ParseResult<'_, N> {
     terminated(decimal_number, multispace0)(input)
 }
 
-fn first_line(input: &str) -> IResult<&str, (Row, Col, DroneID, Turn, Weight)> {
+fn first_line(input: &str) -> ParseResult<'_, (Row, Col, DroneID, Turn, Weight)> {
     tuple((decimal_number_ms, decimal_number_ms, decimal_number_ms, decimal_number_ms, decimal_number_ms))(input)
 }
 
-fn products(input: &str) -> IResult<&str, Vec<Product>> {
+fn products(input: &str) -> ParseResult<'_, Vec<Product>> {
     use std::convert::TryFrom;
     let (input, number_of_products) = decimal_number_ms::<ProductID>(input)?;
     let (input, weights) = many_m_n(number_of_products as usize,
@@ -35,13 +65,13 @@ fn products(input: &str) -> IResult<&str, Vec<Product>> {
     ))
 }
 
-fn location(input: &str) -> IResult<&str, Location> {
+fn location(input: &str) -> ParseResult<'_, Location> {
     let (input, (r, c)): (&str, (Row, Col)) = tuple((decimal_number_ms, decimal_number_ms))(input)?;
     Ok((input, Location{ row: r, col: c }))
 }
 
-fn inventory_of_size(number_of_products: ProductID) -> impl Fn(&str) -> IResult<&str, Vec<WarehouseProductInventory>> {
-    move |input :&str| -> IResult<&str, Vec<WarehouseProductInventory>> {
+fn inventory_of_size(number_of_products: ProductID) -> impl Fn(&str) -> ParseResult<'_, Vec<WarehouseProductInventory>> {
+    move |input :&str| -> ParseResult<'_, Vec<WarehouseProductInventory>> {
         many_m_n(number_of_products as usize,
                  number_of_products as usize,
                  decimal_number_ms::<WarehouseProductInventory>)(input)
@@ -49,9 +79,9 @@ fn inventory_of_size(number_of_products: ProductID) -> impl Fn(&str) -> IResult<
 
 }
 
-fn warehouses_with_inventory_size(inventory_size: ProductID) -> impl Fn(&str) -> IResult<&str, Vec<Warehouse>> {
+fn warehouses_with_inventory_size(inventory_size: ProductID) -> impl Fn(&str) -> ParseResult<'_, Vec<Warehouse>> {
     use std::convert::TryFrom;
-    move |input :&str| -> IResult<&str, Vec<Warehouse>> {
+    move |input :&str| -> ParseResult<'_, Vec<Warehouse>> {
         let (input, number_of_warehouses) = decimal_number_ms::<ProductID>(input)?;
         let (input, locations) = many_m_n(number_of_warehouses as usize,
                                         number_of_warehouses as usize,
@@ -73,7 +103,7 @@ fn warehouses_with_inventory_size(inventory_size: ProductID) -> impl Fn(&str) ->
     }
 }
 
-fn one_order_loc_and_prods(input: &str) -> IResult<&str, (Location, Vec<ProductID>)> {
+fn one_order_loc_and_prods(input: &str) -> ParseResult<'_, (Location, Vec<ProductID>)> {
     let (input, ord_location) = location(input)?;
     let (input, number_of_items) = decimal_number_ms::<ProductID>(input)?;
     let (input, products) = many_m_n(number_of_items as usize,
@@ -83,7 +113,7 @@ fn one_order_loc_and_prods(input: &str) -> IResult<&str, (Location, Vec<ProductI
     Ok((input, (ord_location, products)))
 }
 
-fn orders(input: &str) -> IResult<&str, Vec<Order>> {
+fn orders(input: &str) -> ParseResult<'_, Vec<Order>> {
     let (input, number_of_orders) = decimal_number_ms::<OrderID>(input)?;
     let (input, locs_and_prods) = many_m_n(number_of_orders as usize,
                                    number_of_orders as usize,
@@ -101,7 +131,7 @@ fn orders(input: &str) -> IResult<&str, Vec<Order>> {
 use super::Case;
 use std::convert::TryInto;
 
-pub(crate) fn parse_input_file(input: &str) -> IResult<&str, Case> {
+pub(crate) fn parse_input_file(input: &str) -> ParseResult<'_, Case> {
     let (input, (rows, cols, drones, turns, max_payload)) = first_line(input)?;
     let (input, case_products) = products(input)?;
     let (input, case_warehouses) = warehouses_with_inventory_size(case_products.len().try_into().unwrap())
@@ -121,7 +151,7 @@ pub(crate) fn parse_input_file(input: &str) -> IResult<&str, Case> {
 
 use nom::character::complete::one_of;
 
-fn one_command(input: &str) -> IResult<&str, Command> {
+fn one_command(input: &str) -> ParseResult<'_, Command> {
     let (input, drone_id) = decimal_number_ms::<DroneID>(input)?;
     let (input, command_type) = terminated(one_of("LUDW"), multispace0)(input)?;
     match command_type {
@@ -180,18 +210,23 @@ fn one_command(input: &str) -> IResult<&str, Command> {
 use super::Command;
 use super::CommandNumber;
 
-pub fn parse_submission(input: &str) -> IResult<&str, Vec<Command>> {
+pub fn parse_submission(input: &str) -> ParseResult<'_, Vec<Command>> {
     let (input, number_of_commands) = decimal_number_ms::<CommandNumber>(input)?;
-    many_m_n(number_of_commands as usize,
-             number_of_commands as usize,
-             one_command)(input)
+    // The count comes from the submission, so no room is reserved for the commands up front
+    let (input, commands) = many_m_n(0, number_of_commands as usize, one_command)(input)?;
+    if (commands.len() as CommandNumber) < number_of_commands {
+        // Fails on the command that stopped the parsing
+        one_command(input)?;
+    }
+    Ok((input, commands))
 }
 
 #[cfg(test)]
 mod tests {
     use super::{decimal_number, first_line, products, warehouses_with_inventory_size,
-                orders, Location};
+                orders, parse_submission, locate_error, Location};
     use crate::qual2016::Row;
+    use crate::qual2016::error::Qual2016SyntaxError;
 
     #[test]
     fn decimal_number_works() {
@@ -234,4 +269,21 @@ mod tests {
         assert_eq!(ord[1].products, vec![5]);
         assert_eq!(ord[1].location, Location{row: 3, col: 3});
     }
+
+    #[test]
+    fn huge_command_count_is_a_syntax_error() {
+        let submission = "18446744073709551615\n0 W 1\n";
+        let e = parse_submission(submission).expect_err("should fail");
+        let (span, syntax_error) = locate_error(submission, e);
+        assert_eq!(syntax_error, Qual2016SyntaxError::UnexpectedEndOfFile);
+        assert_eq!(span.line, 3);
+    }
+
+    #[test]
+    fn missing_command_is_located() {
+        let submission = "2\n0 W 1\n0 X 1\n";
+        let (span, syntax_error) = locate_error(submission, parse_submission(submission).expect_err("should fail"));
+        assert_eq!(syntax_error, Qual2016SyntaxError::ExpectedACommand);
+        assert_eq!((span.line, span.column), (3, 3));
+    }
 }
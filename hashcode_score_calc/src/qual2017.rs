@@ -0,0 +1,297 @@
+use thiserror::Error;
+use std::collections::{BTreeMap, HashSet};
+use crate::{ScoringError, InputFileName, ChallengeScorer, ScoreReport, Span};
+use crate::registry::{InputFile, LoadScorer, RegistryError};
+use crate::input_cases::{CaseNames, InputCases};
+use crate::parse::{parse_number, parse_numbers};
+
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Qual2017ScoringError {
+    #[error("Missing parameter on input file at {span}")]
+    MissingParameterOnInputFile{span: Span},
+    #[error("There should be a size for each of the {videos} videos, at {span}")]
+    WrongNumberOfVideoSizes{videos: usize, span: Span},
+    #[error("Expected two numbers at {span}")]
+    WrongLineFormat{span: Span},
+    #[error("There is a different number of cache servers than specified at {span}")]
+    WrongNumberOfCacheServers{span: Span},
+    #[error("Cache server {cache_id} doesn't exist in this case at {span}")]
+    NonExistCacheServer{cache_id: CacheID, span: Span},
+    #[error("Cache server {cache_id} is described more than once at {span}")]
+    CacheServerDescribedTwice{cache_id: CacheID, span: Span},
+    #[error("Video {video_id} doesn't exist in this case at {span}")]
+    NonExistVideo{video_id: VideoID, span: Span},
+    #[error("Video {video_id} is put twice in the same cache server at {span}")]
+    VideoCachedTwice{video_id: VideoID, span: Span},
+    #[error("Cache server {cache_id} holds {size}MB of videos, more than its {capacity}MB capacity")]
+    CacheServerOverCapacity{cache_id: CacheID, size: u64, capacity: u64},
+}
+
+impl From<Qual2017ScoringError> for ScoringError {
+    fn from(e: Qual2017ScoringError) -> Self {
+        ScoringError::ChallengeSpecific(Box::new(e))
+    }
+}
+
+
+type VideoID = u32;
+type CacheID = u32;
+type EndpointID = u32;
+/// In milliseconds
+type Latency = u64;
+
+#[derive(Debug)]
+struct Endpoint {
+    datacenter_latency: Latency,
+    caches: Vec<(CacheID, Latency)>,
+}
+
+#[derive(Debug)]
+struct RequestDescription {
+    video: VideoID,
+    endpoint: EndpointID,
+    requests: u64,
+}
+
+#[derive(Debug)]
+struct Case {
+    video_sizes: Vec<u64>,
+    endpoints: Vec<Endpoint>,
+    request_descriptions: Vec<RequestDescription>,
+    cache_servers: usize,
+    capacity: u64,
+}
+
+impl Case {
+    fn parse(input: &str) -> Result<Self, ScoringError> {
+        use crate::ScoringError::*;
+        use Qual2017ScoringError::*;
+
+        let mut lines = input.lines();
+        let missing_line = || MissingLine(Span::end_of(input));
+
+        let first_line = lines.next().ok_or_else(missing_line)?;
+        let missing_parameter = || MissingParameterOnInputFile{span: Span::after(input, first_line)};
+        let mut first_line_tokens = first_line.split_whitespace();
+        let videos = parse_number::<usize>(input, first_line_tokens.next().ok_or_else(missing_parameter)?)?;
+        let number_of_endpoints = parse_number::<usize>(input, first_line_tokens.next().ok_or_else(missing_parameter)?)?;
+        let number_of_request_descriptions = parse_number::<usize>(input, first_line_tokens.next().ok_or_else(missing_parameter)?)?;
+        let cache_servers = parse_number(input, first_line_tokens.next().ok_or_else(missing_parameter)?)?;
+        let capacity = parse_number(input, first_line_tokens.next().ok_or_else(missing_parameter)?)?;
+
+        let sizes_line = lines.next().ok_or_else(missing_line)?;
+        let video_sizes = parse_numbers::<u64>(input, sizes_line)?;
+        if video_sizes.len() != videos {
+            return Err(WrongNumberOfVideoSizes{videos, span: Span::at(input, sizes_line)}.into());
+        }
+
+        let pair = |line: &str| -> Result<(u64, u64), ScoringError> {
+            match parse_numbers::<u64>(input, line)?.as_slice() {
+                &[first, second] => Ok((first, second)),
+                _ => Err(WrongLineFormat{span: Span::at(input, line)}.into()),
+            }
+        };
+
+        let mut endpoints = Vec::with_capacity(number_of_endpoints);
+        for _endpoint_id in 0..number_of_endpoints {
+            let (datacenter_latency, number_of_caches) = pair(lines.next().ok_or_else(missing_line)?)?;
+            let caches = (0..number_of_caches)
+                .map(|_| {
+                    let (cache_id, latency) = pair(lines.next().ok_or_else(missing_line)?)?;
+                    Ok((cache_id as CacheID, latency))
+                })
+                .collect::<Result<Vec<_>, ScoringError>>()?;
+            endpoints.push(Endpoint { datacenter_latency, caches });
+        }
+
+        let mut request_descriptions = Vec::with_capacity(number_of_request_descriptions);
+        for _request_id in 0..number_of_request_descriptions {
+            let line = lines.next().ok_or_else(missing_line)?;
+            let numbers = parse_numbers::<u64>(input, line)?;
+            if numbers.len() != 3 {
+                return Err(WrongLineFormat{span: Span::at(input, line)}.into());
+            }
+            request_descriptions.push(RequestDescription {
+                video: numbers[0] as VideoID,
+                endpoint: numbers[1] as EndpointID,
+                requests: numbers[2],
+            });
+        }
+
+        Ok(Case { video_sizes, endpoints, request_descriptions, cache_servers, capacity })
+    }
+}
+
+const CASE_NAMES: &[CaseNames] = &[
+    CaseNames { id: "example", aliases: &["example.in"] },
+    CaseNames { id: "me_at_the_zoo", aliases: &["me_at_the_zoo.in"] },
+    CaseNames { id: "videos_worth_spreading", aliases: &["videos_worth_spreading.in"] },
+    CaseNames { id: "trending_today", aliases: &["trending_today.in"] },
+    CaseNames { id: "kittens", aliases: &["kittens.in"] },
+];
+
+/// Scores the videos put on each cache server by the latency they save per request, in microseconds
+pub struct Qual2017 {
+    cases: InputCases<Case>
+}
+
+impl LoadScorer for Qual2017 {
+    fn load(input_files: Vec<InputFile>) -> Result<Self, RegistryError> {
+        InputCases::load(input_files, CASE_NAMES, Case::parse)
+            .map(|cases| Self { cases })
+    }
+}
+
+impl ChallengeScorer for Qual2017 {
+    fn input_cases(&self) -> Vec<InputFileName> {
+        self.cases.ids()
+    }
+
+    fn resolve_case(&self, case: &InputFileName) -> Result<InputFileName, ScoringError> {
+        self.cases.resolve(case).cloned()
+    }
+
+    fn validate_input(&self, input: &str) -> Result<(), ScoringError> {
+        Case::parse(input).map(|_case| ())
+    }
+
+    fn report(&self, submission: &str, case_name: &InputFileName) -> Result<ScoreReport, ScoringError> {
+        let (case_id, case) = self.cases.get(case_name)?;
+        report(submission, case_id, case)
+    }
+}
+
+/// The videos held by each cache server
+fn parse_submission(submission: &str, case: &Case) -> Result<Vec<HashSet<VideoID>>, ScoringError> {
+    use Qual2017ScoringError::*;
+
+    let mut lines = submission.lines().filter(|line| !line.trim().is_empty());
+    let first_line = lines.next().ok_or_else(|| ScoringError::MissingLine(Span::end_of(submission)))?;
+    let number_of_cache_servers = parse_number::<usize>(submission, first_line.trim())?;
+
+    let mut cached = vec![None; case.cache_servers];
+    let mut described = 0;
+    for line in lines {
+        let mut tokens = line.split_whitespace();
+        let cache_id_str = tokens.next().expect("the line isn't blank");
+        let cache_id = parse_number::<CacheID>(submission, cache_id_str)?;
+        let videos = match cached.get_mut(cache_id as usize) {
+            None => return Err(NonExistCacheServer{cache_id, span: Span::at(submission, cache_id_str)}.into()),
+            Some(Some(_)) => return Err(CacheServerDescribedTwice{cache_id, span: Span::at(submission, cache_id_str)}.into()),
+            Some(videos) => videos.get_or_insert_with(HashSet::new),
+        };
+
+        let mut size = 0;
+        for video_id_str in tokens {
+            let video_id = parse_number::<VideoID>(submission, video_id_str)?;
+            let video_size = case.video_sizes.get(video_id as usize)
+                .ok_or_else(|| NonExistVideo{video_id, span: Span::at(submission, video_id_str)})?;
+            if !videos.insert(video_id) {
+                return Err(VideoCachedTwice{video_id, span: Span::at(submission, video_id_str)}.into());
+            }
+            size += video_size;
+        }
+
+        if size > case.capacity {
+            return Err(CacheServerOverCapacity{cache_id, size, capacity: case.capacity}.into());
+        }
+        described += 1;
+    }
+
+    if described != number_of_cache_servers {
+        return Err(WrongNumberOfCacheServers{span: Span::at(submission, first_line)}.into());
+    }
+
+    Ok(cached.into_iter().map(Option::unwrap_or_default).collect())
+}
+
+fn report(submission: &str, case_name: &InputFileName, case: &Case) -> Result<ScoreReport, ScoringError> {
+    let cached = parse_submission(submission, case)?;
+
+    let mut saved_latency = 0;
+    let mut total_requests = 0;
+    let mut requests_from_cache = 0;
+    for description in &case.request_descriptions {
+        total_requests += description.requests;
+
+        // A description of a missing endpoint can't be served from a cache server
+        let endpoint = match case.endpoints.get(description.endpoint as usize) {
+            Some(endpoint) => endpoint,
+            None => continue,
+        };
+        let best_latency = endpoint.caches.iter()
+            .filter(|(cache_id, _latency)| match cached.get(*cache_id as usize) {
+                Some(videos) => videos.contains(&description.video),
+                None => false,
+            })
+            .map(|&(_cache_id, latency)| latency)
+            .min();
+
+        if let Some(latency) = best_latency {
+            if latency < endpoint.datacenter_latency {
+                saved_latency += (endpoint.datacenter_latency - latency) * description.requests;
+                requests_from_cache += description.requests;
+            }
+        }
+    }
+
+    // The average saved latency per request, in microseconds and rounded down
+    let score = (saved_latency * 1000).checked_div(total_requests).unwrap_or(0);
+
+    let mut details = BTreeMap::new();
+    details.insert("cache_servers_used".to_owned(), cached.iter().filter(|videos| !videos.is_empty()).count() as u64);
+    details.insert("requests_from_cache".to_owned(), requests_from_cache);
+
+    Ok(ScoreReport { case: case_name.clone(), score, details })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::qual2017::{Case, report};
+    use crate::ScoringError;
+
+    const EXAMPLE: &str = "5 2 4 3 100\n50 50 80 30 110\n1000 3\n0 100\n2 200\n1 300\n500 0\n3 0 1500\n0 1 1000\n4 0 500\n1 0 1000\n";
+
+    fn challenge_error(submission: &str) -> String {
+        let case = Case::parse(EXAMPLE).expect("should parse");
+        match report(submission, &"example".into(), &case) {
+            Err(ScoringError::ChallengeSpecific(e)) => e.to_string(),
+            other => panic!("Expected a challenge specific error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_example_case() {
+        let case = Case::parse(EXAMPLE).expect("should parse");
+        assert_eq!((case.video_sizes.len(), case.cache_servers, case.capacity), (5, 3, 100));
+        assert_eq!(case.endpoints[0].caches, vec![(0, 100), (2, 200), (1, 300)]);
+        assert!(case.endpoints[1].caches.is_empty());
+        assert_eq!(case.request_descriptions.len(), 4);
+    }
+
+    #[test]
+    fn saved_latency_is_averaged_over_all_requests() {
+        let case = Case::parse(EXAMPLE).expect("should parse");
+        // Saves 900ms on the 1000 requests of video 1, out of 4000 requests
+        let report = report("1\n0 1\n", &"example".into(), &case).expect("should score");
+        assert_eq!(report.score, 225000);
+        assert_eq!(report.details["requests_from_cache"], 1000);
+    }
+
+    #[test]
+    fn score_is_rounded_down() {
+        // 1ms saved on one of 3 requests
+        let case = Case::parse("1 2 2 1 100\n10\n1000 1\n0 999\n1 0\n0 0 1\n0 1 2\n").expect("should parse");
+        assert_eq!(report("1\n0 0\n", &"example".into(), &case).expect("should score").score, 333);
+    }
+
+    #[test]
+    fn cache_servers_have_a_capacity() {
+        assert_eq!(challenge_error("1\n0 4\n"), "Cache server 0 holds 110MB of videos, more than its 100MB capacity");
+        assert_eq!(challenge_error("2\n0 1\n0 2\n"), "Cache server 0 is described more than once at line 3, column 1 ('0')");
+        assert_eq!(challenge_error("1\n3 1\n"), "Cache server 3 doesn't exist in this case at line 2, column 1 ('3')");
+        assert_eq!(challenge_error("1\n0 5\n"), "Video 5 doesn't exist in this case at line 2, column 3 ('5')");
+        assert_eq!(challenge_error("1\n0 1 1\n"), "Video 1 is put twice in the same cache server at line 2, column 5 ('1')");
+    }
+}
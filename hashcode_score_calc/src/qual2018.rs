@@ -0,0 +1,282 @@
+use thiserror::Error;
+use std::collections::BTreeMap;
+use crate::{ScoringError, InputFileName, ChallengeScorer, ScoreReport, Span};
+use crate::registry::{InputFile, LoadScorer, RegistryError};
+use crate::input_cases::{CaseNames, InputCases};
+use crate::parse::{parse_number, parse_numbers};
+
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Qual2018ScoringError {
+    #[error("Missing parameter on input file at {span}")]
+    MissingParameterOnInputFile{span: Span},
+    #[error("A ride should have a start, a finish, an earliest start and a latest finish, at {span}")]
+    WrongRideFormat{span: Span},
+    #[error("There should be a line for each of the {vehicles} vehicles, found {lines}")]
+    WrongNumberOfVehicles{vehicles: usize, lines: usize},
+    #[error("Expected the number of rides of the vehicle at {span}")]
+    MissingNumberOfRides{span: Span},
+    #[error("There is a different number of rides than specified at {span}")]
+    WrongNumberOfRides{span: Span},
+    #[error("A vehicle can't take more rides than the case has, at {span}")]
+    TooManyRides{span: Span},
+    #[error("Ride {ride_id} doesn't exist in this case at {span}")]
+    NonExistRide{ride_id: RideID, span: Span},
+    #[error("Ride {ride_id} is assigned more than once at {span}")]
+    RideAssignedTwice{ride_id: RideID, span: Span},
+}
+
+impl From<Qual2018ScoringError> for ScoringError {
+    fn from(e: Qual2018ScoringError) -> Self {
+        ScoringError::ChallengeSpecific(Box::new(e))
+    }
+}
+
+
+type RideID = u32;
+type Step = u64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Intersection {
+    row: u64,
+    column: u64,
+}
+
+impl Intersection {
+    fn distance_to(self, other: Intersection) -> u64 {
+        let distance = |a: u64, b: u64| a.max(b) - a.min(b);
+        distance(self.row, other.row) + distance(self.column, other.column)
+    }
+}
+
+#[derive(Debug)]
+struct Ride {
+    start: Intersection,
+    finish: Intersection,
+    earliest_start: Step,
+    latest_finish: Step,
+}
+
+impl Ride {
+    fn distance(&self) -> u64 {
+        self.start.distance_to(self.finish)
+    }
+}
+
+#[derive(Debug)]
+struct Case {
+    vehicles: usize,
+    rides: Vec<Ride>,
+    /// Earned for every ride that starts exactly at its earliest start
+    bonus: u64,
+    steps: Step,
+}
+
+impl Case {
+    fn parse(input: &str) -> Result<Self, ScoringError> {
+        use crate::ScoringError::*;
+        use Qual2018ScoringError::*;
+
+        let mut lines = input.lines();
+        let missing_line = || MissingLine(Span::end_of(input));
+
+        let first_line = lines.next().ok_or_else(missing_line)?;
+        let missing_parameter = || MissingParameterOnInputFile{span: Span::after(input, first_line)};
+        let mut first_line_tokens = first_line.split_whitespace();
+        let _rows = parse_number::<u64>(input, first_line_tokens.next().ok_or_else(missing_parameter)?)?;
+        let _columns = parse_number::<u64>(input, first_line_tokens.next().ok_or_else(missing_parameter)?)?;
+        let vehicles = parse_number(input, first_line_tokens.next().ok_or_else(missing_parameter)?)?;
+        let number_of_rides = parse_number::<usize>(input, first_line_tokens.next().ok_or_else(missing_parameter)?)?;
+        let bonus = parse_number(input, first_line_tokens.next().ok_or_else(missing_parameter)?)?;
+        let steps = parse_number(input, first_line_tokens.next().ok_or_else(missing_parameter)?)?;
+
+        let mut rides = Vec::with_capacity(number_of_rides);
+        for _ride_id in 0..number_of_rides {
+            let line = lines.next().ok_or_else(missing_line)?;
+            let numbers = parse_numbers::<u64>(input, line)?;
+            if numbers.len() != 6 {
+                return Err(WrongRideFormat{span: Span::at(input, line)}.into());
+            }
+
+            rides.push(Ride {
+                start: Intersection { row: numbers[0], column: numbers[1] },
+                finish: Intersection { row: numbers[2], column: numbers[3] },
+                earliest_start: numbers[4],
+                latest_finish: numbers[5],
+            });
+        }
+
+        Ok(Case { vehicles, rides, bonus, steps })
+    }
+}
+
+const CASE_NAMES: &[CaseNames] = &[
+    CaseNames { id: "a", aliases: &["a_example", "a_example.in"] },
+    CaseNames { id: "b", aliases: &["b_should_be_easy", "b_should_be_easy.in"] },
+    CaseNames { id: "c", aliases: &["c_no_hurry", "c_no_hurry.in"] },
+    CaseNames { id: "d", aliases: &["d_metropolis", "d_metropolis.in"] },
+    CaseNames { id: "e", aliases: &["e_high_bonus", "e_high_bonus.in"] },
+];
+
+/// Drives each vehicle's rides in order, scoring the rides finished in time and a bonus for those started on time
+pub struct Qual2018 {
+    cases: InputCases<Case>
+}
+
+impl LoadScorer for Qual2018 {
+    fn load(input_files: Vec<InputFile>) -> Result<Self, RegistryError> {
+        InputCases::load(input_files, CASE_NAMES, Case::parse)
+            .map(|cases| Self { cases })
+    }
+}
+
+impl ChallengeScorer for Qual2018 {
+    fn input_cases(&self) -> Vec<InputFileName> {
+        self.cases.ids()
+    }
+
+    fn resolve_case(&self, case: &InputFileName) -> Result<InputFileName, ScoringError> {
+        self.cases.resolve(case).cloned()
+    }
+
+    fn validate_input(&self, input: &str) -> Result<(), ScoringError> {
+        Case::parse(input).map(|_case| ())
+    }
+
+    fn report(&self, submission: &str, case_name: &InputFileName) -> Result<ScoreReport, ScoringError> {
+        let (case_id, case) = self.cases.get(case_name)?;
+        report(submission, case_id, case)
+    }
+}
+
+/// The rides of each vehicle, in the order it takes them
+fn parse_submission(submission: &str, case: &Case) -> Result<Vec<Vec<RideID>>, ScoringError> {
+    use Qual2018ScoringError::*;
+
+    let lines: Vec<&str> = submission.lines().filter(|line| !line.trim().is_empty()).collect();
+    if lines.len() != case.vehicles {
+        return Err(WrongNumberOfVehicles{vehicles: case.vehicles, lines: lines.len()}.into());
+    }
+
+    let mut assigned = vec![false; case.rides.len()];
+    let mut vehicles = Vec::with_capacity(lines.len());
+    for line in lines {
+        let mut tokens = line.split_whitespace();
+        let number_of_rides_str = tokens.next().ok_or_else(|| MissingNumberOfRides{span: Span::at(submission, line)})?;
+        let number_of_rides = parse_number::<usize>(submission, number_of_rides_str)?;
+        if number_of_rides > case.rides.len() {
+            return Err(TooManyRides{span: Span::at(submission, number_of_rides_str)}.into());
+        }
+
+        let mut rides = Vec::with_capacity(number_of_rides);
+        for ride_id_str in tokens {
+            let ride_id = parse_number::<RideID>(submission, ride_id_str)?;
+            let span = || Span::at(submission, ride_id_str);
+            match assigned.get_mut(ride_id as usize) {
+                None => return Err(NonExistRide{ride_id, span: span()}.into()),
+                Some(true) => return Err(RideAssignedTwice{ride_id, span: span()}.into()),
+                Some(is_assigned) => *is_assigned = true,
+            }
+            rides.push(ride_id);
+        }
+
+        if rides.len() != number_of_rides {
+            return Err(WrongNumberOfRides{span: Span::at(submission, number_of_rides_str)}.into());
+        }
+        vehicles.push(rides);
+    }
+
+    Ok(vehicles)
+}
+
+fn report(submission: &str, case_name: &InputFileName, case: &Case) -> Result<ScoreReport, ScoringError> {
+    let vehicles = parse_submission(submission, case)?;
+
+    let mut score = 0;
+    let mut rides_on_time = 0;
+    let mut bonuses = 0;
+    for rides in &vehicles {
+        let mut position = Intersection { row: 0, column: 0 };
+        let mut step: Step = 0;
+
+        for &ride_id in rides {
+            let ride = &case.rides[ride_id as usize];
+            let arrival = step.saturating_add(position.distance_to(ride.start));
+            let start = arrival.max(ride.earliest_start);
+            let finish = start.saturating_add(ride.distance());
+
+            // A late ride is still driven, but earns nothing
+            if finish <= ride.latest_finish && finish <= case.steps {
+                score += ride.distance();
+                rides_on_time += 1;
+                if start == ride.earliest_start {
+                    score += case.bonus;
+                    bonuses += 1;
+                }
+            }
+
+            position = ride.finish;
+            step = finish;
+        }
+    }
+
+    let mut details = BTreeMap::new();
+    details.insert("rides_on_time".to_owned(), rides_on_time);
+    details.insert("bonuses".to_owned(), bonuses);
+
+    Ok(ScoreReport { case: case_name.clone(), score, details })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::qual2018::{Case, Qual2018ScoringError, report};
+    use crate::{Span, ScoringError};
+
+    const EXAMPLE: &str = "3 4 2 3 2 10\n0 0 1 3 2 9\n1 2 1 0 0 9\n2 0 2 2 0 9\n";
+
+    fn challenge_error(result: Result<crate::ScoreReport, ScoringError>) -> String {
+        match result {
+            Err(ScoringError::ChallengeSpecific(e)) => e.to_string(),
+            other => panic!("Expected a challenge specific error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_example_case() {
+        let case = Case::parse(EXAMPLE).expect("should parse");
+        assert_eq!((case.vehicles, case.rides.len(), case.bonus, case.steps), (2, 3, 2, 10));
+        assert_eq!(case.rides[0].distance(), 4);
+    }
+
+    #[test]
+    fn late_ride_earns_nothing() {
+        let case = Case::parse(EXAMPLE).expect("should parse");
+        // Ride 1 starts after its earliest start, then ride 0 can only finish at step 10
+        let report = report("2 1 0\n0\n", &"a".into(), &case).expect("should score");
+        assert_eq!(report.score, 2);
+        assert_eq!((report.details["rides_on_time"], report.details["bonuses"]), (1, 0));
+    }
+
+    #[test]
+    fn ride_can_be_assigned_once() {
+        let case = Case::parse(EXAMPLE).expect("should parse");
+        assert_eq!(challenge_error(report("1 0\n1 0\n", &"a".into(), &case)),
+                   Qual2018ScoringError::RideAssignedTwice{ride_id: 0, span: Span { line: 2, column: 3, token: "0".to_owned() }}.to_string());
+        assert_eq!(challenge_error(report("1 3\n0\n", &"a".into(), &case)),
+                   "Ride 3 doesn't exist in this case at line 1, column 3 ('3')");
+    }
+
+    #[test]
+    fn every_vehicle_has_a_line() {
+        let case = Case::parse(EXAMPLE).expect("should parse");
+        assert_eq!(challenge_error(report("1 0\n", &"a".into(), &case)),
+                   "There should be a line for each of the 2 vehicles, found 1");
+    }
+
+    #[test]
+    fn ride_count_is_bounded_by_the_case() {
+        let case = Case::parse(EXAMPLE).expect("should parse");
+        assert_eq!(challenge_error(report("18446744073709551615 0\n0\n", &"a".into(), &case)),
+                   "A vehicle can't take more rides than the case has, at line 1, column 1 ('18446744073709551615')");
+    }
+}
@@ -0,0 +1,275 @@
+use thiserror::Error;
+use std::collections::{BTreeMap, HashMap};
+use crate::{ScoringError, InputFileName, ChallengeScorer, ScoreReport, Span};
+use crate::registry::{InputFile, LoadScorer, RegistryError};
+use crate::input_cases::{CaseNames, InputCases};
+use crate::parse::parse_number;
+
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Qual2019ScoringError {
+    #[error("A photo should be H or V at {span}")]
+    WrongOrientation{span: Span},
+    #[error("There is a different number of tags than specified at {span}")]
+    WrongNumberOfTags{span: Span},
+    #[error("There is a different number of slides than specified at {span}")]
+    WrongNumberOfSlides{span: Span},
+    #[error("There are more slides than photos at {span}")]
+    TooManySlides{span: Span},
+    #[error("A slide should have a horizontal photo or two vertical photos, at {span}")]
+    WrongNumberOfPhotos{span: Span},
+    #[error("Photo {photo_id} doesn't exist in this case at {span}")]
+    NonExistPhoto{photo_id: PhotoID, span: Span},
+    #[error("Photo {photo_id} is used more than once at {span}")]
+    PhotoUsedTwice{photo_id: PhotoID, span: Span},
+    #[error("Vertical photo {photo_id} should share its slide with another vertical photo, at {span}")]
+    VerticalPhotoAlone{photo_id: PhotoID, span: Span},
+    #[error("Horizontal photo {photo_id} should be alone on its slide, at {span}")]
+    HorizontalPhotoInPair{photo_id: PhotoID, span: Span},
+}
+
+impl From<Qual2019ScoringError> for ScoringError {
+    fn from(e: Qual2019ScoringError) -> Self {
+        ScoringError::ChallengeSpecific(Box::new(e))
+    }
+}
+
+
+type PhotoID = u32;
+/// A tag interned when the case is parsed, tags are compared as numbers while scoring
+type TagID = u32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Orientation {
+    Horizontal,
+    Vertical,
+}
+
+#[derive(Debug)]
+struct Photo {
+    orientation: Orientation,
+    /// Sorted and without duplicates
+    tags: Vec<TagID>,
+}
+
+#[derive(Debug)]
+struct Case {
+    photos: Vec<Photo>,
+}
+
+impl Case {
+    fn parse(input: &str) -> Result<Self, ScoringError> {
+        use crate::ScoringError::*;
+        use Qual2019ScoringError::*;
+
+        let mut lines = input.lines();
+        let missing_line = || MissingLine(Span::end_of(input));
+
+        let first_line = lines.next().ok_or_else(missing_line)?;
+        let number_of_photos = parse_number::<usize>(input, first_line.trim())?;
+
+        let mut tag_ids = HashMap::<&str, TagID>::new();
+        let mut photos = Vec::with_capacity(number_of_photos);
+        for _photo_id in 0..number_of_photos {
+            let line = lines.next().ok_or_else(missing_line)?;
+            let mut tokens = line.split_whitespace();
+
+            let orientation = match tokens.next() {
+                Some("H") => Orientation::Horizontal,
+                Some("V") => Orientation::Vertical,
+                _ => return Err(WrongOrientation{span: Span::at(input, line)}.into()),
+            };
+            let number_of_tags_str = tokens.next().ok_or_else(|| WrongNumberOfTags{span: Span::after(input, line)})?;
+            let number_of_tags = parse_number::<usize>(input, number_of_tags_str)?;
+
+            let mut tags = tokens
+                .map(|tag| {
+                    let next_id = tag_ids.len() as TagID;
+                    *tag_ids.entry(tag).or_insert(next_id)
+                })
+                .collect::<Vec<_>>();
+            if tags.len() != number_of_tags {
+                return Err(WrongNumberOfTags{span: Span::at(input, number_of_tags_str)}.into());
+            }
+            tags.sort_unstable();
+            tags.dedup();
+
+            photos.push(Photo { orientation, tags });
+        }
+
+        Ok(Case { photos })
+    }
+}
+
+const CASE_NAMES: &[CaseNames] = &[
+    CaseNames { id: "a", aliases: &["a_example", "a_example.txt"] },
+    CaseNames { id: "b", aliases: &["b_lovely_landscapes", "b_lovely_landscapes.txt"] },
+    CaseNames { id: "c", aliases: &["c_memorable_moments", "c_memorable_moments.txt"] },
+    CaseNames { id: "d", aliases: &["d_pet_pictures", "d_pet_pictures.txt"] },
+    CaseNames { id: "e", aliases: &["e_shiny_selfies", "e_shiny_selfies.txt"] },
+];
+
+/// Scores a slideshow by the interest of each transition, the least of the tags two slides share and those only one has
+pub struct Qual2019 {
+    cases: InputCases<Case>
+}
+
+impl LoadScorer for Qual2019 {
+    fn load(input_files: Vec<InputFile>) -> Result<Self, RegistryError> {
+        InputCases::load(input_files, CASE_NAMES, Case::parse)
+            .map(|cases| Self { cases })
+    }
+}
+
+impl ChallengeScorer for Qual2019 {
+    fn input_cases(&self) -> Vec<InputFileName> {
+        self.cases.ids()
+    }
+
+    fn resolve_case(&self, case: &InputFileName) -> Result<InputFileName, ScoringError> {
+        self.cases.resolve(case).cloned()
+    }
+
+    fn validate_input(&self, input: &str) -> Result<(), ScoringError> {
+        Case::parse(input).map(|_case| ())
+    }
+
+    fn report(&self, submission: &str, case_name: &InputFileName) -> Result<ScoreReport, ScoringError> {
+        let (case_id, case) = self.cases.get(case_name)?;
+        report(submission, case_id, case)
+    }
+}
+
+/// The tags of every slide of the slideshow, sorted
+fn parse_slides(submission: &str, case: &Case) -> Result<Vec<Vec<TagID>>, ScoringError> {
+    use Qual2019ScoringError::*;
+
+    let mut lines = submission.lines().filter(|line| !line.trim().is_empty());
+    let first_line = lines.next().ok_or_else(|| ScoringError::MissingLine(Span::end_of(submission)))?;
+    let number_of_slides = parse_number::<usize>(submission, first_line.trim())?;
+
+    if number_of_slides > case.photos.len() {
+        return Err(TooManySlides{span: Span::at(submission, first_line)}.into());
+    }
+
+    let mut used = vec![false; case.photos.len()];
+    let mut slides = Vec::with_capacity(number_of_slides);
+    for line in lines {
+        let mut photos = Vec::with_capacity(2);
+        for photo_id_str in line.split_whitespace() {
+            let photo_id = parse_number::<PhotoID>(submission, photo_id_str)?;
+            let span = || Span::at(submission, photo_id_str);
+            match used.get_mut(photo_id as usize) {
+                None => return Err(NonExistPhoto{photo_id, span: span()}.into()),
+                Some(true) => return Err(PhotoUsedTwice{photo_id, span: span()}.into()),
+                Some(is_used) => *is_used = true,
+            }
+            photos.push((photo_id, &case.photos[photo_id as usize]));
+        }
+
+        let tags = match photos.as_slice() {
+            [(_, photo)] if photo.orientation == Orientation::Horizontal => photo.tags.clone(),
+            [(photo_id, _)] => return Err(VerticalPhotoAlone{photo_id: *photo_id, span: Span::at(submission, line)}.into()),
+            [(_, first), (_, second)] if first.orientation == Orientation::Vertical && second.orientation == Orientation::Vertical =>
+                union(&first.tags, &second.tags),
+            [(first_id, first), (second_id, _)] => {
+                let photo_id = if first.orientation == Orientation::Horizontal { *first_id } else { *second_id };
+                return Err(HorizontalPhotoInPair{photo_id, span: Span::at(submission, line)}.into())
+            },
+            _ => return Err(WrongNumberOfPhotos{span: Span::at(submission, line)}.into()),
+        };
+        slides.push(tags);
+    }
+
+    if slides.len() != number_of_slides {
+        return Err(WrongNumberOfSlides{span: Span::at(submission, first_line)}.into());
+    }
+
+    Ok(slides)
+}
+
+fn union(a: &[TagID], b: &[TagID]) -> Vec<TagID> {
+    let mut tags = Vec::with_capacity(a.len() + b.len());
+    tags.extend_from_slice(a);
+    tags.extend_from_slice(b);
+    tags.sort_unstable();
+    tags.dedup();
+    tags
+}
+
+/// The number of tags in both sorted tag lists
+fn common_tags(a: &[TagID], b: &[TagID]) -> usize {
+    let (mut i, mut j, mut common) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                common += 1;
+                i += 1;
+                j += 1;
+            },
+        }
+    }
+    common
+}
+
+/// The least of the tags the slides share, and the tags only one of them has
+fn interest_factor(left: &[TagID], right: &[TagID]) -> u64 {
+    let common = common_tags(left, right);
+    common.min(left.len() - common).min(right.len() - common) as u64
+}
+
+fn report(submission: &str, case_name: &InputFileName, case: &Case) -> Result<ScoreReport, ScoringError> {
+    let slides = parse_slides(submission, case)?;
+
+    let score = slides.windows(2)
+        .map(|pair| interest_factor(&pair[0], &pair[1]))
+        .sum();
+
+    let mut details = BTreeMap::new();
+    details.insert("slides".to_owned(), slides.len() as u64);
+
+    Ok(ScoreReport { case: case_name.clone(), score, details })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::qual2019::{Case, interest_factor, report};
+    use crate::ScoringError;
+
+    const EXAMPLE: &str = "4\nH 3 cat beach sun\nV 2 selfie smile\nV 2 garden selfie\nH 2 garden cat\n";
+
+    fn challenge_error(submission: &str) -> String {
+        let case = Case::parse(EXAMPLE).expect("should parse");
+        match report(submission, &"a".into(), &case) {
+            Err(ScoringError::ChallengeSpecific(e)) => e.to_string(),
+            other => panic!("Expected a challenge specific error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn tags_are_interned() {
+        let case = Case::parse(EXAMPLE).expect("should parse");
+        assert_eq!(case.photos[0].tags, vec![0, 1, 2]);
+        // "garden" and "cat"
+        assert_eq!(case.photos[3].tags, vec![0, 5]);
+    }
+
+    #[test]
+    fn interest_is_the_least_of_common_and_own_tags() {
+        assert_eq!(interest_factor(&[0, 1, 2], &[0, 5]), 1);
+        assert_eq!(interest_factor(&[0, 1], &[0, 1]), 0);
+        assert_eq!(interest_factor(&[0, 1, 2, 3], &[2, 3, 4, 5]), 2);
+    }
+
+    #[test]
+    fn invalid_slides_are_rejected() {
+        assert_eq!(challenge_error("1\n1\n"), "Vertical photo 1 should share its slide with another vertical photo, at line 2, column 1 ('1')");
+        assert_eq!(challenge_error("1\n0 1\n"), "Horizontal photo 0 should be alone on its slide, at line 2, column 1 ('0')");
+        assert_eq!(challenge_error("2\n0\n0\n"), "Photo 0 is used more than once at line 3, column 1 ('0')");
+        assert_eq!(challenge_error("1\n4\n"), "Photo 4 doesn't exist in this case at line 2, column 1 ('4')");
+        assert_eq!(challenge_error("2\n0\n"), "There is a different number of slides than specified at line 1, column 1 ('2')");
+        assert_eq!(challenge_error("100000000000000000\n0\n"), "There are more slides than photos at line 1, column 1 ('100000000000000000')");
+    }
+}
@@ -1,36 +1,42 @@
 use thiserror::Error;
 use itertools::Itertools;
 use std::cmp::min;
-use std::collections::HashSet;
-use crate::{ScoringError, InputFileName};
+use std::collections::{HashSet, BTreeMap};
+use crate::{ScoringError, InputFileName, ChallengeScorer, ScoreReport, Span};
+use crate::registry::{InputFile, LoadScorer, RegistryError};
+use crate::input_cases::{CaseNames, InputCases};
+use crate::parse::{parse_number, parse_numbers};
 
 
 #[derive(Error, Debug, PartialEq, Eq)]
 pub enum Qual2020ScoringError {
-    #[error("Expected a library id")]
-    MissingLibraryId,
-    #[error("Wrong library id format")]
-    WrongFormatLibraryId,
-    #[error("Expected the number of books at library signup, for library id {library_id}")]
-    MissingNumOfBooksForLibrarySignup{library_id: LibraryID},
-    #[error("Wrong format of number of books at library signup")]
-    WrongFormatNumOfBooks,
-    #[error("Wrong format of book id at library signup")]
-    WrongFormatBookId,
-    #[error("There is a different number of books than specified, library id {library_id}")]
+    #[error("Expected a library id at {span}")]
+    MissingLibraryId{span: Span},
+    #[error("Wrong library id format at {span}")]
+    WrongFormatLibraryId{span: Span},
+    #[error("Expected the number of books at library signup, for library id {library_id} at {span}")]
+    MissingNumOfBooksForLibrarySignup{library_id: LibraryID, span: Span},
+    #[error("Wrong format of number of books at library signup at {span}")]
+    WrongFormatNumOfBooks{span: Span},
+    #[error("Wrong format of book id at library signup at {span}")]
+    WrongFormatBookId{span: Span},
+    #[error("There is a different number of books than specified, library id {library_id} at {span}")]
     WrongNumberOfBooks {
-        library_id: LibraryID
+        library_id: LibraryID,
+        span: Span
     },
-    #[error("There is a different number of libraries than specified")]
-    WrongNumberOfLibrariesToSignUp,
-    #[error("Missing parameter on input file")]
-    MissingParameterOnInputFile,
-    #[error("You're trying to register more libraries than exist in this case")]
-    TooManyLibraries,
-    #[error("You're trying to scan a book that doesn't exist in the library")]
-    LibraryDoesntContainBook{libid: LibraryID, bookid: BookID},
-    #[error("You're trying to sign up a library that doesn't exist")]
-    NonExistLibrary{libid: LibraryID}
+    #[error("There is a different number of libraries than specified at {span}")]
+    WrongNumberOfLibrariesToSignUp{span: Span},
+    #[error("Missing parameter on input file at {span}")]
+    MissingParameterOnInputFile{span: Span},
+    #[error("You're trying to register more libraries than exist in this case at {span}")]
+    TooManyLibraries{span: Span},
+    #[error("You're trying to scan a book that doesn't exist in the library at {span}")]
+    LibraryDoesntContainBook{libid: LibraryID, bookid: BookID, span: Span},
+    #[error("You're trying to sign up a library that doesn't exist at {span}")]
+    NonExistLibrary{libid: LibraryID, span: Span},
+    #[error("Book {bookid} has no score in this case")]
+    NonExistBook{bookid: BookID},
 
 }
 
@@ -52,42 +58,55 @@ struct Library {
     days_to_signup: u32
 }
 
-struct LibrarySignup {
+struct LibrarySignup<'a> {
     id: LibraryID,
-    books_to_scan: Vec<BookID>
+    books_to_scan: Vec<BookID>,
+    // Kept to point at the offending token when the signup doesn't match the case
+    first_line: &'a str,
+    second_line: &'a str
 }
 
-impl LibrarySignup {
-    fn parse_from_2_lines(first_line: &str, second_line: &str) -> Result<Self, Qual2020ScoringError> {
+impl<'a> LibrarySignup<'a> {
+    /// `submission` is the whole file the lines were taken from
+    fn parse_from_2_lines(submission: &str, first_line: &'a str, second_line: &'a str) -> Result<Self, Qual2020ScoringError> {
 
         use Qual2020ScoringError::*;
 
         let mut id_and_num_of_books = first_line.split_whitespace();
-        let library_id = id_and_num_of_books.next()
-            .ok_or(MissingLibraryId)?
+        let library_id_str = id_and_num_of_books.next()
+            .ok_or_else(|| MissingLibraryId{span: Span::after(submission, first_line)})?;
+        let library_id = library_id_str
             .parse::<LibraryID>()
-            .map_err(|_| WrongFormatLibraryId)?;
+            .map_err(|_| WrongFormatLibraryId{span: Span::at(submission, library_id_str)})?;
 
-        let num_of_books = id_and_num_of_books.next()
-            .ok_or(MissingNumOfBooksForLibrarySignup{library_id})?
+        let num_of_books_str = id_and_num_of_books.next()
+            .ok_or_else(|| MissingNumOfBooksForLibrarySignup{library_id, span: Span::after(submission, first_line)})?;
+        let num_of_books = num_of_books_str
             .parse::<u32>()
-            .map_err(|_| WrongFormatNumOfBooks)?;
+            .map_err(|_| WrongFormatNumOfBooks{span: Span::at(submission, num_of_books_str)})?;
 
         let books_to_scan = second_line.split_whitespace()
-            .map(|book_id_str| book_id_str.parse::<BookID>())
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|_| WrongFormatBookId)?;
+            .map(|book_id_str| book_id_str.parse::<BookID>()
+                .map_err(|_| WrongFormatBookId{span: Span::at(submission, book_id_str)}))
+            .collect::<Result<Vec<_>, _>>()?;
 
         if books_to_scan.len() != num_of_books as usize {
-            return Err(WrongNumberOfBooks{ library_id})
+            return Err(WrongNumberOfBooks{ library_id, span: Span::at(submission, num_of_books_str)})
         }
 
-        Ok(Self { id: library_id, books_to_scan })
+        Ok(Self { id: library_id, books_to_scan, first_line, second_line })
+    }
+
+    fn book_span(&self, submission: &str, book_index: usize) -> Span {
+        match self.second_line.split_whitespace().nth(book_index) {
+            Some(book_id_str) => Span::at(submission, book_id_str),
+            None => Span::after(submission, self.second_line),
+        }
     }
 }
 
-struct Submission {
-    libraries_to_signup: Vec<LibrarySignup>
+struct Submission<'a> {
+    libraries_to_signup: Vec<LibrarySignup<'a>>
 }
 
 struct Case {
@@ -103,54 +122,30 @@ impl Case {
         use Qual2020ScoringError::*;
 
         let mut lines = input.lines();
+        let missing_line = || MissingLine(Span::end_of(input));
 
-        let mut first_line = lines.next().ok_or(MissingLine)?.split_whitespace();
-        let number_of_different_books = first_line.next()
-            .ok_or(MissingParameterOnInputFile)?
-            .parse()
-            .map_err(|_| ExpectedANumber)?;
+        let first_line = lines.next().ok_or_else(missing_line)?;
+        let missing_parameter = || MissingParameterOnInputFile{span: Span::after(input, first_line)};
+        let mut first_line_tokens = first_line.split_whitespace();
+        let number_of_different_books = parse_number(input, first_line_tokens.next().ok_or_else(missing_parameter)?)?;
+        let number_of_libraries = parse_number::<u32>(input, first_line_tokens.next().ok_or_else(missing_parameter)?)?;
+        let number_of_days = parse_number(input, first_line_tokens.next().ok_or_else(missing_parameter)?)?;
 
 
-        let number_of_libraries = first_line.next()
-            .ok_or(MissingParameterOnInputFile)?
-            .parse::<u32>()
-            .map_err(|_| ExpectedANumber)?;
-
-        let number_of_days = first_line.next()
-            .ok_or(MissingParameterOnInputFile)?
-            .parse::<u32>()
-            .map_err(|_| ExpectedANumber)?;
-
-
-        let score_per_book = lines.next().ok_or(MissingLine)?.split_whitespace()
-            .map(|x| x.parse::<BookScore>())
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|_| ExpectedANumber)?;
+        let score_per_book = parse_numbers::<BookScore>(input, lines.next().ok_or_else(missing_line)?)?;
 
 
         let mut libraries = Vec::with_capacity(number_of_libraries as usize);
         for library_id in 0..number_of_libraries {
-            let mut first_line = lines.next().ok_or(MissingLine)?.split_whitespace();
-            let _number_of_books = first_line.next()
-                .ok_or(MissingParameterOnInputFile)?
-                .parse::<u32>()
-                .map_err(|_| ExpectedANumber)?;
-
-            let days_to_signup = first_line.next()
-                .ok_or(MissingParameterOnInputFile)?
-                .parse()
-                .map_err(|_| ExpectedANumber)?;
+            let first_line = lines.next().ok_or_else(missing_line)?;
+            let missing_parameter = || MissingParameterOnInputFile{span: Span::after(input, first_line)};
+            let mut first_line_tokens = first_line.split_whitespace();
+            let _number_of_books = parse_number::<u32>(input, first_line_tokens.next().ok_or_else(missing_parameter)?)?;
+            let days_to_signup = parse_number(input, first_line_tokens.next().ok_or_else(missing_parameter)?)?;
+            let max_books_per_day = parse_number(input, first_line_tokens.next().ok_or_else(missing_parameter)?)?;
 
-            let max_books_per_day = first_line.next()
-                .ok_or(MissingParameterOnInputFile)?
-                .parse()
-                .map_err(|_| ExpectedANumber)?;
 
-
-            let books_in_library = lines.next().ok_or(MissingLine)?.split_whitespace()
-                .map(|x| x.parse::<BookID>())
-                .collect::<Result<Vec<_>, _>>()
-                .map_err(|_| ExpectedANumber)?;
+            let books_in_library = parse_numbers::<BookID>(input, lines.next().ok_or_else(missing_line)?)?;
 
             libraries.push(Library{ _id: library_id, days_to_signup, max_books_per_day, books: books_in_library })
 
@@ -161,73 +156,92 @@ impl Case {
     }
 }
 
-lazy_static!{
-    static ref CASE_A: Case = Case::parse(include_str!("../assets/2020qual/inputs/a_example.txt")).
-                                        unwrap();
-    static ref CASE_B: Case = Case::parse(include_str!("../assets/2020qual/inputs/b_read_on.txt")).
-                                        unwrap();
-    static ref CASE_C: Case = Case::parse(include_str!("../assets/2020qual/inputs/c_incunabula.txt")).
-                                        unwrap();
-    static ref CASE_D: Case = Case::parse(include_str!("../assets/2020qual/inputs/d_tough_choices.txt")).
-                                        unwrap();
-    static ref CASE_E: Case = Case::parse(include_str!("../assets/2020qual/inputs/e_so_many_books.txt")).
-                                        unwrap();
-    static ref CASE_F: Case = Case::parse(include_str!("../assets/2020qual/inputs/f_libraries_of_the_world.txt")).
-                                        unwrap();
+const CASE_NAMES: &[CaseNames] = &[
+    CaseNames { id: "a", aliases: &["a_example", "a_example.txt"] },
+    CaseNames { id: "b", aliases: &["b_read_on", "b_read_on.txt"] },
+    CaseNames { id: "c", aliases: &["c_incunabula", "c_incunabula.txt"] },
+    CaseNames { id: "d", aliases: &["d_tough_choices", "d_tough_choices.txt"] },
+    CaseNames { id: "e", aliases: &["e_so_many_books", "e_so_many_books.txt"] },
+    CaseNames { id: "f", aliases: &["f_libraries_of_the_world", "f_libraries_of_the_world.txt"] },
+];
+
+/// Signs up libraries in the order of a submission and scores the distinct books they scan before the deadline
+pub struct Qual2020 {
+    cases: InputCases<Case>
 }
 
+impl LoadScorer for Qual2020 {
+    fn load(input_files: Vec<InputFile>) -> Result<Self, RegistryError> {
+        InputCases::load(input_files, CASE_NAMES, Case::parse)
+            .map(|cases| Self { cases })
+    }
+}
+
+impl ChallengeScorer for Qual2020 {
+    fn input_cases(&self) -> Vec<InputFileName> {
+        self.cases.ids()
+    }
+
+    fn resolve_case(&self, case: &InputFileName) -> Result<InputFileName, ScoringError> {
+        self.cases.resolve(case).cloned()
+    }
+
+    fn validate_input(&self, input: &str) -> Result<(), ScoringError> {
+        Case::parse(input).map(|_case| ())
+    }
 
-pub fn score(submission: &str, case: &InputFileName) -> Result<u64, ScoringError> {
+    fn report(&self, submission: &str, case_name: &InputFileName) -> Result<ScoreReport, ScoringError> {
+        let (case_id, case) = self.cases.get(case_name)?;
+        report(submission, case_id, case)
+    }
+}
+
+fn report(submission: &str, case_name: &InputFileName, case: &Case) -> Result<ScoreReport, ScoringError> {
     use Qual2020ScoringError::*;
-    let case: &Case = match case {
-        InputFileName(ref s) if s.starts_with("a") => &*CASE_A,
-        InputFileName(ref s) if s.starts_with("b") => &*CASE_B,
-        InputFileName(ref s) if s.starts_with("c") => &*CASE_C,
-        InputFileName(ref s) if s.starts_with("d") => &*CASE_D,
-        InputFileName(ref s) if s.starts_with("e") => &*CASE_E,
-        InputFileName(ref s) if s.starts_with("f") => &*CASE_F,
-        input_case @ InputFileName(_) => return Err(ScoringError::UnknownInputCase(input_case.clone()))
-    };
     let mut input_lines = submission.lines();
-    let number_of_libraries_to_signup: u32 = input_lines.next().ok_or(ScoringError::MissingLine)?
-        .parse().map_err(|_| ScoringError::ExpectedANumber)?;
+    let first_line = input_lines.next().ok_or_else(|| ScoringError::MissingLine(Span::end_of(submission)))?;
+    let number_of_libraries_to_signup: u32 = parse_number(submission, first_line)?;
 
     if number_of_libraries_to_signup as usize > case.libraries.len() {
-        return Err(TooManyLibraries.into())
+        return Err(TooManyLibraries{span: Span::at(submission, first_line)}.into())
     }
 
     let mut libraries_to_signup = Vec::with_capacity(number_of_libraries_to_signup as usize);
     for mut double_line in &input_lines.chunks(2) {
-        let fl = double_line.next().ok_or(ScoringError::MissingLine)?;
-        let sl = double_line.next().ok_or(ScoringError::MissingLine)?;
-        let library_signup = LibrarySignup::parse_from_2_lines(fl, sl)?;
+        let fl = double_line.next().ok_or_else(|| ScoringError::MissingLine(Span::end_of(submission)))?;
+        let sl = double_line.next().ok_or_else(|| ScoringError::MissingLine(Span::end_of(submission)))?;
+        let library_signup = LibrarySignup::parse_from_2_lines(submission, fl, sl)?;
         libraries_to_signup.push(library_signup);
 
     }
 
     if libraries_to_signup.len() != number_of_libraries_to_signup as usize {
-        return Err(WrongNumberOfLibrariesToSignUp.into())
+        return Err(WrongNumberOfLibrariesToSignUp{span: Span::at(submission, first_line)}.into())
     }
 
-    let submission = Submission { libraries_to_signup };
+    let submission_libraries = Submission { libraries_to_signup };
 
-    for library_signup in &submission.libraries_to_signup {
+    for library_signup in &submission_libraries.libraries_to_signup {
         let lib_id = library_signup.id;
-        let library: &Library = case.libraries.get(lib_id as usize).ok_or(NonExistLibrary{libid:lib_id})?;
+        let library: &Library = case.libraries.get(lib_id as usize)
+            .ok_or_else(|| NonExistLibrary{libid:lib_id, span: Span::at(submission, library_signup.first_line)})?;
 
-        if let Some(unfound_book_id) = library_signup.books_to_scan.iter().find(|book| !library.books.contains(book)) {
-            return Err(LibraryDoesntContainBook{ libid: lib_id, bookid: *unfound_book_id }.into())
+        if let Some((book_index, unfound_book_id)) = library_signup.books_to_scan.iter().find_position(|book| !library.books.contains(book)) {
+            return Err(LibraryDoesntContainBook{ libid: lib_id, bookid: *unfound_book_id,
+                span: library_signup.book_span(submission, book_index) }.into())
         }
     }
 
     let mut books_scaned: HashSet<BookID, _> = HashSet::new();
     let mut days_left = case.number_of_days;
-    for curr_signup in &submission.libraries_to_signup {
+    let mut signed_up_libraries = 0;
+    for curr_signup in &submission_libraries.libraries_to_signup {
         // wait the sign up time
         let library = &case.libraries[curr_signup.id as usize];
         let days_to_signup = library.days_to_signup;
         if days_left <= days_to_signup { break };
         days_left -= days_to_signup;
+        signed_up_libraries += 1;
 
         let number_of_books_able_to_scan = min((days_left as u64)*(library.max_books_per_day as u64),
                                                curr_signup.books_to_scan.len() as u64);
@@ -235,20 +249,28 @@ pub fn score(submission: &str, case: &InputFileName) -> Result<u64, ScoringError
     }
 
     let score = books_scaned.iter()
-        .map(|&book_id| case.score_per_book[book_id as usize] as u64)
-        .sum();
-    Ok(score)
+        .map(|&book_id| case.score_per_book.get(book_id as usize)
+            .map(|&book_score| book_score as u64)
+            .ok_or(NonExistBook{bookid: book_id}))
+        .sum::<Result<_, _>>()?;
+
+    let mut details = BTreeMap::new();
+    details.insert("signed_up_libraries".to_owned(), signed_up_libraries);
+    details.insert("scanned_books".to_owned(), books_scaned.len() as u64);
+
+    Ok(ScoreReport { case: case_name.clone(), score, details })
 }
 
 #[cfg(test)]
 mod test {
-    use crate::qual2020::{LibrarySignup, Qual2020ScoringError};
+    use crate::qual2020::{LibrarySignup, Qual2020ScoringError, Case, report};
+    use crate::{Span, ScoringError};
 
     #[test]
     fn parse_library_signup() {
         let library_desc = "1 5\n1 2 3 4 5";
         let lines = library_desc.lines().collect::<Vec<_>>();
-        let lib = LibrarySignup::parse_from_2_lines(lines[0], lines[1]).expect("shouldn't fail");
+        let lib = LibrarySignup::parse_from_2_lines(library_desc, lines[0], lines[1]).expect("shouldn't fail");
         assert_eq!(lib.id, 1);
         assert_eq!(lib.books_to_scan, vec![1,2,3,4,5]);
     }
@@ -257,8 +279,38 @@ mod test {
     fn test_failed_parse_library_signup() {
         let library_desc = "1 3\n1 2 3 4 5";
         let lines = library_desc.lines().collect::<Vec<_>>();
-        let failed_lib = LibrarySignup::parse_from_2_lines(lines[0], lines[1]);
+        let failed_lib = LibrarySignup::parse_from_2_lines(library_desc, lines[0], lines[1]);
+        assert_eq!(failed_lib.err(),
+                   Some(Qual2020ScoringError::WrongNumberOfBooks{ library_id: 1,
+                       span: Span { line: 1, column: 3, token: "3".to_owned() }}))
+    }
+
+    #[test]
+    fn bad_book_id_is_located() {
+        let submission = "1\n0 3\n1 x 3";
+        let lines = submission.lines().collect::<Vec<_>>();
+        let failed_lib = LibrarySignup::parse_from_2_lines(submission, lines[1], lines[2]);
         assert_eq!(failed_lib.err(),
-                   Some(Qual2020ScoringError::WrongNumberOfBooks{ library_id: 1}))
+                   Some(Qual2020ScoringError::WrongFormatBookId{
+                       span: Span { line: 3, column: 3, token: "x".to_owned() }}))
+    }
+
+    #[test]
+    fn bad_input_number_is_located() {
+        match Case::parse("6 2 7\n1 2 3 6 5 4\n5 2 two\n") {
+            Err(ScoringError::ExpectedANumber(span)) =>
+                assert_eq!(span, Span { line: 3, column: 5, token: "two".to_owned() }),
+            _ => panic!("should fail on the library line"),
+        }
+    }
+
+    #[test]
+    fn book_without_score_is_an_error() {
+        // The only library has book 5, but there are scores for books 0 and 1 only
+        let case = Case::parse("2 1 5\n1 1\n1 1 1\n5\n").expect("should parse");
+        match report("1\n0 1\n5\n", &"a".into(), &case) {
+            Err(ScoringError::ChallengeSpecific(e)) => assert_eq!(e.to_string(), "Book 5 has no score in this case"),
+            _ => panic!("should fail on the unknown book"),
+        }
     }
 }
@@ -0,0 +1,436 @@
+use thiserror::Error;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use crate::{ScoringError, InputFileName, Score, ChallengeScorer, ScoreReport, Span};
+use crate::registry::{InputFile, LoadScorer, RegistryError};
+use crate::input_cases::{CaseNames, InputCases};
+use crate::parse::parse_number;
+
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Qual2021ScoringError {
+    #[error("Missing parameter on input file at {span}")]
+    MissingParameterOnInputFile{span: Span},
+    #[error("A street should have a start, an end, a name and a length, at {span}")]
+    WrongStreetFormat{span: Span},
+    #[error("There is a different number of streets than specified at {span}")]
+    WrongNumberOfStreets{span: Span},
+    #[error("A car path should have at least two streets, at {span}")]
+    PathTooShort{span: Span},
+    #[error("Street {name} is described more than once at {span}")]
+    StreetDescribedTwice{name: String, span: Span},
+    #[error("Unknown street {name} at {span}")]
+    UnknownStreet{name: String, span: Span},
+    #[error("A schedule line should have a street name and a duration, at {span}")]
+    WrongScheduleFormat{span: Span},
+    #[error("Intersection {intersection_id} doesn't exist in this case at {span}")]
+    NonExistIntersection{intersection_id: IntersectionID, span: Span},
+    #[error("Intersection {intersection_id} has more than one schedule, at {span}")]
+    IntersectionScheduledTwice{intersection_id: IntersectionID, span: Span},
+    #[error("The schedule of intersection {intersection_id} should have at least one street, at {span}")]
+    EmptySchedule{intersection_id: IntersectionID, span: Span},
+    #[error("Only {incoming} streets end at intersection {intersection_id}, at {span}")]
+    TooManyStreetsInSchedule{intersection_id: IntersectionID, incoming: usize, span: Span},
+    #[error("Street {name} doesn't end at intersection {intersection_id}, at {span}")]
+    StreetNotIntoIntersection{name: String, intersection_id: IntersectionID, span: Span},
+    #[error("Street {name} is in the schedule of its intersection more than once, at {span}")]
+    StreetScheduledTwice{name: String, span: Span},
+    #[error("A green light should last between 1 and {duration} seconds, at {span}")]
+    WrongGreenDuration{duration: Time, span: Span},
+}
+
+impl From<Qual2021ScoringError> for ScoringError {
+    fn from(e: Qual2021ScoringError) -> Self {
+        ScoringError::ChallengeSpecific(Box::new(e))
+    }
+}
+
+
+type IntersectionID = u32;
+type StreetID = u32;
+type CarID = u32;
+/// In seconds
+type Time = u32;
+
+#[derive(Debug)]
+struct Street {
+    end: IntersectionID,
+    length: Time,
+}
+
+#[derive(Debug)]
+struct Case {
+    duration: Time,
+    /// The number of streets ending at each intersection
+    incoming_streets: Vec<usize>,
+    streets: Vec<Street>,
+    street_ids: HashMap<String, StreetID>,
+    /// The streets each car drives through, it starts at the end of the first one
+    paths: Vec<Vec<StreetID>>,
+    bonus: Score,
+}
+
+impl Case {
+    fn parse(input: &str) -> Result<Self, ScoringError> {
+        use crate::ScoringError::*;
+        use Qual2021ScoringError::*;
+
+        let mut lines = input.lines();
+        let missing_line = || MissingLine(Span::end_of(input));
+
+        let first_line = lines.next().ok_or_else(missing_line)?;
+        let missing_parameter = || MissingParameterOnInputFile{span: Span::after(input, first_line)};
+        let mut first_line_tokens = first_line.split_whitespace();
+        let duration = parse_number(input, first_line_tokens.next().ok_or_else(missing_parameter)?)?;
+        let intersections = parse_number::<usize>(input, first_line_tokens.next().ok_or_else(missing_parameter)?)?;
+        let number_of_streets = parse_number::<usize>(input, first_line_tokens.next().ok_or_else(missing_parameter)?)?;
+        let number_of_cars = parse_number::<usize>(input, first_line_tokens.next().ok_or_else(missing_parameter)?)?;
+        let bonus = parse_number(input, first_line_tokens.next().ok_or_else(missing_parameter)?)?;
+
+        let mut incoming_streets = vec![0; intersections];
+        let mut streets = Vec::with_capacity(number_of_streets);
+        let mut street_ids = HashMap::with_capacity(number_of_streets);
+        for street_id in 0..number_of_streets {
+            let line = lines.next().ok_or_else(missing_line)?;
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            if tokens.len() != 4 {
+                return Err(WrongStreetFormat{span: Span::at(input, line)}.into());
+            }
+            let _start = parse_number::<IntersectionID>(input, tokens[0])?;
+            let end = parse_number(input, tokens[1])?;
+            match incoming_streets.get_mut(end as usize) {
+                Some(incoming) => *incoming += 1,
+                None => return Err(NonExistIntersection{intersection_id: end, span: Span::at(input, tokens[1])}.into()),
+            }
+            let length = parse_number(input, tokens[3])?;
+
+            if street_ids.insert(tokens[2].to_owned(), street_id as StreetID).is_some() {
+                return Err(StreetDescribedTwice{name: tokens[2].to_owned(), span: Span::at(input, tokens[2])}.into());
+            }
+            streets.push(Street { end, length });
+        }
+
+        let mut paths = Vec::with_capacity(number_of_cars);
+        for _car_id in 0..number_of_cars {
+            let line = lines.next().ok_or_else(missing_line)?;
+            let mut tokens = line.split_whitespace();
+            let number_of_streets_str = tokens.next().ok_or_else(|| WrongNumberOfStreets{span: Span::at(input, line)})?;
+            let number_of_streets = parse_number::<usize>(input, number_of_streets_str)?;
+            // A car starts at the end of its first street, so it has to drive at least one more
+            if number_of_streets < 2 {
+                return Err(PathTooShort{span: Span::at(input, number_of_streets_str)}.into());
+            }
+
+            let path = tokens
+                .map(|name| street_ids.get(name).copied()
+                    .ok_or_else(|| UnknownStreet{name: name.to_owned(), span: Span::at(input, name)}))
+                .collect::<Result<Vec<_>, _>>()?;
+            if path.len() != number_of_streets {
+                return Err(WrongNumberOfStreets{span: Span::at(input, number_of_streets_str)}.into());
+            }
+            paths.push(path);
+        }
+
+        Ok(Case { duration, incoming_streets, streets, street_ids, paths, bonus })
+    }
+}
+
+const CASE_NAMES: &[CaseNames] = &[
+    CaseNames { id: "a", aliases: &["a.txt"] },
+    CaseNames { id: "b", aliases: &["b.txt"] },
+    CaseNames { id: "c", aliases: &["c.txt"] },
+    CaseNames { id: "d", aliases: &["d.txt"] },
+    CaseNames { id: "e", aliases: &["e.txt"] },
+    CaseNames { id: "f", aliases: &["f.txt"] },
+];
+
+/// Runs the traffic light schedules of a submission second by second, scoring the cars that finish their path in time
+pub struct Qual2021 {
+    cases: InputCases<Case>
+}
+
+impl LoadScorer for Qual2021 {
+    fn load(input_files: Vec<InputFile>) -> Result<Self, RegistryError> {
+        InputCases::load(input_files, CASE_NAMES, Case::parse)
+            .map(|cases| Self { cases })
+    }
+}
+
+impl ChallengeScorer for Qual2021 {
+    fn input_cases(&self) -> Vec<InputFileName> {
+        self.cases.ids()
+    }
+
+    fn resolve_case(&self, case: &InputFileName) -> Result<InputFileName, ScoringError> {
+        self.cases.resolve(case).cloned()
+    }
+
+    fn validate_input(&self, input: &str) -> Result<(), ScoringError> {
+        Case::parse(input).map(|_case| ())
+    }
+
+    fn report(&self, submission: &str, case_name: &InputFileName) -> Result<ScoreReport, ScoringError> {
+        let (case_id, case) = self.cases.get(case_name)?;
+        report(submission, case_id, case)
+    }
+}
+
+/// When a street's light is green, as a part of its intersection's cycle
+#[derive(Debug, Clone, Copy)]
+struct GreenLight {
+    cycle: Time,
+    from: Time,
+    until: Time,
+}
+
+impl GreenLight {
+    fn is_green_at(self, t: Time) -> bool {
+        let in_cycle = t % self.cycle;
+        self.from <= in_cycle && in_cycle < self.until
+    }
+}
+
+/// The green light of every street that is in a schedule
+fn parse_schedules(submission: &str, case: &Case) -> Result<Vec<Option<GreenLight>>, ScoringError> {
+    use Qual2021ScoringError::*;
+
+    let mut lines = submission.lines().filter(|line| !line.trim().is_empty());
+    let mut next_line = || lines.next().ok_or_else(|| ScoringError::MissingLine(Span::end_of(submission)));
+
+    let first_line = next_line()?;
+    let number_of_schedules = parse_number::<usize>(submission, first_line.trim())?;
+
+    let mut scheduled = vec![false; case.incoming_streets.len()];
+    let mut green_lights = vec![None; case.streets.len()];
+    for _schedule in 0..number_of_schedules {
+        let intersection_line = next_line()?.trim();
+        let intersection_id = parse_number::<IntersectionID>(submission, intersection_line)?;
+        let span = || Span::at(submission, intersection_line);
+        match scheduled.get_mut(intersection_id as usize) {
+            None => return Err(NonExistIntersection{intersection_id, span: span()}.into()),
+            Some(true) => return Err(IntersectionScheduledTwice{intersection_id, span: span()}.into()),
+            Some(is_scheduled) => *is_scheduled = true,
+        }
+
+        let number_of_streets_line = next_line()?.trim();
+        let number_of_streets = parse_number::<usize>(submission, number_of_streets_line)?;
+        if number_of_streets == 0 {
+            return Err(EmptySchedule{intersection_id, span: Span::at(submission, number_of_streets_line)}.into());
+        }
+        let incoming = case.incoming_streets[intersection_id as usize];
+        if number_of_streets > incoming {
+            return Err(TooManyStreetsInSchedule{intersection_id, incoming, span: Span::at(submission, number_of_streets_line)}.into());
+        }
+
+        let mut schedule = Vec::with_capacity(number_of_streets);
+        for _street in 0..number_of_streets {
+            let line = next_line()?;
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            if tokens.len() != 2 {
+                return Err(WrongScheduleFormat{span: Span::at(submission, line)}.into());
+            }
+            let (name, span) = (tokens[0], || Span::at(submission, tokens[0]));
+            let street_id = *case.street_ids.get(name)
+                .ok_or_else(|| UnknownStreet{name: name.to_owned(), span: span()})?;
+            if case.streets[street_id as usize].end != intersection_id {
+                return Err(StreetNotIntoIntersection{name: name.to_owned(), intersection_id, span: span()}.into());
+            }
+            if schedule.iter().any(|&(scheduled_id, _)| scheduled_id == street_id) {
+                return Err(StreetScheduledTwice{name: name.to_owned(), span: span()}.into());
+            }
+
+            let green_duration = parse_number::<Time>(submission, tokens[1])?;
+            if green_duration == 0 || green_duration > case.duration {
+                return Err(WrongGreenDuration{duration: case.duration, span: Span::at(submission, tokens[1])}.into());
+            }
+            schedule.push((street_id, green_duration));
+        }
+
+        let cycle = schedule.iter().map(|&(_, green_duration)| green_duration).sum();
+        let mut from = 0;
+        for (street_id, green_duration) in schedule {
+            green_lights[street_id as usize] = Some(GreenLight { cycle, from, until: from + green_duration });
+            from += green_duration;
+        }
+    }
+
+    Ok(green_lights)
+}
+
+struct Car<'case> {
+    path: &'case [StreetID],
+    /// The street of the path the car is on
+    on_street: usize,
+}
+
+impl Car<'_> {
+    fn street(&self) -> StreetID {
+        self.path[self.on_street]
+    }
+
+    fn is_on_last_street(&self) -> bool {
+        self.on_street + 1 == self.path.len()
+    }
+}
+
+/// Where the cars are, second by second
+struct Traffic<'case> {
+    case: &'case Case,
+    cars: Vec<Car<'case>>,
+    /// The cars waiting at the end of each street, in the order they got there
+    queues: Vec<VecDeque<CarID>>,
+    /// The streets with cars waiting, the only ones worth checking each second
+    waiting_on: BTreeSet<StreetID>,
+    /// The cars reaching the end of a street, by the second they get there
+    arrivals: BTreeMap<Time, Vec<CarID>>,
+}
+
+impl<'case> Traffic<'case> {
+    fn new(case: &'case Case) -> Self {
+        let mut traffic = Traffic {
+            case,
+            cars: case.paths.iter().map(|path| Car { path, on_street: 0 }).collect(),
+            queues: vec![VecDeque::new(); case.streets.len()],
+            waiting_on: BTreeSet::new(),
+            arrivals: BTreeMap::new(),
+        };
+        for car_id in 0..traffic.cars.len() {
+            traffic.queue(car_id as CarID);
+        }
+        traffic
+    }
+
+    fn queue(&mut self, car_id: CarID) {
+        let street_id = self.cars[car_id as usize].street();
+        self.queues[street_id as usize].push_back(car_id);
+        self.waiting_on.insert(street_id);
+    }
+
+    /// Moves the cars through the green lights at second `t`, returns the cars that reached
+    /// the end of their path with the second they got there
+    fn step(&mut self, t: Time, green_lights: &[Option<GreenLight>]) -> Vec<(CarID, Time)> {
+        for car_id in self.arrivals.remove(&t).unwrap_or_default() {
+            self.queue(car_id);
+        }
+
+        let crossing: Vec<StreetID> = self.waiting_on.iter()
+            .copied()
+            .filter(|&street_id| match green_lights[street_id as usize] {
+                Some(green_light) => green_light.is_green_at(t),
+                None => false,
+            })
+            .collect();
+
+        let mut finished = Vec::new();
+        for street_id in crossing {
+            let queue = &mut self.queues[street_id as usize];
+            let car_id = queue.pop_front().expect("streets with waiting cars have a queue");
+            if queue.is_empty() {
+                self.waiting_on.remove(&street_id);
+            }
+
+            let car = &mut self.cars[car_id as usize];
+            car.on_street += 1;
+            let reaches_end_at = t + self.case.streets[car.street() as usize].length;
+            if car.is_on_last_street() {
+                finished.push((car_id, reaches_end_at));
+            } else if reaches_end_at < self.case.duration {
+                self.arrivals.entry(reaches_end_at).or_default().push(car_id);
+            }
+        }
+        finished
+    }
+}
+
+fn report(submission: &str, case_name: &InputFileName, case: &Case) -> Result<ScoreReport, ScoringError> {
+    let green_lights = parse_schedules(submission, case)?;
+
+    let mut traffic = Traffic::new(case);
+    let mut score: Score = 0;
+    let mut cars_finished = 0;
+    let mut early_arrival_bonus: Score = 0;
+    for t in 0..case.duration {
+        for (_car_id, finished_at) in traffic.step(t, &green_lights) {
+            if finished_at <= case.duration {
+                let early_by = (case.duration - finished_at) as Score;
+                score += case.bonus + early_by;
+                early_arrival_bonus += early_by;
+                cars_finished += 1;
+            }
+        }
+    }
+
+    let mut details = BTreeMap::new();
+    details.insert("cars_finished".to_owned(), cars_finished);
+    details.insert("early_arrival_bonus".to_owned(), early_arrival_bonus);
+
+    Ok(ScoreReport { case: case_name.clone(), score, details })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::qual2021::{Case, report};
+    use crate::ScoringError;
+
+    const EXAMPLE: &str = "6 4 5 2 1000\n\
+                           2 0 rue-de-londres 1\n\
+                           0 1 rue-d-amsterdam 1\n\
+                           3 1 rue-d-athenes 1\n\
+                           2 3 rue-de-rome 2\n\
+                           1 2 rue-de-moscou 3\n\
+                           4 rue-de-londres rue-d-amsterdam rue-de-moscou rue-de-rome\n\
+                           3 rue-d-athenes rue-de-moscou rue-de-londres\n";
+
+    fn challenge_error(submission: &str) -> String {
+        let case = Case::parse(EXAMPLE).expect("should parse");
+        match report(submission, &"a".into(), &case) {
+            Err(ScoringError::ChallengeSpecific(e)) => e.to_string(),
+            other => panic!("Expected a challenge specific error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_example_case() {
+        let case = Case::parse(EXAMPLE).expect("should parse");
+        assert_eq!((case.duration, case.incoming_streets.len(), case.streets.len(), case.bonus), (6, 4, 5, 1000));
+        assert_eq!(case.paths[1], vec![2, 4, 0]);
+    }
+
+    #[test]
+    fn a_path_needs_two_streets() {
+        match Case::parse("3 2 2 1 10\n0 1 a 1\n1 0 b 1\n1 a\n") {
+            Err(ScoringError::ChallengeSpecific(e)) =>
+                assert_eq!(e.to_string(), "A car path should have at least two streets, at line 4, column 1 ('1')"),
+            other => panic!("Expected a challenge specific error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn cars_wait_for_a_green_light() {
+        let case = Case::parse(EXAMPLE).expect("should parse");
+        // Without schedules every light stays red
+        assert_eq!(report("0\n", &"a".into(), &case).expect("should score").score, 0);
+
+        // Car 1 crosses at 0, reaches the end of rue-de-moscou at 3 and finishes at 4, car 0 never leaves
+        let report = report("2\n1\n1\nrue-d-athenes 1\n2\n1\nrue-de-moscou 1\n", &"a".into(), &case).expect("should score");
+        assert_eq!(report.score, 1002);
+        assert_eq!(report.details["cars_finished"], 1);
+    }
+
+    #[test]
+    fn one_car_crosses_a_green_light_per_second() {
+        // Both cars start on the same street, the second one crosses a second later
+        let case = Case::parse("3 2 2 2 10\n0 1 a 1\n1 0 b 1\n2 a b\n2 a b\n").expect("should parse");
+        let report = report("1\n1\n1\na 1\n", &"a".into(), &case).expect("should score");
+        assert_eq!(report.score, 10 + 2 + 10 + 1);
+    }
+
+    #[test]
+    fn invalid_schedules_are_rejected() {
+        assert_eq!(challenge_error("1\n0\n1\nrue-de-rome 1\n"), "Street rue-de-rome doesn't end at intersection 0, at line 4, column 1 ('rue-de-rome')");
+        assert_eq!(challenge_error("1\n4\n1\nrue-de-rome 1\n"), "Intersection 4 doesn't exist in this case at line 2, column 1 ('4')");
+        assert_eq!(challenge_error("1\n1\n2\nrue-d-athenes 1\nrue-d-athenes 2\n"), "Street rue-d-athenes is in the schedule of its intersection more than once, at line 5, column 1 ('rue-d-athenes')");
+        assert_eq!(challenge_error("1\n1\n1\nrue-d-athenes 0\n"), "A green light should last between 1 and 6 seconds, at line 4, column 15 ('0')");
+        assert_eq!(challenge_error("1\n1\n100000000000000000\n"), "Only 2 streets end at intersection 1, at line 3, column 1 ('100000000000000000')");
+        assert_eq!(challenge_error("1\n1\n0\n"), "The schedule of intersection 1 should have at least one street, at line 3, column 1 ('0')");
+    }
+}
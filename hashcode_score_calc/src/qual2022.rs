@@ -0,0 +1,397 @@
+use thiserror::Error;
+use std::collections::{BTreeMap, HashMap};
+use crate::{ScoringError, InputFileName, Score, ChallengeScorer, ScoreReport, Span};
+use crate::registry::{InputFile, LoadScorer, RegistryError};
+use crate::input_cases::{CaseNames, InputCases};
+use crate::parse::parse_number;
+
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Qual2022ScoringError {
+    #[error("Missing parameter at {span}")]
+    MissingParameter{span: Span},
+    #[error("{name} is described more than once at {span}")]
+    DescribedTwice{name: String, span: Span},
+    #[error("There is a different number of projects than specified at {span}")]
+    WrongNumberOfProjects{span: Span},
+    #[error("There are more projects than the case has at {span}")]
+    TooManyProjects{span: Span},
+    #[error("Unknown project {name} at {span}")]
+    UnknownProject{name: String, span: Span},
+    #[error("Project {name} is executed more than once at {span}")]
+    ProjectExecutedTwice{name: String, span: Span},
+    #[error("Unknown contributor {name} at {span}")]
+    UnknownContributor{name: String, span: Span},
+    #[error("Project {project} has {roles} roles, but the contributors at {span} fill a different number")]
+    WrongNumberOfContributors{project: String, roles: usize, span: Span},
+    #[error("{name} has more than one role in the same project at {span}")]
+    ContributorTwiceOnProject{name: String, span: Span},
+    #[error("{name} has level {level} in {skill} and no mentor, but the role needs level {required}, at {span}")]
+    SkillTooLow{name: String, skill: String, level: Level, required: Level, span: Span},
+}
+
+impl From<Qual2022ScoringError> for ScoringError {
+    fn from(e: Qual2022ScoringError) -> Self {
+        ScoringError::ChallengeSpecific(Box::new(e))
+    }
+}
+
+
+type ContributorID = u32;
+type ProjectID = u32;
+/// A skill interned when the case is parsed
+type SkillID = u32;
+type Level = u32;
+/// In days
+type Day = u64;
+
+#[derive(Debug)]
+struct Contributor {
+    name: String,
+    /// A skill missing from here has level 0
+    skills: HashMap<SkillID, Level>,
+}
+
+#[derive(Debug)]
+struct Project {
+    name: String,
+    days: Day,
+    score: Score,
+    best_before: Day,
+    /// The skill and level each role needs, in the order contributors are assigned to them
+    roles: Vec<(SkillID, Level)>,
+}
+
+#[derive(Debug)]
+struct Case {
+    skill_names: Vec<String>,
+    contributors: Vec<Contributor>,
+    contributor_ids: HashMap<String, ContributorID>,
+    projects: Vec<Project>,
+    project_ids: HashMap<String, ProjectID>,
+}
+
+impl Case {
+    fn parse(input: &str) -> Result<Self, ScoringError> {
+        use crate::ScoringError::*;
+        use Qual2022ScoringError::*;
+
+        let mut lines = input.lines();
+        let mut next_line = || lines.next().ok_or_else(|| MissingLine(Span::end_of(input)));
+
+        let first_line = next_line()?;
+        let mut first_line_tokens = first_line.split_whitespace();
+        let missing_parameter = || MissingParameter{span: Span::after(input, first_line)};
+        let number_of_contributors = parse_number::<usize>(input, first_line_tokens.next().ok_or_else(missing_parameter)?)?;
+        let number_of_projects = parse_number::<usize>(input, first_line_tokens.next().ok_or_else(missing_parameter)?)?;
+
+        // Every described line is a name followed by numbers
+        let mut name_and_numbers = |count: usize| -> Result<(&str, Vec<u64>), ScoringError> {
+            let line = next_line()?;
+            let mut tokens = line.split_whitespace();
+            let missing_parameter = || MissingParameter{span: Span::after(input, line)};
+            let name = tokens.next().ok_or_else(missing_parameter)?;
+            let numbers = (0..count)
+                .map(|_| parse_number(input, tokens.next().ok_or_else(missing_parameter)?))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok((name, numbers))
+        };
+
+        let mut skill_ids = HashMap::<String, SkillID>::new();
+        let mut skill_names = Vec::new();
+        let mut skill_id = |name: &str| -> SkillID {
+            if let Some(&skill_id) = skill_ids.get(name) {
+                return skill_id;
+            }
+            let skill_id = skill_names.len() as SkillID;
+            skill_ids.insert(name.to_owned(), skill_id);
+            skill_names.push(name.to_owned());
+            skill_id
+        };
+
+        let mut contributors = Vec::with_capacity(number_of_contributors);
+        let mut contributor_ids = HashMap::with_capacity(number_of_contributors);
+        for contributor_id in 0..number_of_contributors {
+            let (name, numbers) = name_and_numbers(1)?;
+            let skills = (0..numbers[0])
+                .map(|_| {
+                    let (skill, level) = name_and_numbers(1)?;
+                    Ok((skill_id(skill), level[0] as Level))
+                })
+                .collect::<Result<HashMap<_, _>, ScoringError>>()?;
+
+            if contributor_ids.insert(name.to_owned(), contributor_id as ContributorID).is_some() {
+                return Err(DescribedTwice{name: name.to_owned(), span: Span::at(input, name)}.into());
+            }
+            contributors.push(Contributor { name: name.to_owned(), skills });
+        }
+
+        let mut projects = Vec::with_capacity(number_of_projects);
+        let mut project_ids = HashMap::with_capacity(number_of_projects);
+        for project_id in 0..number_of_projects {
+            let (name, numbers) = name_and_numbers(4)?;
+            let roles = (0..numbers[3])
+                .map(|_| {
+                    let (skill, level) = name_and_numbers(1)?;
+                    Ok((skill_id(skill), level[0] as Level))
+                })
+                .collect::<Result<Vec<_>, ScoringError>>()?;
+
+            if project_ids.insert(name.to_owned(), project_id as ProjectID).is_some() {
+                return Err(DescribedTwice{name: name.to_owned(), span: Span::at(input, name)}.into());
+            }
+            projects.push(Project {
+                name: name.to_owned(),
+                days: numbers[0],
+                score: numbers[1],
+                best_before: numbers[2],
+                roles,
+            });
+        }
+
+        Ok(Case { skill_names, contributors, contributor_ids, projects, project_ids })
+    }
+}
+
+const CASE_NAMES: &[CaseNames] = &[
+    CaseNames { id: "a", aliases: &["a_an_example", "a_an_example.in", "a_an_example.in.txt"] },
+    CaseNames { id: "b", aliases: &["b_better_start_small", "b_better_start_small.in", "b_better_start_small.in.txt"] },
+    CaseNames { id: "c", aliases: &["c_collaboration", "c_collaboration.in", "c_collaboration.in.txt"] },
+    CaseNames { id: "d", aliases: &["d_dense_schedule", "d_dense_schedule.in", "d_dense_schedule.in.txt"] },
+    CaseNames { id: "e", aliases: &["e_exceptional_skills", "e_exceptional_skills.in", "e_exceptional_skills.in.txt"] },
+    CaseNames { id: "f", aliases: &["f_find_great_mentors", "f_find_great_mentors.in", "f_find_great_mentors.in.txt"] },
+];
+
+/// Runs the projects of a submission in order as their contributors become free, scoring them less a point per day late
+pub struct Qual2022 {
+    cases: InputCases<Case>
+}
+
+impl LoadScorer for Qual2022 {
+    fn load(input_files: Vec<InputFile>) -> Result<Self, RegistryError> {
+        InputCases::load(input_files, CASE_NAMES, Case::parse)
+            .map(|cases| Self { cases })
+    }
+}
+
+impl ChallengeScorer for Qual2022 {
+    fn input_cases(&self) -> Vec<InputFileName> {
+        self.cases.ids()
+    }
+
+    fn resolve_case(&self, case: &InputFileName) -> Result<InputFileName, ScoringError> {
+        self.cases.resolve(case).cloned()
+    }
+
+    fn validate_input(&self, input: &str) -> Result<(), ScoringError> {
+        Case::parse(input).map(|_case| ())
+    }
+
+    fn report(&self, submission: &str, case_name: &InputFileName) -> Result<ScoreReport, ScoringError> {
+        let (case_id, case) = self.cases.get(case_name)?;
+        report(submission, case_id, case)
+    }
+}
+
+/// A project of the submission, with the contributor of each role and the tokens naming them
+struct Assignment<'s> {
+    project_id: ProjectID,
+    contributors: Vec<(ContributorID, &'s str)>,
+}
+
+fn parse_submission<'s>(submission: &'s str, case: &Case) -> Result<Vec<Assignment<'s>>, ScoringError> {
+    use Qual2022ScoringError::*;
+
+    let mut lines = submission.lines().filter(|line| !line.trim().is_empty());
+    let first_line = lines.next().ok_or_else(|| ScoringError::MissingLine(Span::end_of(submission)))?;
+    let number_of_projects = parse_number::<usize>(submission, first_line.trim())?;
+    if number_of_projects > case.projects.len() {
+        return Err(TooManyProjects{span: Span::at(submission, first_line)}.into());
+    }
+
+    let mut executed = vec![false; case.projects.len()];
+    let mut assignments = Vec::with_capacity(number_of_projects);
+    while let Some(project_line) = lines.next() {
+        let name = project_line.trim();
+        let project_id = *case.project_ids.get(name)
+            .ok_or_else(|| UnknownProject{name: name.to_owned(), span: Span::at(submission, name)})?;
+        if std::mem::replace(&mut executed[project_id as usize], true) {
+            return Err(ProjectExecutedTwice{name: name.to_owned(), span: Span::at(submission, name)}.into());
+        }
+
+        let project = &case.projects[project_id as usize];
+        let contributors_line = lines.next().ok_or_else(|| ScoringError::MissingLine(Span::end_of(submission)))?;
+        let contributors = contributors_line.split_whitespace()
+            .map(|name| case.contributor_ids.get(name)
+                .map(|&contributor_id| (contributor_id, name))
+                .ok_or_else(|| UnknownContributor{name: name.to_owned(), span: Span::at(submission, name)}))
+            .collect::<Result<Vec<_>, _>>()?;
+        if contributors.len() != project.roles.len() {
+            return Err(WrongNumberOfContributors{project: project.name.clone(), roles: project.roles.len(),
+                                                 span: Span::at(submission, contributors_line)}.into());
+        }
+        for (i, &(contributor_id, name)) in contributors.iter().enumerate() {
+            if contributors[..i].iter().any(|&(other_id, _)| other_id == contributor_id) {
+                return Err(ContributorTwiceOnProject{name: name.to_owned(), span: Span::at(submission, name)}.into());
+            }
+        }
+
+        assignments.push(Assignment { project_id, contributors });
+    }
+
+    if assignments.len() != number_of_projects {
+        return Err(WrongNumberOfProjects{span: Span::at(submission, first_line)}.into());
+    }
+
+    Ok(assignments)
+}
+
+/// The skill levels and availability of the contributors, as projects are completed
+struct Team<'case> {
+    case: &'case Case,
+    levels: Vec<HashMap<SkillID, Level>>,
+    available_on: Vec<Day>,
+}
+
+impl<'case> Team<'case> {
+    fn new(case: &'case Case) -> Self {
+        Team {
+            case,
+            levels: case.contributors.iter().map(|contributor| contributor.skills.clone()).collect(),
+            available_on: vec![0; case.contributors.len()],
+        }
+    }
+
+    fn level(&self, contributor_id: ContributorID, skill_id: SkillID) -> Level {
+        self.levels[contributor_id as usize].get(&skill_id).copied().unwrap_or(0)
+    }
+
+    /// Checks every role is filled by someone skilled enough, or one level below with a mentor
+    /// on another role of the project
+    fn check_roles(&self, submission: &str, assignment: &Assignment) -> Result<(), Qual2022ScoringError> {
+        let project = &self.case.projects[assignment.project_id as usize];
+        for (&(skill_id, required), &(contributor_id, name)) in project.roles.iter().zip(&assignment.contributors) {
+            let level = self.level(contributor_id, skill_id);
+            let has_mentor = || assignment.contributors.iter()
+                .any(|&(mentor_id, _)| mentor_id != contributor_id && self.level(mentor_id, skill_id) >= required);
+
+            if level < required && !(level + 1 == required && has_mentor()) {
+                return Err(Qual2022ScoringError::SkillTooLow {
+                    name: self.case.contributors[contributor_id as usize].name.clone(),
+                    skill: self.case.skill_names[skill_id as usize].clone(),
+                    level,
+                    required,
+                    span: Span::at(submission, name),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the project as soon as all its contributors are available, returns the day it ends
+    fn complete(&mut self, assignment: &Assignment) -> Day {
+        let project = &self.case.projects[assignment.project_id as usize];
+        let starts_on = assignment.contributors.iter()
+            .map(|&(contributor_id, _)| self.available_on[contributor_id as usize])
+            .max()
+            .unwrap_or(0);
+        let ends_on = starts_on + project.days;
+
+        for (&(skill_id, required), &(contributor_id, _)) in project.roles.iter().zip(&assignment.contributors) {
+            self.available_on[contributor_id as usize] = ends_on;
+
+            // Only a contributor at or under the required level learns from the role
+            let level = self.levels[contributor_id as usize].entry(skill_id).or_insert(0);
+            if *level <= required {
+                *level += 1;
+            }
+        }
+        ends_on
+    }
+}
+
+fn report(submission: &str, case_name: &InputFileName, case: &Case) -> Result<ScoreReport, ScoringError> {
+    let assignments = parse_submission(submission, case)?;
+
+    let mut team = Team::new(case);
+    let mut score: Score = 0;
+    let mut late_projects = 0;
+    for assignment in &assignments {
+        team.check_roles(submission, assignment)?;
+        let ends_on = team.complete(assignment);
+
+        // A late project loses a point for every day past its best before day
+        let project = &case.projects[assignment.project_id as usize];
+        let days_late = ends_on.saturating_sub(project.best_before);
+        if days_late > 0 {
+            late_projects += 1;
+        }
+        score += project.score.saturating_sub(days_late);
+    }
+
+    let mut details = BTreeMap::new();
+    details.insert("projects".to_owned(), assignments.len() as u64);
+    details.insert("late_projects".to_owned(), late_projects);
+
+    Ok(ScoreReport { case: case_name.clone(), score, details })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::qual2022::{Case, report};
+    use crate::ScoringError;
+
+    const EXAMPLE: &str = "3 3\nAnna 1\nC++ 2\nBob 2\nHTML 5\nCSS 5\nMaria 1\nPython 3\n\
+                           Logging 5 10 5 1\nC++ 3\n\
+                           WebServer 7 10 7 2\nHTML 3\nC++ 2\n\
+                           WebChat 10 20 20 2\nPython 3\nHTML 3\n";
+
+    fn challenge_error(submission: &str) -> String {
+        let case = Case::parse(EXAMPLE).expect("should parse");
+        match report(submission, &"a".into(), &case) {
+            Err(ScoringError::ChallengeSpecific(e)) => e.to_string(),
+            other => panic!("Expected a challenge specific error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_example_case() {
+        let case = Case::parse(EXAMPLE).expect("should parse");
+        assert_eq!(case.skill_names, vec!["C++", "HTML", "CSS", "Python"]);
+        assert_eq!(case.contributors[1].skills.len(), 2);
+        assert_eq!(case.projects[1].roles, vec![(1, 3), (0, 2)]);
+    }
+
+    #[test]
+    fn contributors_level_up_on_completion() {
+        let case = Case::parse(EXAMPLE).expect("should parse");
+        // Anna reaches C++ 3 on WebServer, so she can take Logging after it but not before
+        let report = report("2\nWebServer\nBob Anna\nLogging\nAnna\n", &"a".into(), &case).expect("should score");
+        // Logging ends on day 12, 7 days late
+        assert_eq!(report.score, 10 + 3);
+        assert_eq!(report.details["late_projects"], 1);
+
+        assert_eq!(challenge_error("1\nLogging\nAnna\n"), "Anna has level 2 in C++ and no mentor, but the role needs level 3, at line 3, column 1 ('Anna')");
+    }
+
+    #[test]
+    fn mentors_let_a_contributor_one_level_below_take_a_role() {
+        // Bob mentors Anna in HTML, but no one can mentor Maria, who doesn't know it
+        let case = Case::parse("3 1\nAnna 1\nHTML 2\nBob 1\nHTML 3\nMaria 1\nPython 1\n\
+                                Site 1 10 5 2\nHTML 3\nHTML 3\n").expect("should parse");
+        let report = report("1\nSite\nAnna Bob\n", &"a".into(), &case).expect("should score");
+        assert_eq!(report.score, 10);
+
+        assert!(super::report("1\nSite\nMaria Bob\n", &"a".into(), &case).is_err());
+        assert!(super::report("1\nSite\nBob Anna\n", &"a".into(), &case).is_ok());
+    }
+
+    #[test]
+    fn invalid_assignments_are_rejected() {
+        assert_eq!(challenge_error("1\nWebServer\nBob\n"), "Project WebServer has 2 roles, but the contributors at line 3, column 1 ('Bob') fill a different number");
+        assert_eq!(challenge_error("1\nWebServer\nBob Bob\n"), "Bob has more than one role in the same project at line 3, column 5 ('Bob')");
+        assert_eq!(challenge_error("100000000000000000\nWebServer\nBob Anna\n"), "There are more projects than the case has at line 1, column 1 ('100000000000000000')");
+        assert_eq!(challenge_error("1\nWebSite\nBob Anna\n"), "Unknown project WebSite at line 2, column 1 ('WebSite')");
+        assert_eq!(challenge_error("2\nWebServer\nBob Anna\nWebServer\nBob Anna\n"), "Project WebServer is executed more than once at line 4, column 1 ('WebServer')");
+    }
+}
@@ -0,0 +1,185 @@
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::{Challenge, ChallengeDate, ChallengeScorer, ScoringError};
+use crate::qual2016::Qual2016;
+use crate::qual2017::Qual2017;
+use crate::qual2018::Qual2018;
+use crate::qual2019::Qual2019;
+use crate::qual2020::Qual2020;
+use crate::qual2021::Qual2021;
+use crate::qual2022::Qual2022;
+
+#[derive(Error, Debug)]
+pub enum RegistryError {
+    #[error("Couldn't read {}: {source}", .path.display())]
+    Io { path: PathBuf, source: std::io::Error },
+    #[error("Malformed input file {}: {source}", .path.display())]
+    MalformedInput { path: PathBuf, source: ScoringError },
+}
+
+/// An input file read from `<data_dir>/<challenge>/inputs`
+#[derive(Debug)]
+pub struct InputFile {
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+impl InputFile {
+    /// The file name without its extension, e.g. `busy_day` for `busy_day.in`
+    pub fn stem(&self) -> &str {
+        self.path.file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or_default()
+    }
+
+    pub fn file_name(&self) -> &str {
+        self.path.file_name()
+            .and_then(|file_name| file_name.to_str())
+            .unwrap_or_default()
+    }
+
+    pub fn malformed(&self, source: ScoringError) -> RegistryError {
+        RegistryError::MalformedInput { path: self.path.clone(), source }
+    }
+}
+
+const INPUT_FILE_EXTENSIONS: &[&str] = &["in", "txt"];
+
+fn io_error(path: &Path) -> impl FnOnce(std::io::Error) -> RegistryError {
+    let path = path.to_owned();
+    move |source| RegistryError::Io { path, source }
+}
+
+/// Reads every `*.in` and `*.txt` file in `dir`, sorted by file name
+pub fn read_input_files(dir: &Path) -> Result<Vec<InputFile>, RegistryError> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(io_error(dir))? {
+        let path = entry.map_err(io_error(dir))?.path();
+        let is_input_file = matches!(path.extension().and_then(|ext| ext.to_str()),
+                                     Some(ext) if INPUT_FILE_EXTENSIONS.contains(&ext));
+        if path.is_file() && is_input_file {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    paths.into_iter()
+        .map(|path| {
+            let contents = std::fs::read_to_string(&path).map_err(io_error(&path))?;
+            Ok(InputFile { path, contents })
+        })
+        .collect()
+}
+
+/// A scorer built from the input files of its challenge
+pub trait LoadScorer: ChallengeScorer + Sized + 'static {
+    fn load(input_files: Vec<InputFile>) -> Result<Self, RegistryError>;
+}
+
+type ScorerLoader = fn(Vec<InputFile>) -> Result<Box<dyn ChallengeScorer>, RegistryError>;
+
+fn load<S: LoadScorer>(inputs: Vec<InputFile>) -> Result<Box<dyn ChallengeScorer>, RegistryError> {
+    Ok(Box::new(S::load(inputs)?))
+}
+
+struct ChallengeDefinition {
+    directory: &'static str,
+    date: ChallengeDate,
+    load: ScorerLoader,
+}
+
+const KNOWN_CHALLENGES: &[ChallengeDefinition] = &[
+    ChallengeDefinition {
+        directory: "2020qual",
+        date: ChallengeDate::Qualification(2020),
+        load: load::<Qual2020>,
+    },
+    ChallengeDefinition {
+        directory: "2016qual",
+        date: ChallengeDate::Qualification(2016),
+        load: load::<Qual2016>,
+    },
+    ChallengeDefinition {
+        directory: "2017qual",
+        date: ChallengeDate::Qualification(2017),
+        load: load::<Qual2017>,
+    },
+    ChallengeDefinition {
+        directory: "2018qual",
+        date: ChallengeDate::Qualification(2018),
+        load: load::<Qual2018>,
+    },
+    ChallengeDefinition {
+        directory: "2019qual",
+        date: ChallengeDate::Qualification(2019),
+        load: load::<Qual2019>,
+    },
+    ChallengeDefinition {
+        directory: "2021qual",
+        date: ChallengeDate::Qualification(2021),
+        load: load::<Qual2021>,
+    },
+    ChallengeDefinition {
+        directory: "2022qual",
+        date: ChallengeDate::Qualification(2022),
+        load: load::<Qual2022>,
+    },
+];
+
+/// The challenges whose input files were found under a data directory.
+///
+/// Each challenge is looked up in `<data_dir>/<challenge>/inputs`, challenges without
+/// such a directory are not registered.
+pub struct ChallengeRegistry {
+    data_dir: PathBuf,
+    challenges: Vec<Challenge>,
+}
+
+impl ChallengeRegistry {
+    pub fn load<P: Into<PathBuf>>(data_dir: P) -> Result<Self, RegistryError> {
+        let mut registry = Self { data_dir: data_dir.into(), challenges: Vec::new() };
+        registry.reload()?;
+        Ok(registry)
+    }
+
+    /// Rescans the data directory, keeping the current challenges if any input is malformed
+    pub fn reload(&mut self) -> Result<(), RegistryError> {
+        let mut challenges = Vec::with_capacity(KNOWN_CHALLENGES.len());
+        for definition in KNOWN_CHALLENGES {
+            let inputs_dir = self.data_dir.join(definition.directory).join("inputs");
+            if !inputs_dir.is_dir() {
+                continue;
+            }
+
+            let scorer = (definition.load)(read_input_files(&inputs_dir)?)?;
+            challenges.push(Challenge { scorer, date: definition.date.clone() });
+        }
+
+        self.challenges = challenges;
+        Ok(())
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    pub fn challenges(&self) -> &[Challenge] {
+        &self.challenges
+    }
+
+    pub fn get(&self, date: &ChallengeDate) -> Option<&Challenge> {
+        self.challenges.iter().find(|c| &c.date == date)
+    }
+
+    pub fn into_challenges(self) -> Vec<Challenge> {
+        self.challenges
+    }
+}
+
+/// `HASHCODE_DATA_DIR` if set, otherwise the assets shipped with this crate
+pub fn default_data_dir() -> PathBuf {
+    std::env::var_os("HASHCODE_DATA_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("assets"))
+}
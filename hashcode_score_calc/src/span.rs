@@ -0,0 +1,96 @@
+use std::fmt::{self, Display};
+use serde_derive::{Serialize, Deserialize};
+
+const MAX_TOKEN_LENGTH: usize = 32;
+
+/// A position in an input or submission file, and the token found there
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    /// Empty when nothing was found at this position
+    pub token: String,
+}
+
+impl Span {
+    /// The position of the first token in `fragment`, which should be a slice of `text`
+    pub fn at(text: &str, fragment: &str) -> Self {
+        let rest = text[Self::offset_of(text, fragment)..].trim_start();
+        let token = rest.split_whitespace().next().unwrap_or_default();
+        Self::new(text, text.len() - rest.len(), token)
+    }
+
+    /// The position right after `fragment`, e.g. where a missing value was expected on a line
+    pub fn after(text: &str, fragment: &str) -> Self {
+        let offset = (Self::offset_of(text, fragment) + fragment.len()).min(text.len());
+        Self::new(text, offset, "")
+    }
+
+    pub fn end_of(text: &str) -> Self {
+        Self::after(text, text)
+    }
+
+    /// Fragments from outside of `text` are positioned at its end
+    fn offset_of(text: &str, fragment: &str) -> usize {
+        let text_start = text.as_ptr() as usize;
+        let fragment_start = fragment.as_ptr() as usize;
+        if fragment_start >= text_start && fragment_start + fragment.len() <= text_start + text.len() {
+            fragment_start - text_start
+        } else {
+            text.len()
+        }
+    }
+
+    fn new(text: &str, offset: usize, token: &str) -> Self {
+        let before = &text[..offset];
+        Self {
+            line: before.matches('\n').count() + 1,
+            column: before.rsplit('\n').next().unwrap_or_default().chars().count() + 1,
+            token: token.chars().take(MAX_TOKEN_LENGTH).collect(),
+        }
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        if self.token.is_empty() {
+            write!(f, "line {}, column {}", self.line, self.column)
+        } else {
+            write!(f, "line {}, column {} ('{}')", self.line, self.column, self.token)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Span;
+
+    #[test]
+    fn span_of_token() {
+        let text = "2\n1 3\n5 x 3\n";
+        let token = text.lines().nth(2).unwrap().split_whitespace().nth(1).unwrap();
+        let span = Span::at(text, token);
+        assert_eq!(span, Span { line: 3, column: 3, token: "x".to_owned() });
+        assert_eq!(span.to_string(), "line 3, column 3 ('x')");
+    }
+
+    #[test]
+    fn span_skips_leading_whitespace() {
+        let text = "4\n    6 L 0";
+        assert_eq!(Span::at(text, &text[1..]), Span { line: 2, column: 5, token: "6".to_owned() });
+    }
+
+    #[test]
+    fn span_after_line() {
+        let text = "2\n1\n5 2 3";
+        let line = text.lines().nth(1).unwrap();
+        let span = Span::after(text, line);
+        assert_eq!(span, Span { line: 2, column: 2, token: String::new() });
+        assert_eq!(span.to_string(), "line 2, column 2");
+    }
+
+    #[test]
+    fn span_at_end_of_file() {
+        assert_eq!(Span::end_of("2\n1 3\n"), Span { line: 3, column: 1, token: String::new() });
+    }
+}
@@ -0,0 +1,23 @@
+// Not every test uses every helper
+#![allow(dead_code)]
+
+use hashcode_score_calc::{get_challenges, ChallengeDate, ScoreReport, Year};
+use hashcode_score_calc::registry::{read_input_files, LoadScorer};
+use std::path::Path;
+
+/// The challenge scorer loaded from the inputs shipped in `assets/<directory>/inputs`
+pub fn load_scorer<S: LoadScorer>(directory: &str) -> S {
+    let inputs_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets").join(directory).join("inputs");
+    S::load(read_input_files(&inputs_dir).expect("Should read the inputs"))
+        .expect("Inputs should be valid")
+}
+
+/// Reports on `submission` with the registered qualification scorer of `year`
+pub fn report_through_registry(year: Year, submission: &str, case: &str) -> ScoreReport {
+    let challenges = get_challenges().expect("Should load the challenges");
+    let challenge = challenges.iter()
+        .find(|c| c.date == ChallengeDate::Qualification(year))
+        .unwrap_or_else(|| panic!("qualification {} should be registered", year));
+
+    challenge.scorer.report(submission, &case.into()).expect("Should succeed")
+}
@@ -1,4 +1,11 @@
-use hashcode_score_calc::qual2016;
+mod common;
+
+use hashcode_score_calc::ChallengeScorer;
+use hashcode_score_calc::qual2016::Qual2016;
+
+fn qual2016() -> Qual2016 {
+    common::load_scorer("2016qual")
+}
 
 
 #[test]
@@ -6,7 +13,7 @@ fn example_input() {
     let submission = include_str!("../assets/2016qual/submissions/example_submission.txt");
     let _input = include_str!("../assets/2016qual/inputs/example.in");
 
-    assert_eq!(qual2016::score(submission, &"example".into()).expect("Should succeed"), 194);
+    assert_eq!(qual2016().score(submission, &"example".into()).expect("Should succeed"), 194);
 }
 
 
@@ -14,14 +21,14 @@ fn example_input() {
 fn mother_of_all_warehouses_solo_single_delivery_of_prezi_group() {
     let submission = include_str!("../assets/2016qual/submissions/mother_of_all_warehouses.100.out");
 
-    assert_eq!(qual2016::score(submission, &"mother_of_all_warehouses".into()).expect("Should succeed"), 100);
+    assert_eq!(qual2016().score(submission, &"mother_of_all_warehouses".into()).expect("Should succeed"), 100);
 }
 
 #[test]
 fn mother_of_all_warehouses_double_drone_single_delivery_each_of_prezi_group() {
     let submission = include_str!("../assets/2016qual/submissions/mother_of_all_warehouses.200.out");
 
-    assert_eq!(qual2016::score(submission, &"mother_of_all_warehouses".into()).expect("Should succeed"), 200);
+    assert_eq!(qual2016().score(submission, &"mother_of_all_warehouses".into()).expect("Should succeed"), 200);
 }
 
 
@@ -29,7 +36,7 @@ fn mother_of_all_warehouses_double_drone_single_delivery_each_of_prezi_group() {
 fn mother_of_all_warehouses_multiple_deliveries_per_drone_of_prezi_group() {
     let submission = include_str!("../assets/2016qual/submissions/mother_of_all_warehouses.2100.out");
 
-    assert_eq!(qual2016::score(submission, &"mother_of_all_warehouses".into()).expect("Should succeed"), 2100);
+    assert_eq!(qual2016().score(submission, &"mother_of_all_warehouses".into()).expect("Should succeed"), 2100);
 }
 
 #[test]
@@ -41,6 +48,76 @@ fn should_not_fail() {
     6 D 331 541 1
     "#;
 
-    assert_eq!(qual2016::score(submission, &"mother_of_all_warehouses".into()).expect("Should succeed"), 0);
+    assert_eq!(qual2016().score(submission, &"mother_of_all_warehouses".into()).expect("Should succeed"), 0);
+}
+
+
+#[test]
+fn unknown_command_is_located() {
+    use hashcode_score_calc::{ScoringError, Span};
+
+    let submission = "2\n0 L 0 0 1\n0 X 0 0 1\n";
+
+    let error = qual2016().score(submission, &"example".into()).expect_err("X isn't a command");
+    match &error {
+        ScoringError::SubmissionFileError { span, .. } =>
+            assert_eq!(span, &Span { line: 3, column: 3, token: "X".to_owned() }),
+        other => panic!("Expected a submission file error, got {:?}", other),
+    }
+    assert_eq!(error.to_string(),
+               "Error parsing the submission file at line 3, column 3 ('X'): Expected a command, one of L, U, D or W");
+}
+
+#[test]
+fn overflowing_load_weight_is_an_error() {
+    // 700 items of product 0 weigh 70000, more than a u16 holds
+    let submission = "1\n0 L 0 0 700\n";
+
+    let error = qual2016().score(submission, &"example".into()).expect_err("The drone can't carry that");
+    assert_eq!(error.to_string(), "Challenge Specific: Drone 0 carry too much");
+}
+
+#[test]
+fn unloading_frees_the_drone() {
+    // Product 0 weighs 100 and the payload is 500, so the second load only fits after the unload.
+    // Used to fail with "Drone 0 carry too much" when unloading left the items on the drone
+    let submission = "4\n0 L 0 0 5\n0 U 0 0 5\n0 L 0 0 5\n0 W 1\n";
+
+    assert_eq!(qual2016().score(submission, &"example".into()).expect("Should succeed"), 0);
+}
+
+#[test]
+fn unloaded_items_cannot_be_delivered() {
+    // Used to score 86, completing order 1 with the item left in warehouse 0
+    let submission = "3\n0 L 0 0 1\n0 U 0 0 1\n0 D 1 0 1\n";
+
+    let error = qual2016().score(submission, &"example".into()).expect_err("The drone is empty");
+    assert_eq!(error.to_string(), "Challenge Specific: You're trying to unload more then you have of product 0 from drone number 0");
+}
+
+#[test]
+fn commands_with_zero_items_are_rejected() {
+    let submission = "2\n0 L 0 0 1\n1 D 1 0 0\n";
+
+    let error = qual2016().score(submission, &"example".into()).expect_err("Nothing to deliver");
+    assert_eq!(error.to_string(), "Challenge Specific: Load, Deliver and Unload should have a positive number of items, drone 1");
+}
+
+#[test]
+fn commands_for_a_missing_drone_are_rejected() {
+    // The example has drones 0 to 2
+    let submission = "1\n3 W 1\n";
+
+    let error = qual2016().score(submission, &"example".into()).expect_err("There is no drone 3");
+    assert_eq!(error.to_string(), "Challenge Specific: There is a command for drone 3, which is not present in this case");
 }
 
+#[test]
+fn only_deliveries_before_the_last_turn_score() {
+    // Loading takes turn 0 and the flight to order 1 takes five turns, so waiting 43 turns delivers on turn 49 of 50
+    let on_the_last_turn = "3\n0 L 0 0 1\n0 W 43\n0 D 1 0 1\n";
+    let after_the_last_turn = "3\n0 L 0 0 1\n0 W 44\n0 D 1 0 1\n";
+
+    assert_eq!(qual2016().score(on_the_last_turn, &"example".into()).expect("Should succeed"), 2);
+    assert_eq!(qual2016().score(after_the_last_turn, &"example".into()).expect("Should succeed"), 0);
+}
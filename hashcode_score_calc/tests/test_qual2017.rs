@@ -0,0 +1,59 @@
+mod common;
+
+use hashcode_score_calc::ChallengeScorer;
+use hashcode_score_calc::qual2017::Qual2017;
+
+fn qual2017() -> Qual2017 {
+    common::load_scorer("2017qual")
+}
+
+
+#[test]
+fn example_input() {
+    let submission = include_str!("../assets/2017qual/submissions/example_submission.txt");
+
+    assert_eq!(qual2017().score(submission, &"example.in".into()).expect("Should succeed"), 462500);
+}
+
+
+#[test]
+fn example_report_through_challenge_scorer() {
+    let submission = include_str!("../assets/2017qual/submissions/example_submission.txt");
+    let report = common::report_through_registry(2017, submission, "example");
+    assert_eq!(report.score, 462500);
+    assert_eq!(report.details["cache_servers_used"], 3);
+    assert_eq!(report.details["requests_from_cache"], 2500);
+}
+
+#[test]
+fn the_fastest_cache_server_holding_the_video_serves_it() {
+    // Endpoint 0 makes 1000 of the 4000 requests for video 1, 1000ms from the data center
+    assert_eq!(qual2017().score("1\n1 1\n", &"example".into()).expect("Should succeed"), 175000);
+    assert_eq!(qual2017().score("2\n0 1\n1 1\n", &"example".into()).expect("Should succeed"), 225000);
+}
+
+#[test]
+fn endpoints_without_cache_servers_save_nothing() {
+    // Video 0 is only requested by endpoint 1, which isn't connected to any cache server
+    let report = qual2017().report("1\n0 0\n", &"example".into()).expect("Should succeed");
+    assert_eq!(report.score, 0);
+    assert_eq!(report.details["requests_from_cache"], 0);
+
+    let report = qual2017().report("0\n", &"example".into()).expect("Should succeed");
+    assert_eq!((report.score, report.details["cache_servers_used"]), (0, 0));
+}
+
+#[test]
+fn a_cache_server_can_be_filled_up() {
+    // Videos 0 and 1 take exactly the 100MB of a cache server
+    assert_eq!(qual2017().score("1\n0 0 1\n", &"example".into()).expect("Should succeed"), 225000);
+
+    let error = qual2017().score("1\n0 0 1 3\n", &"example".into()).expect_err("130MB don't fit");
+    assert_eq!(error.to_string(), "Challenge Specific: Cache server 0 holds 130MB of videos, more than its 100MB capacity");
+}
+
+#[test]
+fn cache_server_count_must_match_the_lines() {
+    let error = qual2017().score("2\n0 1\n", &"example".into()).expect_err("Only one cache server is described");
+    assert_eq!(error.to_string(), "Challenge Specific: There is a different number of cache servers than specified at line 1, column 1 ('2')");
+}
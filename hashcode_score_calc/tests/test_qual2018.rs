@@ -0,0 +1,52 @@
+mod common;
+
+use hashcode_score_calc::ChallengeScorer;
+use hashcode_score_calc::qual2018::Qual2018;
+
+fn qual2018() -> Qual2018 {
+    common::load_scorer("2018qual")
+}
+
+
+#[test]
+fn example_input() {
+    let submission = include_str!("../assets/2018qual/submissions/example_submission.txt");
+
+    assert_eq!(qual2018().score(submission, &"a_example".into()).expect("Should succeed"), 10);
+}
+
+
+#[test]
+fn example_report_through_challenge_scorer() {
+    let submission = include_str!("../assets/2018qual/submissions/example_submission.txt");
+    let report = common::report_through_registry(2018, submission, "a");
+    assert_eq!(report.score, 10);
+    assert_eq!(report.details["rides_on_time"], 3);
+    assert_eq!(report.details["bonuses"], 1);
+}
+
+#[test]
+fn waiting_for_the_earliest_start_earns_the_bonus() {
+    // Ride 0 is reached on step 0 and can't start before step 2
+    let report = qual2018().report("1 0\n0\n", &"a".into()).expect("Should succeed");
+    assert_eq!(report.score, 4 + 2);
+    assert_eq!(report.details["bonuses"], 1);
+}
+
+#[test]
+fn a_ride_finishing_on_its_latest_finish_is_on_time() {
+    // After ride 0 the vehicle is at (1, 3) on step 6, ride 1 finishes on step 9, ride 2 on step 12
+    let on_time = qual2018().report("2 0 1\n0\n", &"a".into()).expect("Should succeed");
+    assert_eq!(on_time.score, 4 + 2 + 2);
+    assert_eq!(on_time.details["rides_on_time"], 2);
+
+    let late = qual2018().report("2 0 2\n0\n", &"a".into()).expect("Should succeed");
+    assert_eq!(late.score, 4 + 2);
+    assert_eq!(late.details["rides_on_time"], 1);
+}
+
+#[test]
+fn ride_count_must_match_the_rides() {
+    let error = qual2018().score("2 0\n0\n", &"a".into()).expect_err("Only one ride is listed");
+    assert_eq!(error.to_string(), "Challenge Specific: There is a different number of rides than specified at line 1, column 1 ('2')");
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use hashcode_score_calc::ChallengeScorer;
+use hashcode_score_calc::qual2019::Qual2019;
+
+fn qual2019() -> Qual2019 {
+    common::load_scorer("2019qual")
+}
+
+
+#[test]
+fn example_input() {
+    let submission = include_str!("../assets/2019qual/submissions/example_submission.txt");
+
+    assert_eq!(qual2019().score(submission, &"a_example".into()).expect("Should succeed"), 2);
+}
+
+
+#[test]
+fn example_report_through_challenge_scorer() {
+    let submission = include_str!("../assets/2019qual/submissions/example_submission.txt");
+    let report = common::report_through_registry(2019, submission, "a");
+    assert_eq!(report.score, 2);
+    assert_eq!(report.details["slides"], 3);
+}
+
+#[test]
+fn a_single_slide_has_no_transitions() {
+    let report = qual2019().report("1\n0\n", &"a".into()).expect("Should succeed");
+    assert_eq!(report.score, 0);
+    assert_eq!(report.details["slides"], 1);
+}
+
+#[test]
+fn a_vertical_pair_has_the_tags_of_both_photos() {
+    // Photos 1 and 2 make a slide tagged selfie, smile and garden, which shares only garden with photo 3
+    assert_eq!(qual2019().score("2\n1 2\n3\n", &"a".into()).expect("Should succeed"), 1);
+    // Without a common tag, a transition is worth nothing
+    assert_eq!(qual2019().score("2\n0\n1 2\n", &"a".into()).expect("Should succeed"), 0);
+}
+
+#[test]
+fn slides_hold_one_or_two_photos() {
+    let error = qual2019().score("1\n1 2 3\n", &"a".into()).expect_err("Three photos on a slide");
+    assert_eq!(error.to_string(), "Challenge Specific: A slide should have a horizontal photo or two vertical photos, at line 2, column 1 ('1')");
+
+    let error = qual2019().score("1\nV\n", &"a".into()).expect_err("Photos are numbers");
+    assert_eq!(error.to_string(), "Expected a number at line 2, column 1 ('V')");
+}
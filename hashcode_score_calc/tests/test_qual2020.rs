@@ -1,4 +1,11 @@
-use hashcode_score_calc::qual2020;
+mod common;
+
+use hashcode_score_calc::ChallengeScorer;
+use hashcode_score_calc::qual2020::Qual2020;
+
+fn qual2020() -> Qual2020 {
+    common::load_scorer("2020qual")
+}
 
 
 #[test]
@@ -6,6 +13,42 @@ fn example_input() {
     let submission = include_str!("../assets/2020qual/submissions/example_submission.txt");
     let _input = include_str!("../assets/2020qual/inputs/a_example.txt");
 
-    assert_eq!(qual2020::score(submission, &"a_example".into()).expect("Should succeed"), 16);
+    assert_eq!(qual2020().score(submission, &"a_example".into()).expect("Should succeed"), 16);
+}
+
+
+#[test]
+fn example_report_through_challenge_scorer() {
+    let submission = include_str!("../assets/2020qual/submissions/example_submission.txt");
+    let report = common::report_through_registry(2020, submission, "a");
+    assert_eq!(report.score, 16);
+    assert_eq!(report.details["signed_up_libraries"], 2);
+    assert_eq!(report.details["scanned_books"], 5);
+}
+
+#[test]
+fn only_books_scanned_before_the_deadline_count() {
+    // Library 1 is signed up on day 5 of 7, so it scans books 5 and 3 at one a day and never gets to 2 and 0
+    let report = qual2020().report("2\n0 1\n0\n1 4\n5 3 2 0\n", &"a".into()).expect("Should succeed");
+    assert_eq!(report.score, 1 + 4 + 6);
+    assert_eq!(report.details["scanned_books"], 3);
+}
+
+#[test]
+fn books_scanned_twice_score_once() {
+    let report = qual2020().report("2\n0 2\n0 1\n1 1\n0\n", &"a".into()).expect("Should succeed");
+    assert_eq!(report.score, 1 + 2);
+    assert_eq!(report.details["scanned_books"], 2);
 }
 
+#[test]
+fn unknown_libraries_and_books_are_located() {
+    let error = qual2020().score("1\n2 1\n0\n", &"a".into()).expect_err("The case has libraries 0 and 1");
+    assert_eq!(error.to_string(), "Challenge Specific: You're trying to sign up a library that doesn't exist at line 2, column 1 ('2')");
+
+    let error = qual2020().score("1\n1 1\n1\n", &"a".into()).expect_err("Library 1 doesn't have book 1");
+    assert_eq!(error.to_string(), "Challenge Specific: You're trying to scan a book that doesn't exist in the library at line 3, column 1 ('1')");
+
+    let error = qual2020().score("3\n", &"a".into()).expect_err("The case has two libraries");
+    assert_eq!(error.to_string(), "Challenge Specific: You're trying to register more libraries than exist in this case at line 1, column 1 ('3')");
+}
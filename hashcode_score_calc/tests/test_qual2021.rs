@@ -0,0 +1,59 @@
+mod common;
+
+use hashcode_score_calc::ChallengeScorer;
+use hashcode_score_calc::qual2021::Qual2021;
+
+fn qual2021() -> Qual2021 {
+    common::load_scorer("2021qual")
+}
+
+
+#[test]
+fn example_input() {
+    let submission = include_str!("../assets/2021qual/submissions/example_submission.txt");
+
+    assert_eq!(qual2021().score(submission, &"a.txt".into()).expect("Should succeed"), 1002);
+}
+
+
+#[test]
+fn example_report_through_challenge_scorer() {
+    let submission = include_str!("../assets/2021qual/submissions/example_submission.txt");
+    let report = common::report_through_registry(2021, submission, "a");
+    assert_eq!(report.score, 1002);
+    assert_eq!(report.details["cars_finished"], 1);
+    assert_eq!(report.details["early_arrival_bonus"], 2);
+}
+
+#[test]
+fn a_car_finishing_on_the_last_second_earns_only_the_bonus() {
+    // Every light car 0 meets is always green, it finishes rue-de-rome on second 6 of 6
+    let submission = "3\n0\n1\nrue-de-londres 1\n1\n1\nrue-d-amsterdam 1\n2\n1\nrue-de-moscou 1\n";
+
+    let report = qual2021().report(submission, &"a".into()).expect("Should succeed");
+    assert_eq!(report.score, 1000);
+    assert_eq!((report.details["cars_finished"], report.details["early_arrival_bonus"]), (1, 0));
+}
+
+#[test]
+fn a_car_finishing_after_the_last_second_earns_nothing() {
+    // Sharing intersection 1 with rue-d-athenes holds car 0 a second, so it would finish on second 7.
+    // Car 1 crosses from rue-d-athenes on second 1 and finishes on second 5
+    let submission = "3\n0\n1\nrue-de-londres 1\n1\n2\nrue-d-amsterdam 1\nrue-d-athenes 1\n2\n1\nrue-de-moscou 1\n";
+
+    let report = qual2021().report(submission, &"a".into()).expect("Should succeed");
+    assert_eq!(report.score, 1000 + 1);
+    assert_eq!(report.details["cars_finished"], 1);
+}
+
+#[test]
+fn schedule_lines_are_checked() {
+    let error = qual2021().score("2\n0\n1\nrue-de-londres 1\n0\n1\nrue-de-londres 1\n", &"a".into()).expect_err("Intersection 0 is scheduled twice");
+    assert_eq!(error.to_string(), "Challenge Specific: Intersection 0 has more than one schedule, at line 5, column 1 ('0')");
+
+    let error = qual2021().score("1\n0\n1\nrue-de-lyon 1\n", &"a".into()).expect_err("There is no rue-de-lyon");
+    assert_eq!(error.to_string(), "Challenge Specific: Unknown street rue-de-lyon at line 4, column 1 ('rue-de-lyon')");
+
+    let error = qual2021().score("1\n0\n1\nrue-de-londres\n", &"a".into()).expect_err("The duration is missing");
+    assert_eq!(error.to_string(), "Challenge Specific: A schedule line should have a street name and a duration, at line 4, column 1 ('rue-de-londres')");
+}
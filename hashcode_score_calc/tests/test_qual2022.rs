@@ -0,0 +1,50 @@
+mod common;
+
+use hashcode_score_calc::ChallengeScorer;
+use hashcode_score_calc::qual2022::Qual2022;
+
+fn qual2022() -> Qual2022 {
+    common::load_scorer("2022qual")
+}
+
+
+#[test]
+fn example_input() {
+    let submission = include_str!("../assets/2022qual/submissions/example_submission.txt");
+
+    assert_eq!(qual2022().score(submission, &"a_an_example".into()).expect("Should succeed"), 33);
+}
+
+
+#[test]
+fn example_report_through_challenge_scorer() {
+    let submission = include_str!("../assets/2022qual/submissions/example_submission.txt");
+    let report = common::report_through_registry(2022, submission, "a");
+    assert_eq!(report.score, 33);
+    assert_eq!(report.details["projects"], 3);
+    assert_eq!(report.details["late_projects"], 1);
+}
+
+#[test]
+fn a_project_ending_on_its_best_before_day_scores_in_full() {
+    let report = qual2022().report("1\nWebServer\nBob Anna\n", &"a".into()).expect("Should succeed");
+    assert_eq!(report.score, 10);
+    assert_eq!(report.details["late_projects"], 0);
+}
+
+#[test]
+fn a_project_waits_for_its_contributors() {
+    // Bob is on WebChat until day 10, so WebServer ends on day 17, 10 days late for a score of 10
+    let report = qual2022().report("2\nWebChat\nMaria Bob\nWebServer\nBob Anna\n", &"a".into()).expect("Should succeed");
+    assert_eq!(report.score, 20);
+    assert_eq!(report.details["late_projects"], 1);
+}
+
+#[test]
+fn contributors_and_project_count_are_checked() {
+    let error = qual2022().score("1\nWebServer\nBob Carl\n", &"a".into()).expect_err("There is no Carl");
+    assert_eq!(error.to_string(), "Challenge Specific: Unknown contributor Carl at line 3, column 5 ('Carl')");
+
+    let error = qual2022().score("2\nWebServer\nBob Anna\n", &"a".into()).expect_err("Only one project is listed");
+    assert_eq!(error.to_string(), "Challenge Specific: There is a different number of projects than specified at line 1, column 1 ('2')");
+}
@@ -0,0 +1,66 @@
+use hashcode_score_calc::{ChallengeRegistry, ChallengeDate, RegistryError};
+use std::path::{Path, PathBuf};
+
+fn scratch_data_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("hashcode_registry_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("2016qual/inputs")).expect("Should create the data dir");
+    dir
+}
+
+#[test]
+fn discovers_shipped_inputs() {
+    let registry = ChallengeRegistry::load(Path::new(env!("CARGO_MANIFEST_DIR")).join("assets"))
+        .expect("Shipped inputs should be valid");
+
+    let qual2016 = registry.get(&ChallengeDate::Qualification(2016)).expect("2016 should be registered");
+    assert_eq!(qual2016.input_file_names(),
+               vec!["busy_day".into(), "example".into(), "mother_of_all_warehouses".into(), "redundancy".into()]);
+
+    let qual2017 = registry.get(&ChallengeDate::Qualification(2017)).expect("2017 should be registered");
+    assert_eq!(qual2017.input_file_names(), vec!["example".into()]);
+
+    let qual2018 = registry.get(&ChallengeDate::Qualification(2018)).expect("2018 should be registered");
+    assert_eq!(qual2018.input_file_names(), vec!["a".into()]);
+
+    let qual2019 = registry.get(&ChallengeDate::Qualification(2019)).expect("2019 should be registered");
+    assert_eq!(qual2019.input_file_names(), vec!["a".into()]);
+
+    let qual2020 = registry.get(&ChallengeDate::Qualification(2020)).expect("2020 should be registered");
+    assert_eq!(qual2020.input_file_names(),
+               vec!["a".into(), "b".into(), "c".into(), "d".into(), "e".into(), "f".into()]);
+
+    let qual2021 = registry.get(&ChallengeDate::Qualification(2021)).expect("2021 should be registered");
+    assert_eq!(qual2021.input_file_names(), vec!["a".into()]);
+
+    let qual2022 = registry.get(&ChallengeDate::Qualification(2022)).expect("2022 should be registered");
+    assert_eq!(qual2022.input_file_names(), vec!["a".into()]);
+}
+
+#[test]
+fn skips_challenges_without_inputs_directory() {
+    let data_dir = scratch_data_dir("partial");
+    std::fs::write(data_dir.join("2016qual/inputs/example.in"),
+                   include_str!("../assets/2016qual/inputs/example.in")).unwrap();
+    // Not an input file, should be ignored
+    std::fs::write(data_dir.join("2016qual/inputs/README.md"), "notes").unwrap();
+
+    let registry = ChallengeRegistry::load(&data_dir).expect("Should load");
+    assert_eq!(registry.challenges().len(), 1);
+    assert_eq!(registry.challenges()[0].input_file_names(), vec!["example".into()]);
+
+    std::fs::remove_dir_all(data_dir).unwrap();
+}
+
+#[test]
+fn malformed_input_is_an_error() {
+    let data_dir = scratch_data_dir("malformed");
+    std::fs::write(data_dir.join("2016qual/inputs/broken.in"), "100 100 three").unwrap();
+
+    match ChallengeRegistry::load(&data_dir) {
+        Err(RegistryError::MalformedInput { path, .. }) => assert!(path.ends_with("broken.in")),
+        other => panic!("Expected a malformed input error, got {:?}", other.map(|r| r.challenges().len())),
+    }
+
+    std::fs::remove_dir_all(data_dir).unwrap();
+}
@@ -0,0 +1,194 @@
+use hashcode_score_calc::{ChallengeDate, ChallengeRegistry, InputFileName, Score};
+use hashcode_score_calc::registry::default_data_dir;
+use serde_derive::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "Scores Hash Code submissions offline
+
+Usage:
+    hashcode-score [--json] [--data-dir <dir>] <challenge> <case> <output file>
+    hashcode-score [--json] [--data-dir <dir>] <challenge> <outputs directory>
+
+<challenge> is an id such as qual2016 or qual2020. The output files of a directory are
+matched to a case by their name, e.g. busy_day.out or a_example.txt.
+
+Exits with 1 when a submission is invalid, and with 2 on usage errors.";
+
+const EXIT_INVALID_SUBMISSION: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+
+enum Outputs {
+    Single { case: InputFileName, file: PathBuf },
+    Directory(PathBuf),
+}
+
+struct Args {
+    json: bool,
+    data_dir: PathBuf,
+    challenge: ChallengeDate,
+    outputs: Outputs,
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Args, String> {
+    let mut json = false;
+    let mut data_dir = default_data_dir();
+    let mut positional = Vec::new();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--data-dir" => data_dir = args.next().ok_or("--data-dir needs a directory")?.into(),
+            "-h" | "--help" => return Err(USAGE.to_owned()),
+            flag if flag.starts_with("--") => return Err(format!("Unknown option {}\n\n{}", flag, USAGE)),
+            _ => positional.push(arg),
+        }
+    }
+
+    let challenge = positional.get(0).ok_or(USAGE)?
+        .parse::<ChallengeDate>()
+        .map_err(|e| e.to_string())?;
+
+    let outputs = match positional.as_slice() {
+        [_, dir] => Outputs::Directory(dir.into()),
+        [_, case, file] => Outputs::Single { case: case.as_str().into(), file: file.into() },
+        _ => return Err(USAGE.to_owned()),
+    };
+
+    Ok(Args { json, data_dir, challenge, outputs })
+}
+
+#[derive(Serialize)]
+struct CaseResult {
+    case: InputFileName,
+    file: PathBuf,
+    score: Option<Score>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    challenge: String,
+    results: Vec<CaseResult>,
+    /// The sum of the best score of every case
+    total: Score,
+}
+
+impl Summary {
+    fn new(challenge: &ChallengeDate, results: Vec<CaseResult>) -> Self {
+        let mut best_per_case = HashMap::<&InputFileName, Score>::new();
+        for result in &results {
+            if let Some(score) = result.score {
+                let best = best_per_case.entry(&result.case).or_default();
+                *best = (*best).max(score);
+            }
+        }
+        let total = best_per_case.values().sum();
+
+        Self { challenge: challenge.id(), results, total }
+    }
+
+    fn has_invalid_submission(&self) -> bool {
+        self.results.iter().any(|result| result.error.is_some())
+    }
+
+    fn print_text(&self) {
+        for result in &self.results {
+            match (&result.score, &result.error) {
+                (Some(score), _) => println!("{}\t{}\t{}", result.case.as_str(), result.file.display(), score),
+                (None, Some(error)) => println!("{}\t{}\tinvalid: {}", result.case.as_str(), result.file.display(), error),
+                (None, None) => unreachable!("A result has either a score or an error"),
+            }
+        }
+        println!("total\t\t{}", self.total);
+    }
+}
+
+fn score_file(challenge: &hashcode_score_calc::Challenge, case: InputFileName, file: PathBuf) -> CaseResult {
+    let scored = std::fs::read_to_string(&file)
+        .map_err(|e| format!("Couldn't read the file: {}", e))
+        .and_then(|submission| challenge.scorer.score(&submission, &case).map_err(|e| e.to_string()));
+
+    match scored {
+        Ok(score) => CaseResult { case, file, score: Some(score), error: None },
+        Err(error) => CaseResult { case, file, score: None, error: Some(error) },
+    }
+}
+
+/// Output files are named after their case, optionally followed by more dot separated parts,
+/// e.g. `busy_day.out`, `busy_day.100439.out` or `a_example.txt`
+fn case_of_output_file(challenge: &hashcode_score_calc::Challenge, file: &Path) -> Option<InputFileName> {
+    let file_name = file.file_name()?.to_str()?;
+    let mut candidates = vec![file_name];
+    candidates.extend(file.file_stem().and_then(|stem| stem.to_str()));
+    candidates.extend(file_name.split('.').next());
+
+    candidates.into_iter()
+        .find_map(|candidate| challenge.scorer.resolve_case(&candidate.into()).ok())
+}
+
+fn output_files(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| format!("Couldn't read {}: {}", dir.display(), e))?;
+
+    let mut files = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files)
+}
+
+fn run(args: Args) -> Result<Summary, String> {
+    let registry = ChallengeRegistry::load(&args.data_dir).map_err(|e| e.to_string())?;
+    let challenge = registry.get(&args.challenge)
+        .ok_or_else(|| format!("No inputs for {} in {}", args.challenge, args.data_dir.display()))?;
+
+    let results = match args.outputs {
+        Outputs::Single { case, file } => {
+            let case = challenge.scorer.resolve_case(&case).map_err(|e| e.to_string())?;
+            vec![score_file(challenge, case, file)]
+        },
+        Outputs::Directory(dir) => {
+            let mut results = Vec::new();
+            for file in output_files(&dir)? {
+                match case_of_output_file(challenge, &file) {
+                    Some(case) => results.push(score_file(challenge, case, file)),
+                    None => eprintln!("Skipping {}, it doesn't match any input case", file.display()),
+                }
+            }
+            results
+        },
+    };
+
+    Ok(Summary::new(&args.challenge, results))
+}
+
+fn main() {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(EXIT_USAGE);
+        }
+    };
+    let json = args.json;
+
+    let summary = match run(args) {
+        Ok(summary) => summary,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(EXIT_USAGE);
+        }
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&summary).expect("The summary is always serializable"));
+    } else {
+        summary.print_text();
+    }
+
+    if summary.has_invalid_submission() {
+        std::process::exit(EXIT_INVALID_SUBMISSION);
+    }
+}
@@ -0,0 +1,72 @@
+use std::path::Path;
+use std::process::Command;
+
+fn hashcode_score() -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_hashcode-score"));
+    command.arg("--data-dir").arg(Path::new(env!("CARGO_MANIFEST_DIR")).join("../hashcode_score_calc/assets"));
+    command
+}
+
+fn submissions_dir(challenge: &str) -> std::path::PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../hashcode_score_calc/assets").join(challenge).join("submissions")
+}
+
+#[test]
+fn scores_a_single_file() {
+    let output = hashcode_score()
+        .args(&["qual2020", "a_example"])
+        .arg(submissions_dir("2020qual").join("example_submission.txt"))
+        .output()
+        .expect("Should run");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("a\t"), "unexpected output {}", stdout);
+    assert!(stdout.ends_with("total\t\t16\n"), "unexpected output {}", stdout);
+}
+
+#[test]
+fn scores_a_directory_as_json() {
+    let output = hashcode_score()
+        .args(&["--json", "qual2016"])
+        .arg(submissions_dir("2016qual"))
+        .output()
+        .expect("Should run");
+
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).expect("Should be a json");
+    assert_eq!(summary["challenge"], "qual2016");
+
+    let results = summary["results"].as_array().expect("Should list the results");
+    let best_of = |case: &str| results.iter()
+        .filter(|result| result["case"] == case)
+        .map(|result| result["score"].as_u64().expect("Should be valid"))
+        .max()
+        .unwrap_or(0);
+    // example_submission.txt isn't named after a case
+    assert_eq!(results.len(), 6);
+    // Only the best of the mother_of_all_warehouses submissions counts
+    assert_eq!(summary["total"], best_of("busy_day") + best_of("mother_of_all_warehouses"));
+}
+
+#[test]
+fn invalid_submission_exits_with_failure() {
+    let dir = std::env::temp_dir().join(format!("hashcode_cli_invalid_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("a.txt");
+    std::fs::write(&file, "1\n0 x\n").unwrap();
+
+    let output = hashcode_score().args(&["qual2020", "a"]).arg(&file).output().expect("Should run");
+
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("invalid: "), "unexpected output {}", stdout);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn unknown_challenge_is_a_usage_error() {
+    let output = hashcode_score().args(&["qual1999", "a", "a.txt"]).output().expect("Should run");
+    assert_eq!(output.status.code(), Some(2));
+}
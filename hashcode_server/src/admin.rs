@@ -0,0 +1,91 @@
+use std::sync::Arc;
+use tokio::sync::{Mutex, MutexGuard};
+use warp::Filter;
+
+/// The token organizers send as `Authorization: Bearer <token>` to admin endpoints,
+/// which are all refused when no token is configured
+#[derive(Clone)]
+pub struct AdminToken(Option<String>);
+
+#[derive(Debug)]
+pub struct AdminOnly;
+impl warp::reject::Reject for AdminOnly {}
+
+impl AdminToken {
+    pub fn new(token: Option<String>) -> Self {
+        Self(token.filter(|token| !token.is_empty()))
+    }
+
+    /// Read from `HASHCODE_ADMIN_TOKEN`
+    pub fn from_env() -> Self {
+        Self::new(std::env::var("HASHCODE_ADMIN_TOKEN").ok())
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.0.is_some()
+    }
+
+    pub fn accepts(&self, authorization: Option<&str>) -> bool {
+        match (&self.0, authorization) {
+            (Some(token), Some(authorization)) if authorization.starts_with("Bearer ") =>
+                constant_time_eq(token.as_bytes(), authorization["Bearer ".len()..].trim().as_bytes()),
+            _ => false,
+        }
+    }
+}
+
+/// Held by the admin actions that change scores or submissions, so they run one at a time and
+/// never in the middle of a rejudge
+#[derive(Clone, Default)]
+pub struct AdminLock(Arc<Mutex<()>>);
+
+impl AdminLock {
+    pub async fn lock(&self) -> MutexGuard<'_, ()> {
+        self.0.lock().await
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Passes only requests carrying the admin token
+pub fn admin_only(admin_token: AdminToken) -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("authorization")
+        .and_then(move |authorization: Option<String>| {
+            let accepted = admin_token.accepts(authorization.as_deref());
+            async move {
+                if accepted {
+                    Ok(())
+                } else {
+                    Err(warp::reject::custom(AdminOnly))
+                }
+            }
+        })
+        .untuple_one()
+}
+
+pub async fn handle_admin_rejection(rej: warp::Rejection) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Some(AdminOnly) = rej.find() {
+        Ok(warp::reply::with_status("This requires the admin token", http::StatusCode::UNAUTHORIZED))
+    } else {
+        Err(rej)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AdminToken;
+
+    #[test]
+    fn accepts_only_the_configured_token() {
+        let admin_token = AdminToken::new(Some("s3cret".to_owned()));
+        assert!(admin_token.accepts(Some("Bearer s3cret")));
+        assert!(!admin_token.accepts(Some("Bearer s3cre")));
+        assert!(!admin_token.accepts(Some("s3cret")));
+        assert!(!admin_token.accepts(None));
+
+        assert!(!AdminToken::new(Some(String::new())).accepts(Some("Bearer ")));
+        assert!(!AdminToken::new(None).accepts(Some("Bearer s3cret")));
+    }
+}
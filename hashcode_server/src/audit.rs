@@ -0,0 +1,120 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::event_log::append_line;
+use crate::models::TeamName;
+use crate::models::solution::ChallengeDate;
+use crate::submissions::{SubmissionRecord, Timestamp};
+use crate::rejudge::ScoreChange;
+
+/// A change made by an organizer with the admin token
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum AdminAction {
+    ScoreboardRevealed {
+        challenge: ChallengeDate,
+    },
+    TeamDeleted {
+        team_name: TeamName,
+    },
+    TeamRenamed {
+        from: TeamName,
+        to: TeamName,
+    },
+    ScoresReset {
+        team_name: TeamName,
+        challenge: ChallengeDate,
+    },
+    SubmissionRemoved {
+        team_name: TeamName,
+        index: usize,
+        record: SubmissionRecord,
+    },
+    Rejudged {
+        challenge: ChallengeDate,
+        changes: Vec<ScoreChange>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    pub at: Timestamp,
+    pub action: AdminAction,
+}
+
+/// Every admin action, one json line each, kept apart from the event log so it is never replayed
+#[derive(Clone)]
+pub struct AuditLog {
+    entries: Arc<Mutex<Vec<AuditEntry>>>,
+    file: Option<Arc<Mutex<tokio::fs::File>>>,
+}
+
+impl AuditLog {
+    /// A log that doesn't persist anything
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        Self { entries: Arc::new(Mutex::new(Vec::new())), file: None }
+    }
+
+    /// Opens (or creates) the log at `path`, lines that can't be read are skipped
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let stored = if path.exists() { std::fs::read_to_string(path)? } else { String::new() };
+        let entries = stored.lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| serde_json::from_str(line)
+                .map_err(|e| log::warn!("Skipping a bad audit log line: {}", e))
+                .ok())
+            .collect();
+
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if !stored.is_empty() && !stored.ends_with('\n') {
+            file.write_all(b"\n")?;
+        }
+
+        Ok(Self { entries: Arc::new(Mutex::new(entries)), file: Some(Arc::new(Mutex::new(tokio::fs::File::from_std(file)))) })
+    }
+
+    pub async fn record(&self, action: AdminAction) {
+        log::info!("Admin action: {:?}", action);
+        let entry = AuditEntry { at: crate::submissions::now(), action };
+
+        if let Some(file) = &self.file {
+            let mut line = serde_json::to_string(&entry).expect("Audit entries are always serializable");
+            line.push('\n');
+
+            if let Err(e) = append_line(file, &line).await {
+                log::error!("Couldn't persist audit entry {:?}: {}", entry, e);
+            }
+        }
+        self.entries.lock().await.push(entry);
+    }
+
+    /// Oldest first
+    pub async fn entries(&self) -> Vec<AuditEntry> {
+        self.entries.lock().await.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AdminAction, AuditLog};
+
+    #[tokio::test]
+    async fn entries_survive_a_restart() {
+        let path = std::env::temp_dir().join(format!("hashcode_audit_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let action = AdminAction::TeamDeleted { team_name: "spam".into() };
+
+        AuditLog::open(&path).expect("Should create the log").record(action.clone()).await;
+
+        let entries = AuditLog::open(&path).expect("Should reopen the log").entries().await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, action);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
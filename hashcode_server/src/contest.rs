@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::models::solution::ChallengeDate;
+use crate::submissions::Timestamp;
+use crate::event_log::{Event, EventLog};
+
+/// The time window in which submissions to a challenge are accepted
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Contest {
+    pub challenge: ChallengeDate,
+    pub starts_at: Timestamp,
+    pub ends_at: Timestamp,
+    /// From when the public scoreboard stops updating, until it is revealed
+    pub freezes_at: Option<Timestamp>,
+    pub revealed: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ContestState {
+    NotStarted,
+    Running,
+    Ended,
+}
+
+impl Contest {
+    pub fn state_at(&self, now: Timestamp) -> ContestState {
+        if now < self.starts_at {
+            ContestState::NotStarted
+        } else if now < self.ends_at {
+            ContestState::Running
+        } else {
+            ContestState::Ended
+        }
+    }
+
+    /// The time the public scoreboard shows, when it is frozen at `now`
+    pub fn frozen_at(&self, now: Timestamp) -> Option<Timestamp> {
+        self.freezes_at.filter(|&freezes_at| freezes_at <= now && !self.revealed)
+    }
+}
+
+/// How a contest is written in the contest file, e.g.
+/// `{"challenge": "qual2020", "start": 1585126800, "duration_minutes": 240, "freeze_minutes": 60}`
+#[derive(Deserialize)]
+struct ContestConfig {
+    challenge: String,
+    /// Seconds since the unix epoch
+    start: u64,
+    duration_minutes: u64,
+    /// How long before the end the public scoreboard freezes
+    freeze_minutes: Option<u64>,
+}
+
+/// The contest of each challenge, challenges without one accept submissions at any time
+#[derive(Clone)]
+pub struct ContestSchedule {
+    inner: Arc<RwLock<HashMap<ChallengeDate, Contest>>>,
+    event_log: EventLog,
+}
+
+impl ContestSchedule {
+    pub fn with_contests(contests: Vec<Contest>, event_log: EventLog) -> Self {
+        let contests = contests.into_iter()
+            .map(|contest| (contest.challenge.clone(), contest))
+            .collect();
+        Self { inner: Arc::new(RwLock::new(contests)), event_log }
+    }
+
+    /// Reads a json list of contests
+    pub fn from_file<P: AsRef<Path>>(path: P, event_log: EventLog) -> io::Result<Self> {
+        let configs: Vec<ContestConfig> = serde_json::from_str(&std::fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Bad contest file: {}", e)))?;
+
+        let contests = configs.into_iter()
+            .map(|config| {
+                let challenge = config.challenge.parse::<ChallengeDate>()
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+                let starts_at = config.start * 1000;
+                let ends_at = starts_at + config.duration_minutes * 60 * 1000;
+                let freezes_at = config.freeze_minutes.map(|minutes| ends_at.saturating_sub(minutes * 60 * 1000));
+                Ok(Contest { challenge, starts_at, ends_at, freezes_at, revealed: false })
+            })
+            .collect::<io::Result<_>>()?;
+
+        Ok(Self::with_contests(contests, event_log))
+    }
+
+    /// Restores the state recorded by `event`, without logging it again
+    pub async fn apply(&mut self, event: &Event) {
+        if let Event::ScoreboardRevealed { challenge } = event {
+            self.set_revealed(challenge).await;
+        }
+    }
+
+    /// Unfreezes the public scoreboard of `challenge`, false when it has no contest
+    pub async fn reveal(&mut self, challenge: &ChallengeDate) -> bool {
+        let has_contest = self.set_revealed(challenge).await;
+        if has_contest {
+            self.event_log.append(&Event::ScoreboardRevealed { challenge: challenge.clone() }).await;
+        }
+        has_contest
+    }
+
+    async fn set_revealed(&mut self, challenge: &ChallengeDate) -> bool {
+        match self.inner.write().await.get_mut(challenge) {
+            Some(contest) => {
+                contest.revealed = true;
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub async fn contests(&self) -> Vec<Contest> {
+        self.inner.read().await.values().cloned().collect()
+    }
+
+    pub async fn get(&self, challenge: &ChallengeDate) -> Option<Contest> {
+        self.inner.read().await.get(challenge).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Contest, ContestState};
+    use crate::models::solution::ChallengeDate;
+
+    #[test]
+    fn submission_window_is_start_inclusive_end_exclusive() {
+        let contest = Contest {
+            challenge: ChallengeDate::Qualification(2020),
+            starts_at: 1000,
+            ends_at: 2000,
+            freezes_at: None,
+            revealed: false,
+        };
+        assert_eq!(contest.state_at(999), ContestState::NotStarted);
+        assert_eq!(contest.state_at(1000), ContestState::Running);
+        assert_eq!(contest.state_at(1999), ContestState::Running);
+        assert_eq!(contest.state_at(2000), ContestState::Ended);
+    }
+
+    #[test]
+    fn scoreboard_is_frozen_until_revealed() {
+        let mut contest = Contest {
+            challenge: ChallengeDate::Qualification(2020),
+            starts_at: 1000,
+            ends_at: 2000,
+            freezes_at: Some(1500),
+            revealed: false,
+        };
+        assert_eq!(contest.frozen_at(1499), None);
+        assert_eq!(contest.frozen_at(1500), Some(1500));
+        assert_eq!(contest.frozen_at(3000), Some(1500));
+
+        contest.revealed = true;
+        assert_eq!(contest.frozen_at(3000), None);
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::models::{Team, TeamName};
+use crate::models::solution::{ChallengeDate, InputFileName};
+use crate::scoreboard::{ChallengeScores, Score};
+use crate::submissions::{RejudgedRecord, SubmissionRecord};
+
+/// A change to the server state, stored as one json line in the event log
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Event {
+    TeamRegistered {
+        team: Team,
+    },
+    ScoreAdded {
+        team_name: TeamName,
+        challenge: ChallengeDate,
+        input: InputFileName,
+        score: Score,
+    },
+    SubmissionRecorded {
+        record: SubmissionRecord,
+    },
+    ScoreboardRevealed {
+        challenge: ChallengeDate,
+    },
+    TeamDeleted {
+        team_name: TeamName,
+    },
+    TeamRenamed {
+        from: TeamName,
+        to: TeamName,
+    },
+    /// Clears a team's scores and submissions on a challenge
+    ScoresReset {
+        team_name: TeamName,
+        challenge: ChallengeDate,
+    },
+    /// Removes the submission at `index` of the team's history
+    SubmissionRemoved {
+        team_name: TeamName,
+        index: usize,
+    },
+    /// Replaces a team's best scores on a challenge
+    TeamScoresSet {
+        team_name: TeamName,
+        challenge: ChallengeDate,
+        scores: HashMap<InputFileName, Score>,
+    },
+    /// Replaces every score of a challenge, and the outcomes of the submissions that changed when re-judged
+    Rejudged {
+        challenge: ChallengeDate,
+        scores: ChallengeScores,
+        records: Vec<RejudgedRecord>,
+    },
+}
+
+/// Append-only storage of every [`Event`], replayed on startup to restore the server state
+#[derive(Clone)]
+pub struct EventLog {
+    file: Option<Arc<Mutex<tokio::fs::File>>>,
+}
+
+impl EventLog {
+    /// A log that doesn't persist anything
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        Self { file: None }
+    }
+
+    /// Opens (or creates) the log at `path`, returning the events already stored in it
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<(Self, Vec<Event>)> {
+        let path = path.as_ref();
+        let stored = if path.exists() { std::fs::read_to_string(path)? } else { String::new() };
+        let (events, valid_length) = parse_events(&stored)?;
+
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if valid_length < stored.len() {
+            file.set_len(valid_length as u64)?;
+        } else if !stored.is_empty() && !stored.ends_with('\n') {
+            file.write_all(b"\n")?;
+        }
+
+        Ok((Self { file: Some(Arc::new(Mutex::new(tokio::fs::File::from_std(file)))) }, events))
+    }
+
+    pub async fn append(&self, event: &Event) {
+        if let Some(file) = &self.file {
+            let mut line = serde_json::to_string(event).expect("Events are always serializable");
+            line.push('\n');
+
+            if let Err(e) = append_line(file, &line).await {
+                log::error!("Couldn't persist event {:?}: {}", event, e);
+            }
+        }
+    }
+}
+
+/// Writes `line` at the end of `file` off the executor threads, returning once it's written
+pub async fn append_line(file: &Mutex<tokio::fs::File>, line: &str) -> io::Result<()> {
+    let mut file = file.lock().await;
+    file.write_all(line.as_bytes()).await?;
+    file.flush().await
+}
+
+/// The stored events, and the length of the log without a truncated last line
+fn parse_events(stored: &str) -> io::Result<(Vec<Event>, usize)> {
+    let mut events = Vec::new();
+    let mut line_start = 0;
+
+    for (line_index, line) in stored.split('\n').enumerate() {
+        let line_end = line_start + line.len();
+        if !line.trim().is_empty() {
+            match serde_json::from_str(line) {
+                Ok(event) => events.push(event),
+                // A crash while appending may leave the last line cut short
+                Err(e) if line_end == stored.len() => {
+                    log::warn!("Dropping a truncated last event: {}", e);
+                    return Ok((events, line_start));
+                },
+                Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                                    format!("Bad event on line {}: {}", line_index + 1, e))),
+            }
+        }
+        line_start = line_end + 1;
+    }
+
+    Ok((events, stored.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EventLog;
+    use crate::models::Team;
+    use crate::models::solution::ChallengeDate;
+    use crate::scoreboard::ScoreBoard;
+    use crate::teams_db::TeamsDb;
+
+    fn scratch_log_path(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("hashcode_event_log_{}_{}.jsonl", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[tokio::test]
+    async fn state_survives_a_restart() {
+        let path = scratch_log_path("restart");
+        let team = Team { name: "abc".into(), participants: vec!["ori".to_owned()] };
+        let challenge = ChallengeDate::Qualification(2020);
+
+        {
+            let (log, events) = EventLog::open(&path).expect("Should create the log");
+            assert!(events.is_empty());
+
+            let mut teams = TeamsDb::with_event_log(log.clone());
+            let mut scoreboard = ScoreBoard::with_event_log(log);
+            teams.insert(team.clone()).await;
+            scoreboard.add_team_score(&team.name, &"a".into(), 16, challenge.clone()).await;
+            scoreboard.add_team_score(&team.name, &"a".into(), 10, challenge.clone()).await;
+        }
+
+        let (log, events) = EventLog::open(&path).expect("Should reopen the log");
+        let mut teams = TeamsDb::with_event_log(log.clone());
+        let mut scoreboard = ScoreBoard::with_event_log(log);
+        for event in &events {
+            teams.apply(event).await;
+            scoreboard.apply(event).await;
+        }
+
+        assert!(teams.contains(&team.name).await);
+        assert_eq!(scoreboard.total_score(&team.name, challenge).await, 16);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn truncated_last_event_is_ignored() {
+        let path = scratch_log_path("truncated");
+        std::fs::write(&path, "{\"TeamRegistered\":{\"team\":{\"name\":\"abc\",\"participants\":[]}}}\n{\"TeamReg").unwrap();
+
+        let (log, events) = EventLog::open(&path).expect("Should open the log");
+        assert_eq!(events.len(), 1);
+
+        let mut teams = TeamsDb::with_event_log(log);
+        teams.insert(Team { name: "def".into(), participants: vec![] }).await;
+
+        let (_log, events) = EventLog::open(&path).expect("Should reopen the log");
+        assert_eq!(events.len(), 2);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
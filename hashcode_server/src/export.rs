@@ -0,0 +1,139 @@
+use serde_derive::Deserialize;
+
+use crate::models::solution::InputFileName;
+use crate::scoreboard::RankedTeam;
+use crate::submissions::SubmissionRecord;
+
+/// The query string of the export endpoints
+#[derive(Deserialize, Debug, Default)]
+pub struct ExportOptions {
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    /// Json when no format is asked for, `None` for an unknown one
+    pub fn parse(format: Option<&str>) -> Option<Self> {
+        match format {
+            Some("csv") => Some(ExportFormat::Csv),
+            Some("json") | None => Some(ExportFormat::Json),
+            Some(_) => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Json => "application/json",
+        }
+    }
+}
+
+/// Replies with `body` as a file to download, named `<file_stem>.<extension>`
+pub fn attachment(body: String, format: ExportFormat, file_stem: &str) -> impl warp::Reply {
+    let content_disposition = format!("attachment; filename=\"{}.{}\"", file_stem, format.extension());
+    warp::reply::with_header(
+        warp::reply::with_header(body, "content-type", format.content_type()),
+        "content-disposition",
+        content_disposition,
+    )
+}
+
+/// One line per team, with a column for each input. `inputs` are the challenge's cases, in order
+pub fn scoreboard_csv(ranked: &[RankedTeam], inputs: &[InputFileName]) -> String {
+    let mut inputs = inputs.to_vec();
+    for team in ranked {
+        let mut extra: Vec<&InputFileName> = team.best_per_input.keys().filter(|input| !inputs.contains(input)).collect();
+        extra.sort();
+        inputs.extend(extra.into_iter().cloned());
+    }
+
+    let mut header = vec!["rank".to_owned(), "team_name".to_owned()];
+    header.extend(inputs.iter().map(|input| input.as_str().to_owned()));
+    header.extend(vec!["total_score".to_owned(), "achieved_at".to_owned()]);
+
+    let mut csv = csv_line(&header);
+    for team in ranked {
+        let mut fields = vec![team.rank.to_string(), team.team_name.to_string()];
+        fields.extend(inputs.iter().map(|input| optional(team.best_per_input.get(input))));
+        fields.push(team.total_score.to_string());
+        fields.push(optional(team.achieved_at.as_ref()));
+        csv.push_str(&csv_line(&fields));
+    }
+    csv
+}
+
+/// One line per input case of a submission, as recorded in the history
+pub fn submissions_csv(records: &[SubmissionRecord]) -> String {
+    let header = ["team_name", "challenge", "input", "score", "error", "submitted_at", "submission_hash", "reset"];
+    let mut csv = csv_line(&header.iter().map(|field| (*field).to_owned()).collect::<Vec<_>>());
+    for record in records {
+        csv.push_str(&csv_line(&[
+            record.team_name.to_string(),
+            record.challenge.id(),
+            record.input.as_str().to_owned(),
+            optional(record.score.as_ref()),
+            record.error.clone().unwrap_or_default(),
+            record.submitted_at.to_string(),
+            record.submission_hash.clone(),
+            record.reset.to_string(),
+        ]));
+    }
+    csv
+}
+
+fn optional<T: ToString>(value: Option<&T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+fn csv_line(fields: &[String]) -> String {
+    let mut line = fields.iter().map(|field| csv_field(field)).collect::<Vec<_>>().join(",");
+    line.push_str("\r\n");
+    line
+}
+
+/// Quotes a field that has a separator, a quote or a line break in it
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::scoreboard_csv;
+    use crate::scoreboard::RankedTeam;
+
+    #[test]
+    fn team_names_are_quoted() {
+        let ranked = vec![
+            RankedTeam {
+                rank: 1,
+                team_name: "Bits, \"Bytes\"".into(),
+                total_score: 30,
+                best_per_input: vec![("a".into(), 10), ("c".into(), 20)].into_iter().collect(),
+                achieved_at: Some(1234),
+            },
+            RankedTeam { rank: 2, team_name: "idle".into(), total_score: 0, best_per_input: Default::default(), achieved_at: None },
+        ];
+
+        assert_eq!(scoreboard_csv(&ranked, &["a".into(), "b".into()]),
+                   "rank,team_name,a,b,c,total_score,achieved_at\r\n\
+                    1,\"Bits, \"\"Bytes\"\"\",10,,20,30,1234\r\n\
+                    2,idle,,,,0,\r\n");
+    }
+}
@@ -3,8 +3,19 @@ use warp::Filter;
 use crate::scoreboard::ScoreBoard;
 use hashcode_score_calc::Challenge;
 use std::sync::Arc;
-use crate::models::solution::ChallengeDate;
+use crate::models::solution::{ChallengeDate, InputFileName};
 use crate::handlers::UnknownChallenge;
+use crate::secret_keys::SecretKeys;
+use crate::submissions::SubmissionHistory;
+use crate::models::TeamName;
+use crate::contest::ContestSchedule;
+use crate::admin::{admin_only, AdminLock, AdminToken};
+use crate::rate_limit::RateLimiter;
+use crate::jobs::{JobTracker, ScoringPool};
+use crate::handlers::SubmitOptions;
+use crate::audit::AuditLog;
+use crate::submission_store::SubmissionStore;
+use crate::export::ExportOptions;
 
 fn with_db(
     db: TeamsDb,
@@ -12,11 +23,34 @@ fn with_db(
     warp::any().map(move || db.clone())
 }
 
-fn with_challenges(
-    challenges: Vec<Challenge>,
-) -> impl Filter<Extract = (Arc<Vec<Challenge>>,), Error = std::convert::Infallible> + Clone {
-    let ac = Arc::new(challenges);
-    warp::any().map(move || ac.clone())
+fn with_secret_keys(
+    secret_keys: Arc<SecretKeys>,
+) -> impl Filter<Extract = (Arc<SecretKeys>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || secret_keys.clone())
+}
+
+fn with_submission_history(
+    history: SubmissionHistory,
+) -> impl Filter<Extract = (SubmissionHistory,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || history.clone())
+}
+
+fn with_contests(
+    contests: ContestSchedule,
+) -> impl Filter<Extract = (ContestSchedule,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || contests.clone())
+}
+
+fn with_jobs(
+    jobs: JobTracker,
+) -> impl Filter<Extract = (JobTracker,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || jobs.clone())
+}
+
+fn with_scoring_pool(
+    scoring_pool: ScoringPool,
+) -> impl Filter<Extract = (ScoringPool,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || scoring_pool.clone())
 }
 
 fn with_scoreboard(
@@ -25,16 +59,48 @@ fn with_scoreboard(
     warp::any().map(move || db.clone())
 }
 
+fn with_admin_lock(
+    admin_lock: AdminLock,
+) -> impl Filter<Extract = (AdminLock,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || admin_lock.clone())
+}
+
+fn with_audit_log(
+    audit_log: AuditLog,
+) -> impl Filter<Extract = (AuditLog,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || audit_log.clone())
+}
+
+fn with_state(
+    state: ServerState,
+) -> impl Filter<Extract = (ServerState,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || state.clone())
+}
+
 pub fn team_registration(
     teams: TeamsDb,
+    secret_keys: Arc<SecretKeys>,
 ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
     warp::post()
         .and(warp::path::path("register_team"))
         .and(warp::body::json())
         .and(with_db(teams))
+        .and(with_secret_keys(secret_keys))
         .and_then(crate::handlers::add_team)
 }
 
+pub fn token_renewal(
+    teams: TeamsDb,
+    secret_keys: Arc<SecretKeys>,
+) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    warp::post()
+        .and(warp::path::path("renew_token"))
+        .and(warp::body::json())
+        .and(with_db(teams))
+        .and(with_secret_keys(secret_keys))
+        .and_then(crate::handlers::renew_token)
+}
+
 pub fn list_teams(
     teams: TeamsDb,
 ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
@@ -45,17 +111,52 @@ pub fn list_teams(
         .and_then(crate::handlers::list_teams)
 }
 
-pub fn submit_solution(scoreboard: ScoreBoard) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone
+pub fn team_submissions(
+    teams: TeamsDb,
+    history: SubmissionHistory,
+    secret_keys: Arc<SecretKeys>,
+) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("teams"))
+        .and(team_name_from_path())
+        .and(warp::path("submissions"))
+        .and(warp::path::end())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(with_db(teams))
+        .and(with_submission_history(history))
+        .and(with_secret_keys(secret_keys))
+        .and_then(crate::handlers::team_submissions)
+}
+
+pub fn submit_solution(state: &ServerState) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone
 {
     warp::post()
         .and(warp::body::json())
         .and(warp::path::path("submit"))
-        .and(with_challenges(hashcode_score_calc::get_challenges()))
-        .and(with_scoreboard(scoreboard))
+        .and(optional_query::<SubmitOptions>())
+        .and(with_state(state.clone()))
         .and_then(crate::handlers::submit_solution)
         .recover(crate::handlers::handle_submit_rejection)
 }
 
+/// The options in the query string, all defaulted when there is none
+fn optional_query<T>() -> impl Filter<Extract = (T,), Error = std::convert::Infallible> + Clone
+    where T: serde::de::DeserializeOwned + Default + Send + 'static
+{
+    warp::query::<T>()
+        .or(warp::any().map(T::default))
+        .unify()
+}
+
+pub fn view_job(jobs: JobTracker) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone
+{
+    warp::get()
+        .and(warp::path("jobs"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(with_jobs(jobs))
+        .and_then(crate::handlers::view_job)
+}
 
 //TODO: This is a quick and dirty hack
 pub fn challenge_data_from_path() -> impl Filter<Extract = (ChallengeDate,), Error = warp::Rejection> + Clone
@@ -64,6 +165,11 @@ pub fn challenge_data_from_path() -> impl Filter<Extract = (ChallengeDate,), Err
         match s.as_str() {
             "qual2020" => Ok(ChallengeDate::Qualification(2020)),
             "qual2016" => Ok(ChallengeDate::Qualification(2016)),
+            "qual2017" => Ok(ChallengeDate::Qualification(2017)),
+            "qual2018" => Ok(ChallengeDate::Qualification(2018)),
+            "qual2019" => Ok(ChallengeDate::Qualification(2019)),
+            "qual2021" => Ok(ChallengeDate::Qualification(2021)),
+            "qual2022" => Ok(ChallengeDate::Qualification(2022)),
             _ => Err(warp::reject::custom(UnknownChallenge))
         }
     }
@@ -71,27 +177,271 @@ pub fn challenge_data_from_path() -> impl Filter<Extract = (ChallengeDate,), Err
        .and_then(handle)
 }
 
-pub fn view_scoreboard(scoreboard: ScoreBoard, teams: TeamsDb) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone
+/// A percent encoded team name, as team names may have spaces and non ascii letters
+pub fn team_name_from_path() -> impl Filter<Extract = (TeamName,), Error = warp::Rejection> + Clone
+{
+    warp::path::param::<String>()
+        .map(|name: String| TeamName::from(percent_encoding::percent_decode_str(&name).decode_utf8_lossy()))
+}
+
+pub fn view_scoreboard(scoreboard: ScoreBoard, teams: TeamsDb, contests: ContestSchedule, history: SubmissionHistory, secret_keys: Arc<SecretKeys>) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone
 {
     warp::get()
         .and(warp::path::path("scoreboard"))
         .and(challenge_data_from_path())
+        .and(warp::path::end())
+        .and(warp::header::optional::<String>("authorization"))
         .and(with_scoreboard(scoreboard))
         .and(with_db(teams))
+        .and(with_contests(contests))
+        .and(with_submission_history(history))
+        .and(with_secret_keys(secret_keys))
         .and_then(crate::handlers::view_scoreboard)
 }
 
-pub fn game_api(
-    teams: TeamsDb,
-    scoreboard: ScoreBoard
-) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
-    team_registration(teams.clone())
+pub fn scoreboard_events(scoreboard: ScoreBoard, contests: ContestSchedule) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone
+{
+    warp::get()
+        .and(warp::path::path("scoreboard"))
+        .and(challenge_data_from_path())
+        .and(warp::path("events"))
+        .and(warp::path::end())
+        .and(with_scoreboard(scoreboard))
+        .and(with_contests(contests))
+        .and_then(crate::handlers::scoreboard_events)
+}
+
+pub fn view_score_history(contests: ContestSchedule, history: SubmissionHistory) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone
+{
+    warp::get()
+        .and(warp::path::path("scoreboard"))
+        .and(challenge_data_from_path())
+        .and(warp::path("history"))
+        .and(warp::path::end())
+        .and(with_contests(contests))
+        .and(with_submission_history(history))
+        .and_then(crate::handlers::view_score_history)
+}
+
+pub fn export_scoreboard(state: &ServerState) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone
+{
+    warp::get()
+        .and(warp::path::path("scoreboard"))
+        .and(challenge_data_from_path())
+        .and(warp::path("export"))
+        .and(warp::path::end())
+        .and(optional_query::<ExportOptions>())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(with_state(state.clone()))
+        .and_then(crate::handlers::export_scoreboard)
+}
+
+pub fn view_input_scoreboard(state: &ServerState) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone
+{
+    warp::get()
+        .and(warp::path::path("scoreboard"))
+        .and(challenge_data_from_path())
+        .and(warp::path::param::<String>().map(|input: String| InputFileName::from(input.as_str())))
+        .and(warp::path::end())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(with_state(state.clone()))
+        .and_then(crate::handlers::view_input_scoreboard)
+}
+
+pub fn reveal_scoreboard(contests: ContestSchedule, scoreboard: ScoreBoard, admin_token: AdminToken, audit_log: AuditLog) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone
+{
+    warp::post()
+        .and(warp::path("admin"))
+        .and(warp::path("scoreboard"))
+        .and(challenge_data_from_path())
+        .and(warp::path("reveal"))
+        .and(warp::path::end())
+        .and(admin_only(admin_token))
+        .and(with_contests(contests))
+        .and(with_scoreboard(scoreboard))
+        .and(with_audit_log(audit_log))
+        .and_then(crate::handlers::reveal_scoreboard)
+        .recover(crate::admin::handle_admin_rejection)
+}
+
+pub fn delete_team(state: &ServerState) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone
+{
+    warp::delete()
+        .and(warp::path("admin"))
+        .and(warp::path("teams"))
+        .and(team_name_from_path())
+        .and(warp::path::end())
+        .and(admin_only(state.admin_token.clone()))
+        .and(with_db(state.teams.clone()))
+        .and(with_scoreboard(state.scoreboard.clone()))
+        .and(with_submission_history(state.history.clone()))
+        .and(with_audit_log(state.audit_log.clone()))
+        .and(with_admin_lock(state.admin_lock.clone()))
+        .and_then(crate::handlers::delete_team)
+        .recover(crate::admin::handle_admin_rejection)
+}
+
+pub fn rename_team(state: &ServerState) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone
+{
+    warp::post()
+        .and(warp::path("admin"))
+        .and(warp::path("teams"))
+        .and(team_name_from_path())
+        .and(warp::path("rename"))
+        .and(warp::path::end())
+        .and(admin_only(state.admin_token.clone()))
+        .and(warp::body::json())
+        .and(with_state(state.clone()))
+        .and_then(crate::handlers::rename_team)
+        .recover(crate::admin::handle_admin_rejection)
+}
+
+pub fn reset_team_scores(state: &ServerState) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone
+{
+    warp::delete()
+        .and(warp::path("admin"))
+        .and(warp::path("teams"))
+        .and(team_name_from_path())
+        .and(warp::path("scores"))
+        .and(challenge_data_from_path())
+        .and(warp::path::end())
+        .and(admin_only(state.admin_token.clone()))
+        .and(with_db(state.teams.clone()))
+        .and(with_scoreboard(state.scoreboard.clone()))
+        .and(with_submission_history(state.history.clone()))
+        .and(with_audit_log(state.audit_log.clone()))
+        .and(with_admin_lock(state.admin_lock.clone()))
+        .and_then(crate::handlers::reset_team_scores)
+        .recover(crate::admin::handle_admin_rejection)
+}
+
+pub fn remove_submission(state: &ServerState) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone
+{
+    warp::delete()
+        .and(warp::path("admin"))
+        .and(warp::path("teams"))
+        .and(team_name_from_path())
+        .and(warp::path("submissions"))
+        .and(warp::path::param::<usize>())
+        .and(warp::path::end())
+        .and(admin_only(state.admin_token.clone()))
+        .and(with_scoreboard(state.scoreboard.clone()))
+        .and(with_submission_history(state.history.clone()))
+        .and(with_audit_log(state.audit_log.clone()))
+        .and(with_admin_lock(state.admin_lock.clone()))
+        .and_then(crate::handlers::remove_submission)
+        .recover(crate::admin::handle_admin_rejection)
+}
+
+pub fn rejudge(state: &ServerState) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone
+{
+    warp::post()
+        .and(warp::path("admin"))
+        .and(warp::path("rejudge"))
+        .and(challenge_data_from_path())
+        .and(warp::path::end())
+        .and(admin_only(state.admin_token.clone()))
+        .and(with_state(state.clone()))
+        .and_then(crate::handlers::rejudge)
+        .recover(crate::admin::handle_admin_rejection)
+}
+
+pub fn export_submissions(state: &ServerState) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone
+{
+    warp::get()
+        .and(warp::path("admin"))
+        .and(warp::path("submissions"))
+        .and(warp::path("export"))
+        .and(warp::path::end())
+        .and(admin_only(state.admin_token.clone()))
+        .and(optional_query::<ExportOptions>())
+        .and(with_submission_history(state.history.clone()))
+        .and_then(crate::handlers::export_submissions)
+        .recover(crate::admin::handle_admin_rejection)
+}
+
+pub fn view_audit_log(admin_token: AdminToken, audit_log: AuditLog) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone
+{
+    warp::get()
+        .and(warp::path("admin"))
+        .and(warp::path("audit"))
+        .and(warp::path::end())
+        .and(admin_only(admin_token))
+        .and(with_audit_log(audit_log))
+        .and_then(crate::handlers::view_audit_log)
+        .recover(crate::admin::handle_admin_rejection)
+}
+
+pub fn view_workers(state: &ServerState) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone
+{
+    warp::get()
+        .and(warp::path("admin"))
+        .and(warp::path("workers"))
+        .and(warp::path::end())
+        .and(admin_only(state.admin_token.clone()))
+        .and(with_scoring_pool(state.scoring_pool.clone()))
+        .and_then(crate::handlers::view_workers)
+        .recover(crate::admin::handle_admin_rejection)
+}
+
+pub fn view_contests(contests: ContestSchedule) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone
+{
+    warp::get()
+        .and(warp::path("contest"))
+        .and(warp::path::end())
+        .and(with_contests(contests))
+        .and_then(crate::handlers::view_contests)
+}
+
+/// Everything the api shares between requests
+#[derive(Clone)]
+pub struct ServerState {
+    pub teams: TeamsDb,
+    pub scoreboard: ScoreBoard,
+    pub challenges: Arc<Vec<Challenge>>,
+    pub secret_keys: Arc<SecretKeys>,
+    pub history: SubmissionHistory,
+    pub contests: ContestSchedule,
+    pub admin_token: AdminToken,
+    pub rate_limiter: RateLimiter,
+    pub scoring_pool: ScoringPool,
+    pub jobs: JobTracker,
+    pub audit_log: AuditLog,
+    pub submission_store: SubmissionStore,
+    pub admin_lock: AdminLock,
+}
+
+pub fn game_api(state: ServerState) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    let submit = submit_solution(&state);
+    let export = export_scoreboard(&state);
+    let input_scoreboard = view_input_scoreboard(&state);
+    let admin = delete_team(&state)
+        .or(rename_team(&state))
+        .or(reset_team_scores(&state))
+        .or(remove_submission(&state))
+        .or(rejudge(&state))
+        .or(export_submissions(&state))
+        .or(view_workers(&state));
+    let ServerState { teams, scoreboard, secret_keys, history, contests, admin_token, jobs, audit_log, .. } = state;
+
+    team_registration(teams.clone(), secret_keys.clone())
         .with(warp::log("team-registration"))
+        .or(token_renewal(teams.clone(), secret_keys.clone()))
         .or(list_teams(teams.clone()))
-        .or(submit_solution(scoreboard.clone()))
-        .or(view_scoreboard(scoreboard, teams.clone())
+        .or(team_submissions(teams.clone(), history.clone(), secret_keys.clone()))
+        .or(view_score_history(contests.clone(), history.clone()))
+        .or(scoreboard_events(scoreboard.clone(), contests.clone()))
+        .or(view_contests(contests.clone()))
+        .or(reveal_scoreboard(contests.clone(), scoreboard.clone(), admin_token.clone(), audit_log.clone()))
+        .or(admin)
+        .or(view_audit_log(admin_token, audit_log))
+        .or(submit)
+        .or(view_job(jobs))
+        .or(view_scoreboard(scoreboard, teams.clone(), contests, history, secret_keys)
             .with(warp::log("scoreboard"))
         )
+        .or(export)
+        .or(input_scoreboard)
         .or(warp::fs::dir("static")
             .with(warp::log("static-serv")))
         .or(warp::get()
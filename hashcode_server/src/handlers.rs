@@ -1,20 +1,39 @@
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
 use hex_string::HexString;
-use crate::teams_db::TeamsDb;
-use crate::models::{TeamName, Team};
+use hashcode_score_calc::{Challenge, Score, ScoringError};
+use serde_derive::{Deserialize, Serialize};
+use crate::teams_db::{RenameError, TeamsDb};
+use crate::models::{TeamName, Team, TokenRenewRequest};
 use crate::{sign_on_team_name, verify_team_token};
-use crate::scoreboard::ScoreBoard;
+use crate::secret_keys::SecretKeys;
+use crate::submissions::{Progress, SubmissionHistory, SubmissionRecord};
+use crate::contest::{Contest, ContestSchedule, ContestState};
+use crate::scoreboard::{ScoreBoard, ScoreboardUpdate};
+use crate::jobs::{JobError, JobId, JobStatus, JobTracker, ScoringPool};
+use crate::filters::ServerState;
+use crate::audit::{AdminAction, AuditLog};
+use crate::admin::AdminLock;
+use crate::submission_store::SubmissionStore;
+use crate::export::{ExportFormat, ExportOptions};
+use crate::event_log::Event;
 use crate::models::solution::{InputFileName, ChallengeDate, SolutionSubmitRequest};
 
 pub async fn add_team(
     new_team: Team,
     mut teams_db: TeamsDb,
+    secret_keys: Arc<SecretKeys>,
 ) -> Result<impl warp::Reply, std::convert::Infallible> {
     if teams_db.contains(&new_team.name).await {
         return Ok(warp::reply::json(&crate::ApiError::ErrorTeamExists));
     }
+    // Tokens of the old team would work for the new one
+    if teams_db.is_retired(&new_team.name).await {
+        return Ok(warp::reply::json(&crate::ApiError::ErrorTeamNameRetired));
+    }
 
-    let new_team_token = sign_on_team_name(&new_team.name);
+    let new_team_token = sign_on_team_name(&secret_keys, &new_team.name);
 
     log::info!("Team '{}' was registered with token {}", new_team.name,
                 HexString::from_bytes(&new_team_token.token).as_str());
@@ -26,6 +45,22 @@ pub async fn add_team(
     Ok(warp::reply::json(&new_team_token))
 }
 
+/// Swaps a token signed with a previous key for one signed with the current key
+pub async fn renew_token(
+    renew_req: TokenRenewRequest,
+    teams_db: TeamsDb,
+    secret_keys: Arc<SecretKeys>,
+) -> Result<impl warp::Reply, std::convert::Infallible> {
+    if !teams_db.contains(&renew_req.team_name).await
+        || !verify_team_token(&secret_keys, &renew_req.token.into(), &renew_req.team_name) {
+        return Ok(warp::reply::json(&crate::ApiError::WrongToken));
+    }
+
+    log::info!("Team '{}' renewed its token", renew_req.team_name);
+
+    Ok(warp::reply::json(&sign_on_team_name(&secret_keys, &renew_req.team_name)))
+}
+
 pub async fn list_teams(teams_db: TeamsDb) -> Result<impl warp::Reply, std::convert::Infallible> {
     let listed_teams: Vec<TeamName> = teams_db.list_team_names().await;
 
@@ -44,69 +79,575 @@ impl warp::reject::Reject for WrongToken {}
 pub struct UnknownChallenge;
 impl warp::reject::Reject for UnknownChallenge {}
 
+impl fmt::Display for UnknownChallenge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "It seems like you're trying to play an unimplemented game")
+    }
+}
+
 #[derive(Debug)]
 pub struct BadSubmission(hashcode_score_calc::ScoringError);
 impl warp::reject::Reject for BadSubmission {}
 
-use hashcode_score_calc::Challenge;
-use std::sync::Arc;
+#[derive(Debug)]
+pub struct ContestNotRunning {
+    contest: Contest,
+    state: ContestState,
+    now: crate::submissions::Timestamp,
+}
+impl warp::reject::Reject for ContestNotRunning {}
+
+impl fmt::Display for ContestNotRunning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.state {
+            ContestState::NotStarted => match (self.contest.starts_at - self.now) / 60_000 {
+                0 => write!(f, "The {} contest starts in less than a minute", self.contest.challenge),
+                minutes => write!(f, "The {} contest hasn't started yet, it starts in {} minutes", self.contest.challenge, minutes),
+            },
+            _ => write!(f, "The {} contest has ended, submissions are closed", self.contest.challenge),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ScoringFailed(JobError);
+impl warp::reject::Reject for ScoringFailed {}
+
+#[derive(Debug)]
+pub struct RateLimited {
+    retry_after: crate::submissions::Timestamp,
+}
+impl warp::reject::Reject for RateLimited {}
+
+impl RateLimited {
+    /// Whole seconds, as the `Retry-After` header expects
+    fn retry_after_secs(&self) -> u64 {
+        std::time::Duration::from_millis(self.retry_after).as_secs_f64().ceil() as u64
+    }
+}
 
-pub async fn submit_solution(solution_req: SolutionSubmitRequest, challenges: Arc<Vec<Challenge>>, mut scoreboard: ScoreBoard) -> Result<impl warp::Reply, warp::Rejection> {
-    use hashcode_score_calc::Score;
+/// The query of `/submit`, `?async=true` scores in the background and replies with a job id
+#[derive(Deserialize, Default)]
+pub struct SubmitOptions {
+    #[serde(rename = "async", default)]
+    background: bool,
+}
+
+#[derive(Serialize)]
+struct JobCreated {
+    job_id: JobId,
+}
 
-    if !verify_team_token(&solution_req.token.into(), &solution_req.team_name) {
+pub async fn submit_solution(solution_req: SolutionSubmitRequest, options: SubmitOptions, state: ServerState) -> Result<impl warp::Reply, warp::Rejection> {
+    let ServerState { teams, challenges, secret_keys, contests, rate_limiter, scoring_pool, mut jobs, scoreboard, mut history, submission_store, .. } = state;
+
+    // A deleted or renamed team still holds a valid token for its old name
+    if !teams.contains(&solution_req.team_name).await
+        || !verify_team_token(&secret_keys, &solution_req.token.into(), &solution_req.team_name) {
         return Err(warp::reject::custom(WrongToken));
     }
 
     let SolutionSubmitRequest{solution, team_name, ..} = solution_req;
 
-    let new_scores = {
+    if let Some(contest) = contests.get(&solution.challenge).await {
+        let now = crate::submissions::now();
+        let state = contest.state_at(now);
+        if state != ContestState::Running {
+            let not_running = ContestNotRunning { contest, state, now };
+            record_rejection(&mut history, &team_name, &solution.challenge, &solution.solutions, &not_running.to_string()).await;
+            return Err(warp::reject::custom(not_running));
+        }
+    }
 
-        let relevant_challenge = challenges.iter()
-            .find(|&c| c.date == solution.challenge)
-            .ok_or(warp::reject::custom(UnknownChallenge))?;
+    if let Err(throttled) = rate_limiter.try_submit(&team_name, &solution.challenge, crate::submissions::now()).await {
+        let rate_limited = RateLimited { retry_after: throttled.retry_after };
+        // Only the first rejection of a window, a flooding team shouldn't fill the history and the event log
+        if throttled.first_in_window {
+            let error = format!("Too many submissions, retry in {} seconds", rate_limited.retry_after_secs());
+            record_rejection(&mut history, &team_name, &solution.challenge, &solution.solutions, &error).await;
+        }
+        return Err(warp::reject::custom(rate_limited));
+    }
+
+    if !challenges.iter().any(|c| c.date == solution.challenge) {
+        record_rejection(&mut history, &team_name, &solution.challenge, &solution.solutions, &UnknownChallenge.to_string()).await;
+        return Err(warp::reject::custom(UnknownChallenge));
+    }
+
+    let submission = Submission {
+        team_name,
+        challenge: solution.challenge,
+        solutions: Arc::new(solution.solutions),
+    };
 
-        let mut new_scores = HashMap::<InputFileName, Score>::new();
-        for input_file_name in &relevant_challenge.input_file_names {
-            let submission = match solution.solutions.get(input_file_name) {
-                None => { continue },
-                Some(sub) => sub,
+    if options.background {
+        let job_id = jobs.create().await;
+        let finished_job = job_id.clone();
+        tokio::spawn(async move {
+            let status = match submission.score(challenges, scoring_pool, scoreboard, history, submission_store).await {
+                Ok(scores) => JobStatus::Done { scores },
+                Err(e) => JobStatus::Failed { error: e.to_string() },
             };
+            jobs.finish(&finished_job, status).await;
+        });
+        Ok(warp::reply::json(&JobCreated { job_id }))
+    } else {
+        match submission.score(challenges, scoring_pool, scoreboard, history, submission_store).await {
+            Ok(scores) => Ok(warp::reply::json(&scores)),
+            Err(SubmitError::Rejected(e)) => Err(warp::reject::custom(BadSubmission(e))),
+            Err(SubmitError::Unscored(e)) => Err(warp::reject::custom(ScoringFailed(e))),
+        }
+    }
+}
 
-            let score = (relevant_challenge.score_function)(submission, input_file_name)
-                .map_err(|e| warp::reject::custom(BadSubmission(e)))?;
+#[derive(Debug)]
+enum SubmitError {
+    /// A case of the submission is invalid
+    Rejected(ScoringError),
+    /// The scoring job didn't finish
+    Unscored(JobError),
+}
 
-            new_scores.insert(input_file_name.clone(), score);
+impl fmt::Display for SubmitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SubmitError::Rejected(e) => write!(f, "{}", e),
+            SubmitError::Unscored(e) => write!(f, "{}", e),
         }
-        new_scores
-    };
+    }
+}
+
+/// A submission that passed the token, contest and rate limit checks
+struct Submission {
+    team_name: TeamName,
+    challenge: ChallengeDate,
+    solutions: Arc<HashMap<InputFileName, String>>,
+}
+
+impl Submission {
+    /// Scores every case on the scoring pool, then records the outcome in the history and the scoreboard.
+    /// The whole submission is rejected with its first invalid case.
+    async fn score(self, challenges: Arc<Vec<Challenge>>, scoring_pool: ScoringPool, mut scoreboard: ScoreBoard,
+                   mut history: SubmissionHistory, submission_store: SubmissionStore) -> Result<HashMap<InputFileName, Score>, SubmitError> {
+        let scoring = {
+            let challenge = self.challenge.clone();
+            let solutions = self.solutions.clone();
+            move || {
+                let relevant_challenge = challenges.iter()
+                    .find(|c| c.date == challenge)
+                    .expect("The challenge is checked before scoring");
+                score_cases(relevant_challenge, &solutions)
+            }
+        };
+
+        let scored = match scoring_pool.run(scoring).await {
+            Ok(Ok(scored)) => scored,
+            Ok(Err((input_file_name, case_id, e))) => {
+                self.record_judged(&mut history, &submission_store, &input_file_name, &case_id, Err(e.to_string())).await;
+                return Err(SubmitError::Rejected(e));
+            },
+            Err(e) => {
+                for (input_file_name, submission) in self.solutions.iter() {
+                    let record = SubmissionRecord::new(&self.team_name, &self.challenge, input_file_name, submission,
+                                                       Err(e.to_string()));
+                    if e == JobError::Crashed {
+                        log::error!("The scorer panicked on case {} of a {} submission by '{}', sha256 {}",
+                                    input_file_name.as_str(), self.challenge, self.team_name, record.submission_hash);
+                        // Kept to re-judge once the scorer is fixed
+                        submission_store.save(&record.submission_hash, submission).await;
+                    }
+                    history.record(record).await;
+                }
+                return Err(SubmitError::Unscored(e));
+            },
+        };
+
+        let mut new_scores = HashMap::new();
+        for (input_file_name, case_id, score) in scored {
+            self.record_judged(&mut history, &submission_store, &input_file_name, &case_id, Ok(score)).await;
+            new_scores.insert(case_id, score);
+        }
+        for (case_id, score) in &new_scores {
+            scoreboard.add_team_score(&self.team_name, case_id, *score, self.challenge.clone()).await;
+        }
+
+        Ok(new_scores)
+    }
+
+    /// Records a case the scorer judged, keeping its text to re-judge it
+    async fn record_judged(&self, history: &mut SubmissionHistory, submission_store: &SubmissionStore,
+                           input_file_name: &InputFileName, case_id: &InputFileName, outcome: Result<Score, String>) {
+        let submission = &self.solutions[input_file_name];
+        let record = SubmissionRecord::new(&self.team_name, &self.challenge, case_id, submission, outcome);
+        submission_store.save(&record.submission_hash, submission).await;
+        history.record(record).await;
+    }
+}
+
+/// The submitted name, case id and score of every case, or the submitted name, the case id if it
+/// resolved and the error of the first invalid case
+type ScoredCases = Result<Vec<(InputFileName, InputFileName, Score)>, (InputFileName, InputFileName, ScoringError)>;
+
+fn score_cases(challenge: &Challenge, solutions: &HashMap<InputFileName, String>) -> ScoredCases {
+    let mut scored = Vec::new();
+    for (input_file_name, submission) in solutions {
+        let case_id = match challenge.scorer.resolve_case(input_file_name) {
+            Ok(case_id) => case_id,
+            Err(e) => return Err((input_file_name.clone(), input_file_name.clone(), e)),
+        };
+
+        match challenge.scorer.score(submission, &case_id) {
+            Ok(score) => scored.push((input_file_name.clone(), case_id, score)),
+            Err(e) => return Err((input_file_name.clone(), case_id, e)),
+        }
+    }
+    Ok(scored)
+}
 
-    for (input_file_name, score) in &new_scores {
-        scoreboard.add_team_score(&team_name, input_file_name, *score, solution.challenge.clone()).await;
+/// Records every case of a submission that was refused as a whole
+async fn record_rejection(history: &mut SubmissionHistory, team_name: &TeamName, challenge: &ChallengeDate,
+                          solutions: &HashMap<InputFileName, String>, error: &str) {
+    for (input_file_name, submission) in solutions {
+        history.record(SubmissionRecord::new(team_name, challenge, input_file_name, submission,
+                                             Err(error.to_owned()))).await;
     }
+}
 
-    Ok(warp::reply::json(&new_scores))
+/// The jobs of background submissions, anyone holding a job id can poll it
+pub async fn view_job(job_id: JobId, jobs: JobTracker) -> Result<impl warp::Reply, std::convert::Infallible> {
+    match jobs.status(&job_id).await {
+        Some(status) => Ok(warp::reply::with_status(warp::reply::json(&status), http::StatusCode::OK)),
+        None => Ok(warp::reply::with_status(warp::reply::json(&crate::ApiError::UnknownJob), http::StatusCode::NOT_FOUND)),
+    }
 }
 
-pub async fn view_scoreboard(challenge_date: ChallengeDate, scoreboard: ScoreBoard, teams: TeamsDb) -> Result<impl warp::Reply, std::convert::Infallible> {
+/// The team token sent as `Authorization: Bearer <token>`
+fn bearer_token(authorization: Option<&str>) -> Option<crate::TeamToken> {
+    use std::str::FromStr;
 
-    let mut score_view = HashMap::new();
+    authorization
+        .filter(|value| value.starts_with("Bearer "))
+        .and_then(|value| HexString::from_str(value["Bearer ".len()..].trim()).ok())
+        .map(crate::TeamToken::from)
+}
+
+/// The submissions of a team, for the team holding its token as `Authorization: Bearer <token>`
+pub async fn team_submissions(
+    team_name: TeamName,
+    authorization: Option<String>,
+    teams: TeamsDb,
+    history: SubmissionHistory,
+    secret_keys: Arc<SecretKeys>,
+) -> Result<impl warp::Reply, std::convert::Infallible> {
+    if !teams.contains(&team_name).await {
+        return Ok(warp::reply::with_status(warp::reply::json(&crate::ApiError::UnknownTeam), http::StatusCode::NOT_FOUND));
+    }
+
+    match bearer_token(authorization.as_deref()) {
+        Some(token) if verify_team_token(&secret_keys, &token, &team_name) =>
+            Ok(warp::reply::with_status(warp::reply::json(&history.of_team(&team_name).await), http::StatusCode::OK)),
+        _ => Ok(warp::reply::with_status(warp::reply::json(&crate::ApiError::WrongToken), http::StatusCode::UNAUTHORIZED)),
+    }
+}
+
+async fn frozen_at(contests: &ContestSchedule, challenge_date: &ChallengeDate) -> Option<crate::submissions::Timestamp> {
+    contests.get(challenge_date).await
+        .and_then(|contest| contest.frozen_at(crate::submissions::now()))
+}
+
+/// The progress of every team on `challenge_date` as the requester may see it. While the scoreboard is frozen
+/// every team sees the scores from the freeze time, except for its own live scores when it sends its token
+/// as `Authorization: Bearer <token>`
+async fn visible_progress(
+    challenge_date: &ChallengeDate,
+    authorization: Option<&str>,
+    scoreboard: &ScoreBoard,
+    teams: &TeamsDb,
+    contests: &ContestSchedule,
+    history: &SubmissionHistory,
+    secret_keys: &SecretKeys,
+) -> Vec<(TeamName, Progress)> {
+    let frozen_at = frozen_at(contests, challenge_date).await;
+    let token = bearer_token(authorization);
+
+    let mut score_view = Vec::new();
     for tn in teams.list_team_names().await {
-        let best_score_of_team = scoreboard.total_score(&tn, challenge_date.clone()).await;
-        score_view.insert(tn, best_score_of_team);
+        let is_own_team = matches!(&token, Some(token) if verify_team_token(secret_keys, token, &tn));
+        let progress = match frozen_at {
+            Some(frozen_at) if !is_own_team => history.progress(&tn, challenge_date, Some(frozen_at)).await,
+            _ => Progress {
+                best_per_input: scoreboard.best_per_input(&tn, challenge_date.clone()).await,
+                ..history.progress(&tn, challenge_date, None).await
+            },
+        };
+        score_view.push((tn, progress));
+    }
+    score_view
+}
+
+/// Every team ranked by total score
+pub async fn view_scoreboard(
+    challenge_date: ChallengeDate,
+    authorization: Option<String>,
+    scoreboard: ScoreBoard,
+    teams: TeamsDb,
+    contests: ContestSchedule,
+    history: SubmissionHistory,
+    secret_keys: Arc<SecretKeys>,
+) -> Result<impl warp::Reply, std::convert::Infallible> {
+    let score_view = visible_progress(&challenge_date, authorization.as_deref(), &scoreboard, &teams, &contests, &history, &secret_keys).await;
+    Ok(warp::reply::json(&crate::scoreboard::rank(score_view)))
+}
+
+/// The teams that scored on `input` ranked by their best score on it, the input may be named by any of its aliases
+pub async fn view_input_scoreboard(challenge_date: ChallengeDate, input: InputFileName, authorization: Option<String>, state: ServerState) -> Result<impl warp::Reply, std::convert::Infallible> {
+    let ServerState { challenges, scoreboard, teams, contests, history, secret_keys, .. } = state;
+    let case_id = challenges.iter()
+        .find(|challenge| challenge.date == challenge_date)
+        .and_then(|challenge| challenge.scorer.resolve_case(&input).ok());
+    let case_id = match case_id {
+        Some(case_id) => case_id,
+        None => return Ok(warp::reply::with_status(warp::reply::json(&crate::ApiError::UnknownInput), http::StatusCode::NOT_FOUND)),
+    };
+
+    let score_view = visible_progress(&challenge_date, authorization.as_deref(), &scoreboard, &teams, &contests, &history, &secret_keys).await;
+    Ok(warp::reply::with_status(warp::reply::json(&crate::scoreboard::rank_on_input(score_view, &case_id)), http::StatusCode::OK))
+}
+
+/// The scoreboard as a csv or json file, as `view_scoreboard` shows it
+pub async fn export_scoreboard(challenge_date: ChallengeDate, options: ExportOptions, authorization: Option<String>, state: ServerState) -> Result<Box<dyn warp::Reply>, std::convert::Infallible> {
+    let format = match ExportFormat::parse(options.format.as_deref()) {
+        Some(format) => format,
+        None => return Ok(Box::new(unknown_export_format(&options))),
+    };
+    let ServerState { challenges, scoreboard, teams, contests, history, secret_keys, .. } = state;
+
+    let score_view = visible_progress(&challenge_date, authorization.as_deref(), &scoreboard, &teams, &contests, &history, &secret_keys).await;
+    let ranked = crate::scoreboard::rank(score_view);
+    let body = match format {
+        ExportFormat::Csv => {
+            let inputs = challenges.iter()
+                .find(|challenge| challenge.date == challenge_date)
+                .map(|challenge| challenge.scorer.input_cases())
+                .unwrap_or_default();
+            crate::export::scoreboard_csv(&ranked, &inputs)
+        },
+        ExportFormat::Json => serde_json::to_string_pretty(&ranked).expect("The scoreboard is always serializable"),
+    };
+
+    Ok(Box::new(crate::export::attachment(body, format, &format!("{}_scoreboard", challenge_date.id()))))
+}
+
+fn unknown_export_format(options: &ExportOptions) -> warp::reply::WithStatus<String> {
+    warp::reply::with_status(
+        format!("Unknown export format '{}', expected csv or json", options.format.as_deref().unwrap_or_default()),
+        http::StatusCode::BAD_REQUEST)
+}
+
+/// Streams the scoreboard updates of `challenge_date` as server-sent events. Improvements aren't
+/// pushed while the scoreboard is frozen, a client that falls behind is told to reload instead
+pub async fn scoreboard_events(challenge_date: ChallengeDate, scoreboard: ScoreBoard, contests: ContestSchedule) -> Result<impl warp::Reply, std::convert::Infallible> {
+    let updates = scoreboard.subscribe();
+    let events = futures::stream::unfold(updates, move |mut updates| {
+        let challenge_date = challenge_date.clone();
+        let contests = contests.clone();
+        async move {
+            loop {
+                let update = match updates.recv().await {
+                    Ok(update) => update,
+                    Err(tokio::sync::broadcast::RecvError::Lagged(_)) => ScoreboardUpdate::Reload { challenge: Some(challenge_date.clone()) },
+                    Err(tokio::sync::broadcast::RecvError::Closed) => return None,
+                };
+                if !update.concerns(&challenge_date) {
+                    continue;
+                }
+                if let ScoreboardUpdate::Improved { .. } = update {
+                    if frozen_at(&contests, &challenge_date).await.is_some() {
+                        continue;
+                    }
+                }
+                return Some((Ok::<_, std::convert::Infallible>(warp::sse::json(update)), updates));
+            }
+        }
+    });
+
+    Ok(warp::sse::reply(warp::sse::keep_alive().stream(events)))
+}
+
+pub async fn view_score_history(challenge_date: ChallengeDate, contests: ContestSchedule, history: SubmissionHistory) -> Result<impl warp::Reply, std::convert::Infallible> {
+    let mut score_history = history.total_score_history(&challenge_date).await;
+
+    if let Some(frozen_at) = frozen_at(&contests, &challenge_date).await {
+        for points in score_history.values_mut() {
+            points.retain(|point| point.at < frozen_at);
+        }
+        score_history.retain(|_, points| !points.is_empty());
     }
 
-    Ok(warp::reply::json(&score_view))
+    Ok(warp::reply::json(&score_history))
 }
 
-pub async fn handle_submit_rejection(rej: warp::Rejection) -> Result<impl warp::Reply, warp::Rejection> {
+pub async fn reveal_scoreboard(challenge_date: ChallengeDate, mut contests: ContestSchedule, scoreboard: ScoreBoard, audit_log: AuditLog) -> Result<impl warp::Reply, std::convert::Infallible> {
+    if contests.reveal(&challenge_date).await {
+        scoreboard.notify_changed(Some(challenge_date.clone()));
+        audit_log.record(AdminAction::ScoreboardRevealed { challenge: challenge_date.clone() }).await;
+        Ok(warp::reply::with_status(format!("The {} scoreboard is revealed", challenge_date), http::StatusCode::OK))
+    } else {
+        Ok(warp::reply::with_status(format!("There's no {} contest to reveal", challenge_date), http::StatusCode::NOT_FOUND))
+    }
+}
+
+fn no_such_team(team_name: &TeamName) -> warp::reply::WithStatus<String> {
+    warp::reply::with_status(format!("There's no team named '{}'", team_name), http::StatusCode::NOT_FOUND)
+}
+
+/// Removes a team with its scores and submissions
+pub async fn delete_team(team_name: TeamName, mut teams: TeamsDb, mut scoreboard: ScoreBoard, mut history: SubmissionHistory,
+                         audit_log: AuditLog, admin_lock: AdminLock) -> Result<impl warp::Reply, std::convert::Infallible> {
+    let _admin_lock = admin_lock.lock().await;
+    if !teams.remove(&team_name).await {
+        return Ok(no_such_team(&team_name));
+    }
+
+    let event = Event::TeamDeleted { team_name: team_name.clone() };
+    scoreboard.apply(&event).await;
+    history.apply(&event).await;
+    audit_log.record(AdminAction::TeamDeleted { team_name: team_name.clone() }).await;
+
+    Ok(warp::reply::with_status(format!("Team '{}' was deleted", team_name), http::StatusCode::OK))
+}
+
+#[derive(Deserialize)]
+pub struct RenameRequest {
+    new_name: TeamName,
+}
 
-    if let Some(UnknownChallenge) = rej.find() {
-        Ok("It seems like you're trying to play an unimplemented game".to_owned())
+/// Moves a team with its scores and submissions to a new name, replying with the token of the new name
+pub async fn rename_team(team_name: TeamName, rename_req: RenameRequest, state: ServerState) -> Result<Box<dyn warp::Reply>, std::convert::Infallible> {
+    let ServerState { mut teams, mut scoreboard, mut history, secret_keys, audit_log, admin_lock, .. } = state;
+    let _admin_lock = admin_lock.lock().await;
+    let new_name = rename_req.new_name;
+    match teams.rename(&team_name, &new_name).await {
+        Ok(()) => {},
+        Err(RenameError::UnknownTeam) => return Ok(Box::new(no_such_team(&team_name))),
+        Err(RenameError::NameTaken) => return Ok(Box::new(warp::reply::with_status(
+            format!("There's already a team named '{}'", new_name), http::StatusCode::CONFLICT))),
+        Err(RenameError::NameRetired) => return Ok(Box::new(warp::reply::with_status(
+            format!("The name '{}' belonged to another team and can't be used again", new_name), http::StatusCode::CONFLICT))),
+    }
+
+    let event = Event::TeamRenamed { from: team_name.clone(), to: new_name.clone() };
+    scoreboard.apply(&event).await;
+    history.apply(&event).await;
+    audit_log.record(AdminAction::TeamRenamed { from: team_name, to: new_name.clone() }).await;
+
+    Ok(Box::new(warp::reply::json(&sign_on_team_name(&secret_keys, &new_name))))
+}
+
+/// Clears a team's scores on a challenge, its submissions stay in the history marked as reset
+pub async fn reset_team_scores(team_name: TeamName, challenge_date: ChallengeDate, teams: TeamsDb, mut scoreboard: ScoreBoard,
+                               mut history: SubmissionHistory, audit_log: AuditLog, admin_lock: AdminLock) -> Result<impl warp::Reply, std::convert::Infallible> {
+    let _admin_lock = admin_lock.lock().await;
+    if !teams.contains(&team_name).await {
+        return Ok(no_such_team(&team_name));
+    }
+
+    scoreboard.reset_team_scores(&team_name, challenge_date.clone()).await;
+    history.apply(&Event::ScoresReset { team_name: team_name.clone(), challenge: challenge_date.clone() }).await;
+    audit_log.record(AdminAction::ScoresReset { team_name: team_name.clone(), challenge: challenge_date.clone() }).await;
+
+    Ok(warp::reply::with_status(format!("The {} scores of team '{}' were reset", challenge_date, team_name), http::StatusCode::OK))
+}
+
+/// Removes one submission, at its index in `/teams/{name}/submissions`, and recomputes the team's best scores without it
+pub async fn remove_submission(team_name: TeamName, index: usize, mut scoreboard: ScoreBoard, mut history: SubmissionHistory,
+                               audit_log: AuditLog, admin_lock: AdminLock) -> Result<impl warp::Reply, std::convert::Infallible> {
+    let _admin_lock = admin_lock.lock().await;
+    let record = match history.remove(&team_name, index).await {
+        Some(record) => record,
+        None => return Ok(warp::reply::with_status(
+            format!("Team '{}' has no submission {}", team_name, index), http::StatusCode::NOT_FOUND)),
+    };
+
+    let best_scores = history.best_per_input(&team_name, &record.challenge).await;
+    scoreboard.set_team_scores(&team_name, record.challenge.clone(), best_scores).await;
+    audit_log.record(AdminAction::SubmissionRemoved { team_name: team_name.clone(), index, record }).await;
+
+    Ok(warp::reply::with_status(format!("Submission {} of team '{}' was removed", index, team_name), http::StatusCode::OK))
+}
+
+/// Scores every stored submission to a challenge again, and swaps in the new scores
+pub async fn rejudge(challenge_date: ChallengeDate, state: ServerState) -> Result<Box<dyn warp::Reply>, std::convert::Infallible> {
+    let ServerState { challenges, scoring_pool, submission_store, scoreboard, mut history, audit_log, admin_lock, .. } = state;
+
+    if !challenges.iter().any(|c| c.date == challenge_date) {
+        return Ok(Box::new(warp::reply::with_status(format!("There's no {} scorer", challenge_date), http::StatusCode::NOT_FOUND)));
+    }
+
+    // Held until the new scores are in, a concurrent rejudge or removal would shift the submissions it addresses
+    let _admin_lock = admin_lock.lock().await;
+    match crate::rejudge::rejudge(&challenge_date, challenges, &scoring_pool, &submission_store, &scoreboard, &mut history).await {
+        Ok(report) => {
+            audit_log.record(AdminAction::Rejudged { challenge: challenge_date, changes: report.changes.clone() }).await;
+            Ok(Box::new(warp::reply::json(&report)))
+        },
+        Err(e) => Ok(Box::new(warp::reply::with_status(e.to_string(), http::StatusCode::SERVICE_UNAVAILABLE))),
+    }
+}
+
+/// Every submission of every team as a csv or json file
+pub async fn export_submissions(options: ExportOptions, history: SubmissionHistory) -> Result<Box<dyn warp::Reply>, std::convert::Infallible> {
+    let format = match ExportFormat::parse(options.format.as_deref()) {
+        Some(format) => format,
+        None => return Ok(Box::new(unknown_export_format(&options))),
+    };
+
+    let records = history.all().await;
+    let body = match format {
+        ExportFormat::Csv => crate::export::submissions_csv(&records),
+        ExportFormat::Json => serde_json::to_string_pretty(&records).expect("Submission records are always serializable"),
+    };
+
+    Ok(Box::new(crate::export::attachment(body, format, "submissions")))
+}
+
+pub async fn view_audit_log(audit_log: AuditLog) -> Result<impl warp::Reply, std::convert::Infallible> {
+    Ok(warp::reply::json(&audit_log.entries().await))
+}
+
+pub async fn view_workers(scoring_pool: ScoringPool) -> Result<impl warp::Reply, std::convert::Infallible> {
+    Ok(warp::reply::json(&scoring_pool.status()))
+}
+
+#[derive(Serialize)]
+struct ContestView {
+    /// The server time, for countdowns that don't trust the client clock
+    now: crate::submissions::Timestamp,
+    contests: Vec<Contest>,
+}
+
+pub async fn view_contests(contests: ContestSchedule) -> Result<impl warp::Reply, std::convert::Infallible> {
+    Ok(warp::reply::json(&ContestView { now: crate::submissions::now(), contests: contests.contests().await }))
+}
+
+pub async fn handle_submit_rejection(rej: warp::Rejection) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+
+    if let Some(unknown_challenge) = rej.find::<UnknownChallenge>() {
+        Ok(Box::new(unknown_challenge.to_string()))
+    } else if let Some(not_running) = rej.find::<ContestNotRunning>() {
+        Ok(Box::new(not_running.to_string()))
+    } else if let Some(rate_limited) = rej.find::<RateLimited>() {
+        let retry_after_secs = rate_limited.retry_after_secs();
+        let reply = warp::reply::with_status(format!("Too many submissions, retry in {} seconds", retry_after_secs),
+                                             http::StatusCode::TOO_MANY_REQUESTS);
+        Ok(Box::new(warp::reply::with_header(reply, "retry-after", retry_after_secs.to_string())))
     } else  if let Some(BadSubmission(scoring_err)) = rej.find() {
-        Ok(format!("{}", scoring_err))
+        Ok(Box::new(format!("{}", scoring_err)))
+    } else if let Some(ScoringFailed(JobError::QueueFull)) = rej.find() {
+        Ok(Box::new(warp::reply::with_status(JobError::QueueFull.to_string(), http::StatusCode::SERVICE_UNAVAILABLE)))
+    } else if let Some(ScoringFailed(job_err)) = rej.find() {
+        Ok(Box::new(job_err.to_string()))
     } else {
         Err(rej)
     }
 }
-
@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc, Mutex};
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::{oneshot, RwLock};
+use serde_derive::Serialize;
+
+use crate::models::solution::InputFileName;
+use crate::scoreboard::Score;
+use crate::submissions::Timestamp;
+
+/// The status of each job, with the time it finished
+type Jobs = HashMap<JobId, (JobStatus, Option<Timestamp>)>;
+
+/// How long finished jobs can be polled for
+const KEEP_FINISHED_JOBS: Timestamp = 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobError {
+    QueueFull,
+    TimedOut(Duration),
+    Crashed,
+}
+
+impl fmt::Display for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JobError::QueueFull => write!(f, "The scoring queue is full, please retry in a few seconds"),
+            JobError::TimedOut(timeout) => write!(f, "Scoring took longer than {} seconds", timeout.as_secs()),
+            JobError::Crashed => write!(f, "Internal scorer error, the organizers can see what went wrong"),
+        }
+    }
+}
+
+// The state of a task, set by whichever of its worker and its caller gets there first
+const RUNNING: u8 = 0;
+const FINISHED: u8 = 1;
+/// The job timed out, its worker was replaced and stops once the job returns
+const ABANDONED: u8 = 2;
+
+struct Task {
+    job: Box<dyn FnOnce() + Send>,
+    /// `RUNNING`, `FINISHED` or `ABANDONED`
+    state: Arc<AtomicU8>,
+}
+
+/// What the workers share, replacements included
+struct Workers {
+    receiver: Mutex<mpsc::Receiver<Task>>,
+    /// Used to name the worker threads
+    spawned: AtomicUsize,
+    /// Workers still running a job that timed out
+    stuck: AtomicUsize,
+}
+
+fn spawn_worker(workers: Arc<Workers>) {
+    let worker = workers.spawned.fetch_add(1, Ordering::SeqCst);
+    std::thread::Builder::new()
+        .name(format!("scorer-{}", worker))
+        .spawn(move || loop {
+            let task = match workers.receiver.lock() {
+                Ok(receiver) => receiver.recv(),
+                Err(_) => return,
+            };
+            let task = match task {
+                Ok(task) => task,
+                // The pool was dropped
+                Err(_) => return,
+            };
+
+            // A panicking job drops its result sender, the worker carries on
+            if panic::catch_unwind(AssertUnwindSafe(task.job)).is_err() {
+                log::error!("A scoring job panicked");
+            }
+            if task.state.compare_exchange(RUNNING, FINISHED, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+                workers.stuck.fetch_sub(1, Ordering::SeqCst);
+                log::warn!("Scoring worker {} finished a job that timed out, its replacement keeps going", worker);
+                return;
+            }
+        })
+        .expect("Should be able to spawn scoring workers");
+}
+
+/// How many workers the pool has, reported at `/admin/workers`
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct PoolStatus {
+    pub workers: usize,
+    /// Workers still busy with a job that timed out. Each one was replaced, so they don't count in `workers`
+    pub stuck_workers: usize,
+}
+
+/// Worker threads that run scoring jobs away from the async executor
+#[derive(Clone)]
+pub struct ScoringPool {
+    sender: mpsc::SyncSender<Task>,
+    workers: Arc<Workers>,
+    size: usize,
+    timeout: Duration,
+}
+
+impl ScoringPool {
+    /// `workers` threads taking jobs from a queue of at most `queue_size` waiting jobs
+    pub fn new(workers: usize, queue_size: usize, timeout: Duration) -> Self {
+        let (sender, receiver) = mpsc::sync_channel::<Task>(queue_size);
+        let shared = Arc::new(Workers {
+            receiver: Mutex::new(receiver),
+            spawned: AtomicUsize::new(0),
+            stuck: AtomicUsize::new(0),
+        });
+
+        for _worker in 0..workers {
+            spawn_worker(shared.clone());
+        }
+
+        Self { sender, workers: shared, size: workers, timeout }
+    }
+
+    /// Configured by `HASHCODE_SCORING_WORKERS` (4 by default), `HASHCODE_SCORING_QUEUE` (64)
+    /// and `HASHCODE_SCORING_TIMEOUT_SECS` (60)
+    pub fn from_env() -> Result<Self, std::num::ParseIntError> {
+        let from_env = |name: &str, default: u64| std::env::var(name)
+            .map(|value| value.parse::<u64>())
+            .unwrap_or(Ok(default));
+
+        // Without a worker every submission would wait in the queue forever
+        let workers = std::env::var("HASHCODE_SCORING_WORKERS")
+            .map(|value| value.parse::<NonZeroUsize>())
+            .unwrap_or_else(|_| Ok(NonZeroUsize::new(4).expect("4 isn't 0")))?;
+
+        Ok(Self::new(workers.get(),
+                     from_env("HASHCODE_SCORING_QUEUE", 64)? as usize,
+                     Duration::from_secs(from_env("HASHCODE_SCORING_TIMEOUT_SECS", 60)?)))
+    }
+
+    /// Runs `job` on a worker. The timeout starts when a worker picks the job up. A job that times
+    /// out can't be stopped, so its worker is replaced by a new one and stops once the job returns.
+    pub async fn run<T, F>(&self, job: F) -> Result<T, JobError>
+        where F: FnOnce() -> T + Send + 'static, T: Send + 'static
+    {
+        let (started_sender, started) = oneshot::channel();
+        let (result_sender, result) = oneshot::channel();
+        let state = Arc::new(AtomicU8::new(RUNNING));
+        let task = Task {
+            job: Box::new(move || {
+                let _ = started_sender.send(());
+                let _ = result_sender.send(job());
+            }),
+            state: state.clone(),
+        };
+
+        self.sender.try_send(task).map_err(|_| JobError::QueueFull)?;
+        started.await.map_err(|_| JobError::Crashed)?;
+
+        match tokio::time::timeout(self.timeout, result).await {
+            Ok(Ok(result)) => Ok(result),
+            Ok(Err(_)) => Err(JobError::Crashed),
+            Err(_) => {
+                // Unless the job finished right at the timeout, its worker is stuck with it
+                if state.compare_exchange(RUNNING, ABANDONED, Ordering::SeqCst, Ordering::SeqCst).is_ok() {
+                    self.workers.stuck.fetch_add(1, Ordering::SeqCst);
+                    log::warn!("A scoring job timed out, starting a worker to replace the one running it");
+                    spawn_worker(self.workers.clone());
+                }
+                Err(JobError::TimedOut(self.timeout))
+            },
+        }
+    }
+
+    pub fn status(&self) -> PoolStatus {
+        PoolStatus { workers: self.size, stuck_workers: self.workers.stuck.load(Ordering::SeqCst) }
+    }
+}
+
+pub type JobId = String;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum JobStatus {
+    Pending,
+    Done { scores: HashMap<InputFileName, Score> },
+    Failed { error: String },
+}
+
+/// The status of submissions scored in the background, polled at `/jobs/{id}`
+#[derive(Clone)]
+pub struct JobTracker {
+    inner: Arc<RwLock<Jobs>>,
+}
+
+impl JobTracker {
+    pub fn new() -> Self {
+        Self { inner: Arc::new(RwLock::new(HashMap::new())) }
+    }
+
+    /// A new pending job, with an id that can't be guessed by other teams
+    pub async fn create(&mut self) -> JobId {
+        let id = hex_string::HexString::from_bytes(&rand::random::<[u8; 16]>()).as_str().to_owned();
+        let now = crate::submissions::now();
+
+        let mut jobs = self.inner.write().await;
+        jobs.retain(|_, (_, finished_at)| match finished_at {
+            Some(finished_at) => *finished_at + KEEP_FINISHED_JOBS > now,
+            None => true,
+        });
+        jobs.insert(id.clone(), (JobStatus::Pending, None));
+        id
+    }
+
+    pub async fn finish(&mut self, id: &str, status: JobStatus) {
+        self.inner.write().await.insert(id.to_owned(), (status, Some(crate::submissions::now())));
+    }
+
+    pub async fn status(&self, id: &str) -> Option<JobStatus> {
+        self.inner.read().await.get(id).map(|(status, _)| status.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JobError, PoolStatus, ScoringPool};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn runs_jobs_on_workers() {
+        let pool = ScoringPool::new(2, 4, Duration::from_secs(5));
+        let caller = std::thread::current().id();
+
+        let worker = pool.run(|| std::thread::current().id()).await;
+        assert_ne!(worker, Ok(caller));
+    }
+
+    #[test]
+    fn zero_workers_are_rejected() {
+        // The only test that reads the variable
+        std::env::set_var("HASHCODE_SCORING_WORKERS", "0");
+        assert!(ScoringPool::from_env().is_err());
+        std::env::remove_var("HASHCODE_SCORING_WORKERS");
+    }
+
+    #[tokio::test]
+    async fn slow_job_times_out() {
+        let pool = ScoringPool::new(1, 1, Duration::from_millis(50));
+        let result = pool.run(|| std::thread::sleep(Duration::from_millis(500))).await;
+        assert_eq!(result, Err(JobError::TimedOut(Duration::from_millis(50))));
+    }
+
+    #[tokio::test]
+    async fn stuck_worker_is_replaced() {
+        let pool = ScoringPool::new(1, 1, Duration::from_millis(50));
+        let (release, released) = std::sync::mpsc::channel::<()>();
+
+        let result = pool.run(move || released.recv()).await;
+        assert_eq!(result, Err(JobError::TimedOut(Duration::from_millis(50))));
+        assert_eq!(pool.status(), PoolStatus { workers: 1, stuck_workers: 1 });
+
+        // The replacement takes new jobs while the stuck worker still waits
+        assert_eq!(pool.run(|| 16).await, Ok(16));
+
+        release.send(()).expect("The stuck worker should be waiting");
+        tokio::time::delay_for(Duration::from_millis(50)).await;
+        assert_eq!(pool.status(), PoolStatus { workers: 1, stuck_workers: 0 });
+        assert_eq!(pool.run(|| 17).await, Ok(17));
+    }
+
+    #[tokio::test]
+    async fn full_queue_is_rejected() {
+        let pool = ScoringPool::new(1, 1, Duration::from_secs(5));
+
+        let (started, worker_busy) = tokio::sync::oneshot::channel();
+        let running = pool.clone();
+        tokio::spawn(async move {
+            running.run(move || {
+                started.send(()).unwrap();
+                std::thread::sleep(Duration::from_millis(300));
+            }).await
+        });
+        worker_busy.await.unwrap();
+
+        let queued = pool.clone();
+        tokio::spawn(async move { queued.run(|| ()).await });
+        tokio::time::delay_for(Duration::from_millis(50)).await;
+
+        assert_eq!(pool.run(|| ()).await, Err(JobError::QueueFull));
+    }
+
+    #[tokio::test]
+    async fn panicking_job_does_not_kill_the_worker() {
+        let pool = ScoringPool::new(1, 1, Duration::from_secs(5));
+        assert_eq!(pool.run(|| panic!("bad submission")).await, Err::<(), _>(JobError::Crashed));
+        assert_eq!(pool.run(|| 16).await, Ok(16));
+    }
+}
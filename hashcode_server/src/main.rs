@@ -1,6 +1,3 @@
-#[macro_use]
-extern crate lazy_static;
-
 use hex_string::HexString;
 use hmac::Mac;
 use serde_derive::{Deserialize, Serialize};
@@ -10,12 +7,27 @@ mod handlers;
 mod models;
 mod teams_db;
 mod scoreboard;
+mod event_log;
+mod secret_keys;
+mod submissions;
+mod contest;
+mod admin;
+mod rate_limit;
+mod jobs;
+mod audit;
+mod submission_store;
+mod rejudge;
+mod export;
 
 
 #[derive(Debug, Serialize, Deserialize)]
 enum ApiError {
     ErrorTeamExists,
+    ErrorTeamNameRetired,
     WrongToken,
+    UnknownJob,
+    UnknownInput,
+    UnknownTeam,
 }
 impl warp::reject::Reject for ApiError {}
 
@@ -45,44 +57,45 @@ pub struct AccessGranted {
 
 use crate::models::TeamName;
 use teams_db::TeamsDb;
+use secret_keys::{SecretKey, SecretKeys};
 
-lazy_static!{
-    pub static ref SECRET_KEY: [u8; 32] = {
-        rand::random()
-    };
-}
-
-fn sign_on_team_name(team_name: &TeamName) -> TeamToken {
-    let mut mac = hmac::Hmac::<sha2::Sha256>::new_varkey(&*SECRET_KEY)
+fn team_name_mac(key: &SecretKey, team_name: &TeamName) -> hmac::Hmac<sha2::Sha256> {
+    let mut mac = hmac::Hmac::<sha2::Sha256>::new_varkey(key)
         .expect("Hmac init should never be a problem");
 
     mac.input(team_name.as_str().as_bytes());
+    mac
+}
 
+fn sign_on_team_name(keys: &SecretKeys, team_name: &TeamName) -> TeamToken {
     TeamToken {
-        token: mac.result().code().as_slice().into(),
+        token: team_name_mac(keys.current(), team_name).result().code().as_slice().into(),
     }
 }
 
-fn verify_team_token(token: &TeamToken, team_name: &TeamName) -> bool {
-    let mut mac = hmac::Hmac::<sha2::Sha256>::new_varkey(&*SECRET_KEY)
-        .expect("Hmac init should never be a problem");
-
-    mac.input(team_name.as_str().as_bytes());
-
-    match mac.verify(&token.token) {
-        Ok(_) => true,
-        Err(_) => false,
-    }
+/// Accepts tokens signed with the current key, or with the previous key during its grace period
+fn verify_team_token(keys: &SecretKeys, token: &TeamToken, team_name: &TeamName) -> bool {
+    keys.accepted()
+        .any(|key| team_name_mac(key, team_name).verify(&token.token).is_ok())
 }
 
 #[tokio::main]
 async fn main() {
-    use filters::game_api;
+    use filters::{game_api, ServerState};
     use scoreboard::ScoreBoard;
+    use event_log::EventLog;
+    use submissions::SubmissionHistory;
+    use contest::ContestSchedule;
 
     pretty_env_logger::init();
 
-    log::info!("Server secret key: {}", hex_string::HexString::from_bytes(&*SECRET_KEY).as_str());
+    let secret_keys = match SecretKeys::from_env() {
+        Ok(secret_keys) => secret_keys,
+        Err(e) => {
+            log::error!("Couldn't load the secret key: {}", e);
+            std::process::exit(1);
+        }
+    };
 
     let private_local_server: bool = std::env::var("HASHCODE_LOCAL").is_ok();
     let hashcode_port: u16 = std::env::var("HASHCODE_PORT")
@@ -91,10 +104,99 @@ async fn main() {
 
     let bind_address = (if private_local_server {[127u8,0,0,1]} else {[0,0,0,0]}, hashcode_port);
 
-    let teams = TeamsDb::new();
-    let scoreboard = ScoreBoard::new();
+    let challenges = match hashcode_score_calc::get_challenges() {
+        Ok(challenges) => challenges,
+        Err(e) => {
+            log::error!("Couldn't load the challenges: {}", e);
+            std::process::exit(1);
+        }
+    };
+    for challenge in &challenges {
+        log::info!("Loaded challenge {} with input files {:?}", challenge.date, challenge.input_file_names());
+    }
+
+    let event_log_path = std::env::var("HASHCODE_EVENT_LOG")
+        .unwrap_or_else(|_| "hashcode_events.jsonl".to_owned());
+    let (event_log, events) = match EventLog::open(&event_log_path) {
+        Ok(opened) => opened,
+        Err(e) => {
+            log::error!("Couldn't open the event log {}: {}", event_log_path, e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut contests = match std::env::var("HASHCODE_CONTEST_FILE") {
+        Ok(contest_file) => match ContestSchedule::from_file(&contest_file, event_log.clone()) {
+            Ok(contests) => contests,
+            Err(e) => {
+                log::error!("Couldn't load the contests from {}: {}", contest_file, e);
+                std::process::exit(1);
+            }
+        },
+        Err(_) => ContestSchedule::with_contests(vec![], event_log.clone()),
+    };
+    for contest in contests.contests().await {
+        log::info!("Contest {} runs from {} to {} (ms since epoch)", contest.challenge, contest.starts_at, contest.ends_at);
+    }
 
-    warp::serve(game_api(teams, scoreboard))
+    let mut teams = TeamsDb::with_event_log(event_log.clone());
+    let mut scoreboard = ScoreBoard::with_event_log(event_log.clone());
+    let mut history = SubmissionHistory::with_event_log(event_log);
+    for event in &events {
+        teams.apply(event).await;
+        scoreboard.apply(event).await;
+        history.apply(event).await;
+        contests.apply(event).await;
+    }
+    log::info!("Replayed {} events from {}", events.len(), event_log_path);
+
+    let rate_limiter = rate_limit::RateLimiter::from_env()
+        .expect("HASHCODE_SUBMISSIONS_PER_MINUTE should be a positive number");
+    let scoring_pool = jobs::ScoringPool::from_env()
+        .expect("HASHCODE_SCORING_WORKERS should be a positive number, HASHCODE_SCORING_QUEUE and HASHCODE_SCORING_TIMEOUT_SECS numbers");
+
+    let admin_token = admin::AdminToken::from_env();
+    if !admin_token.is_configured() {
+        log::warn!("HASHCODE_ADMIN_TOKEN isn't set, the admin endpoints are disabled");
+    }
+
+    let audit_log_path = std::env::var("HASHCODE_AUDIT_LOG")
+        .unwrap_or_else(|_| "hashcode_audit.jsonl".to_owned());
+    let audit_log = match audit::AuditLog::open(&audit_log_path) {
+        Ok(audit_log) => audit_log,
+        Err(e) => {
+            log::error!("Couldn't open the audit log {}: {}", audit_log_path, e);
+            std::process::exit(1);
+        }
+    };
+
+    let submissions_dir = std::env::var("HASHCODE_SUBMISSIONS_DIR")
+        .unwrap_or_else(|_| "hashcode_submissions".to_owned());
+    let submission_store = match submission_store::SubmissionStore::open(&submissions_dir) {
+        Ok(submission_store) => submission_store,
+        Err(e) => {
+            log::error!("Couldn't open the submissions directory {}: {}", submissions_dir, e);
+            std::process::exit(1);
+        }
+    };
+
+    let state = ServerState {
+        teams,
+        scoreboard,
+        challenges: std::sync::Arc::new(challenges),
+        secret_keys: std::sync::Arc::new(secret_keys),
+        history,
+        contests,
+        admin_token,
+        rate_limiter,
+        scoring_pool,
+        jobs: jobs::JobTracker::new(),
+        audit_log,
+        submission_store,
+        admin_lock: admin::AdminLock::default(),
+    };
+
+    warp::serve(game_api(state))
         .run(bind_address)
         .await;
 }
@@ -106,14 +208,168 @@ mod tests {
     use crate::models::solution::{Solution, ChallengeDate, SolutionSubmitRequest};
     use std::collections::HashMap;
     use crate::models::TeamName;
-    use crate::scoreboard::Score;
+    use crate::scoreboard::{InputRankedTeam, RankedTeam, Score};
+    use hashcode_score_calc::Challenge;
+    use crate::secret_keys::SecretKeys;
+    use crate::submissions::{ScorePoint, SubmissionHistory, SubmissionRecord};
+    use crate::contest::{Contest, ContestSchedule};
+    use crate::event_log::EventLog;
+    use crate::admin::{AdminLock, AdminToken};
+    use crate::rate_limit::RateLimiter;
+    use crate::jobs::{JobTracker, ScoringPool};
+    use crate::audit::AuditLog;
+    use crate::submission_store::SubmissionStore;
+    use crate::scoreboard::ScoreBoard;
+    use crate::filters::ServerState;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn challenges() -> Arc<Vec<Challenge>> {
+        Arc::new(hashcode_score_calc::get_challenges().expect("The shipped challenges should load"))
+    }
+
+    fn secret_keys() -> Arc<SecretKeys> {
+        Arc::new(SecretKeys::generate())
+    }
+
+    /// A fresh in-memory server, with "admin" as the admin token
+    fn server_state() -> ServerState {
+        ServerState {
+            teams: TeamsDb::new(),
+            scoreboard: ScoreBoard::new(),
+            challenges: challenges(),
+            secret_keys: secret_keys(),
+            history: SubmissionHistory::new(),
+            contests: ContestSchedule::with_contests(vec![], EventLog::in_memory()),
+            admin_token: AdminToken::new(Some("admin".to_owned())),
+            rate_limiter: RateLimiter::unlimited(),
+            scoring_pool: ScoringPool::new(2, 8, Duration::from_secs(10)),
+            jobs: JobTracker::new(),
+            audit_log: AuditLog::in_memory(),
+            submission_store: SubmissionStore::in_memory(),
+            admin_lock: AdminLock::default(),
+        }
+    }
+
+    async fn register<F>(api: &F, team_name: &str) -> TeamToken
+        where F: warp::Filter + 'static, F::Extract: warp::Reply + Send
+    {
+        let new_team = Team {
+            name: team_name.into(),
+            participants: vec!["ori".to_owned()],
+        };
+        let res = warp::test::request()
+            .path("/register_team")
+            .method("POST")
+            .json(&new_team)
+            .reply(api)
+            .await;
+        serde_json::from_slice(res.body()).expect("should receive token")
+    }
+
+    fn example_submission(team_name: &str, token: &TeamToken) -> SolutionSubmitRequest {
+        SolutionSubmitRequest {
+            solution: Solution {
+                challenge: ChallengeDate::Qualification(2020),
+                solutions: {
+                    let mut h = HashMap::new();
+                    h.insert("a".into(),
+                             include_str!("../../hashcode_score_calc/assets/2020qual/submissions/example_submission.txt").to_owned());
+                    h
+                }
+            },
+            team_name: team_name.into(),
+            token: hex_string::HexString::from_bytes(&token.token)
+        }
+    }
+
+    /// The total of every team in a `/scoreboard/<challenge>` reply
+    fn scoreboard_totals(body: &[u8]) -> HashMap<TeamName, Score> {
+        serde_json::from_slice::<Vec<RankedTeam>>(body).expect("Should be a json")
+            .into_iter()
+            .map(|team| (team.team_name, team.total_score))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_ranked_scoreboard() {
+        let api = crate::filters::game_api(server_state());
+
+        let mut tokens = Vec::new();
+        for team_name in &["late_team", "early_team", "idle_team"] {
+            tokens.push(register(&api, team_name).await);
+        }
+        for &(team_name, token) in &[("early_team", &tokens[1]), ("late_team", &tokens[0])] {
+            let res = warp::test::request()
+                .method("POST")
+                .path("/submit")
+                .json(&example_submission(team_name, token))
+                .reply(&api)
+                .await;
+            assert_eq!(res.status(), http::StatusCode::OK);
+            tokio::time::delay_for(Duration::from_millis(5)).await;
+        }
+
+        let res = warp::test::request().path("/scoreboard/qual2020").reply(&api).await;
+        let ranked: Vec<RankedTeam> = serde_json::from_slice(res.body()).expect("Should be a json");
+        let ranks: Vec<(usize, &str, Score)> = ranked.iter()
+            .map(|team| (team.rank, team.team_name.as_str(), team.total_score))
+            .collect();
+        assert_eq!(ranks, vec![(1, "early_team", 16), (2, "late_team", 16), (3, "idle_team", 0)], "A tie goes to the first team to reach it");
+        assert_eq!(ranked[0].best_per_input[&"a".into()], 16);
+        assert!(ranked[0].achieved_at < ranked[1].achieved_at);
+        assert_eq!(ranked[2].achieved_at, None);
+
+        let res = warp::test::request().path("/scoreboard/qual2020/a_example").reply(&api).await;
+        let on_input: Vec<InputRankedTeam> = serde_json::from_slice(res.body()).expect("Should be a json");
+        let ranks: Vec<(usize, &str, Score)> = on_input.iter()
+            .map(|team| (team.rank, team.team_name.as_str(), team.score))
+            .collect();
+        assert_eq!(ranks, vec![(1, "early_team", 16), (2, "late_team", 16)], "Only the teams that scored on the input are ranked");
+
+        let res = warp::test::request().path("/scoreboard/qual2020/z").reply(&api).await;
+        assert_eq!(res.status(), http::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_exports() {
+        let api = crate::filters::game_api(server_state());
+
+        let token = register(&api, "first_team").await;
+        let res = warp::test::request().method("POST").path("/submit")
+            .json(&example_submission("first_team", &token))
+            .reply(&api).await;
+        assert_eq!(res.status(), http::StatusCode::OK);
+
+        let res = warp::test::request().path("/scoreboard/qual2020/export?format=csv").reply(&api).await;
+        assert_eq!(res.headers()["content-disposition"], "attachment; filename=\"qual2020_scoreboard.csv\"");
+        let csv = String::from_utf8(res.body().to_vec()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "rank,team_name,a,b,c,d,e,f,total_score,achieved_at");
+        assert!(lines[1].starts_with("1,first_team,16,,,,,,16,"));
+
+        let res = warp::test::request().path("/scoreboard/qual2020/export").reply(&api).await;
+        let ranked: Vec<RankedTeam> = serde_json::from_slice(res.body()).expect("Json is the default");
+        assert_eq!(ranked[0].total_score, 16);
+
+        let res = warp::test::request().path("/scoreboard/qual2020/export?format=xls").reply(&api).await;
+        assert_eq!(res.status(), http::StatusCode::BAD_REQUEST);
+
+        let res = warp::test::request().path("/admin/submissions/export?format=csv").reply(&api).await;
+        assert_eq!(res.status(), http::StatusCode::UNAUTHORIZED);
+
+        let res = warp::test::request().path("/admin/submissions/export?format=json")
+            .header("authorization", "Bearer admin")
+            .reply(&api).await;
+        let records: Vec<SubmissionRecord> = serde_json::from_slice(res.body()).expect("Should be a json");
+        assert_eq!((records.len(), records[0].score), (1, Some(16)));
+    }
 
     #[tokio::test]
     async fn test_list_empty_teams() {
-        use crate::scoreboard::ScoreBoard;
         let teams_db = TeamsDb::new();
         let scoreboard = ScoreBoard::new();
-        let api = crate::filters::game_api(teams_db.clone(), scoreboard);
+        let api = crate::filters::game_api(ServerState { teams: teams_db.clone(), scoreboard, ..server_state() });
 
         let res = warp::test::request().path("/teams").reply(&api).await;
 
@@ -123,11 +379,10 @@ mod tests {
 
     #[tokio::test]
     async fn test_add_team() {
-        use crate::scoreboard::ScoreBoard;
 
         let teams_db = TeamsDb::new();
         let scoreboard = ScoreBoard::new();
-        let api = crate::filters::game_api(teams_db.clone(), scoreboard);
+        let api = crate::filters::game_api(ServerState { teams: teams_db.clone(), scoreboard, ..server_state() });
 
         let new_team = Team {
             name: "first_team".into(),
@@ -146,16 +401,349 @@ mod tests {
         assert!(teams_db.contains(&new_team.name).await);
     }
 
+    #[tokio::test]
+    async fn test_token_renewal_after_key_rotation() {
+        use hex_string::HexString;
+        use crate::models::TokenRenewRequest;
+
+        let teams_db = TeamsDb::new();
+        let scoreboard = ScoreBoard::new();
+        let mut secret_keys = SecretKeys::generate();
+
+        let api = crate::filters::game_api(ServerState {
+            teams: teams_db.clone(),
+            scoreboard: scoreboard.clone(),
+            secret_keys: Arc::new(secret_keys.clone()),
+            ..server_state()
+        });
+        let new_team = Team {
+            name: "first_team".into(),
+            participants: vec!["ori".to_owned()],
+        };
+        let res = warp::test::request()
+            .path("/register_team")
+            .method("POST")
+            .json(&new_team)
+            .reply(&api)
+            .await;
+        let old_token: TeamToken = serde_json::from_slice(res.body()).expect("should receive token");
+
+        secret_keys.rotate(std::time::Duration::from_secs(60));
+        let api = crate::filters::game_api(ServerState {
+            teams: teams_db.clone(),
+            scoreboard,
+            secret_keys: Arc::new(secret_keys.clone()),
+            ..server_state()
+        });
+
+        let res = warp::test::request()
+            .path("/renew_token")
+            .method("POST")
+            .json(&TokenRenewRequest {
+                team_name: new_team.name.clone(),
+                token: HexString::from_bytes(&old_token.token),
+            })
+            .reply(&api)
+            .await;
+        let new_token: TeamToken = serde_json::from_slice(res.body()).expect("should receive a renewed token");
+
+        assert_ne!(new_token.token, old_token.token);
+        assert!(crate::verify_team_token(&secret_keys, &new_token, &new_team.name));
+        assert!(crate::verify_team_token(&secret_keys, &old_token, &new_team.name),
+                "The old token is still valid during the grace period");
+
+        let res = warp::test::request()
+            .path("/renew_token")
+            .method("POST")
+            .json(&TokenRenewRequest {
+                team_name: "other_team".into(),
+                token: HexString::from_bytes(&old_token.token),
+            })
+            .reply(&api)
+            .await;
+        assert_eq!(res.body(), "\"WrongToken\"");
+    }
+
+    #[tokio::test]
+    async fn test_submission_outside_contest() {
+
+        let teams_db = TeamsDb::new();
+        let scoreboard = ScoreBoard::new();
+        let ended = Contest { challenge: ChallengeDate::Qualification(2020), starts_at: 0, ends_at: 1000, freezes_at: None, revealed: false };
+        let history = SubmissionHistory::new();
+        let api = crate::filters::game_api(ServerState {
+            teams: teams_db.clone(),
+            scoreboard: scoreboard.clone(),
+            contests: ContestSchedule::with_contests(vec![ended], EventLog::in_memory()),
+            history: history.clone(),
+            ..server_state()
+        });
+
+        let res = warp::test::request().path("/contest").reply(&api).await;
+        assert_eq!(res.status(), http::StatusCode::OK);
+        let contest_view: serde_json::Value = serde_json::from_slice(res.body()).expect("Should be a json");
+        assert_eq!(contest_view["contests"][0]["ends_at"], 1000);
+
+        let team_token = register(&api, "late_team").await;
+        let res = warp::test::request()
+            .method("POST")
+            .path("/submit")
+            .json(&example_submission("late_team", &team_token))
+            .reply(&api)
+            .await;
+
+        assert_eq!(res.body(), "The qualification 2020 contest has ended, submissions are closed");
+        assert_eq!(scoreboard.total_score(&"late_team".into(), ChallengeDate::Qualification(2020)).await, 0);
+
+        let mut unknown_challenge = example_submission("late_team", &team_token);
+        unknown_challenge.solution.challenge = ChallengeDate::Qualification(2015);
+        let res = warp::test::request()
+            .method("POST")
+            .path("/submit")
+            .json(&unknown_challenge)
+            .reply(&api)
+            .await;
+        assert_eq!(res.body(), "It seems like you're trying to play an unimplemented game");
+
+        let errors: Vec<_> = history.of_team(&"late_team".into()).await.into_iter().map(|record| record.error).collect();
+        assert_eq!(errors, vec![Some("The qualification 2020 contest has ended, submissions are closed".to_owned()),
+                                Some("It seems like you're trying to play an unimplemented game".to_owned())]);
+    }
+
+    #[tokio::test]
+    async fn test_frozen_scoreboard() {
+        use hex_string::HexString;
+
+        let frozen = Contest {
+            challenge: ChallengeDate::Qualification(2020),
+            starts_at: 0,
+            ends_at: crate::submissions::now() + 60 * 60 * 1000,
+            freezes_at: Some(0),
+            revealed: false,
+        };
+        let api = crate::filters::game_api(ServerState {
+            contests: ContestSchedule::with_contests(vec![frozen], EventLog::in_memory()),
+            ..server_state()
+        });
+
+        let team_token = register(&api, "first_team").await;
+        let res = warp::test::request()
+            .method("POST")
+            .path("/submit")
+            .json(&example_submission("first_team", &team_token))
+            .reply(&api)
+            .await;
+        assert_eq!(res.body(), "{\"a\":16}");
+
+        let public_score = || async {
+            let res = warp::test::request().path("/scoreboard/qual2020").reply(&api).await;
+            scoreboard_totals(res.body())[&TeamName::from("first_team")]
+        };
+        assert_eq!(public_score().await, 0, "The public scoreboard is frozen");
+
+        let res = warp::test::request()
+            .path("/scoreboard/qual2020")
+            .header("authorization", format!("Bearer {}", HexString::from_bytes(&team_token.token).as_str()))
+            .reply(&api)
+            .await;
+        let own_view = scoreboard_totals(res.body());
+        assert_eq!(own_view[&TeamName::from("first_team")], 16, "A team sees its own live score");
+
+        let reveal = |authorization: &'static str| warp::test::request()
+            .method("POST")
+            .path("/admin/scoreboard/qual2020/reveal")
+            .header("authorization", authorization)
+            .reply(&api);
+        assert_eq!(reveal("Bearer not-admin").await.status(), http::StatusCode::UNAUTHORIZED);
+        assert_eq!(public_score().await, 0);
+        assert_eq!(reveal("Bearer admin").await.status(), http::StatusCode::OK);
+        assert_eq!(public_score().await, 16);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_submission() {
+
+        let history = SubmissionHistory::new();
+        let api = crate::filters::game_api(ServerState {
+            history: history.clone(),
+            rate_limiter: RateLimiter::new(1, 60 * 1000),
+            ..server_state()
+        });
+
+        let team_token = register(&api, "eager_team").await;
+        let submit = || warp::test::request()
+            .method("POST")
+            .path("/submit")
+            .json(&example_submission("eager_team", &team_token))
+            .reply(&api);
+
+        assert_eq!(submit().await.status(), http::StatusCode::OK);
+
+        let res = submit().await;
+        assert_eq!(res.status(), http::StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = res.headers()["retry-after"].to_str().unwrap().parse().expect("Retry-After should be in seconds");
+        assert!(retry_after > 0 && retry_after <= 60);
+
+        assert_eq!(submit().await.status(), http::StatusCode::TOO_MANY_REQUESTS);
+
+        let res = warp::test::request()
+            .path("/teams/eager_team/submissions")
+            .header("authorization", format!("Bearer {}", hex_string::HexString::from_bytes(&team_token.token).as_str()))
+            .reply(&api)
+            .await;
+        let submissions: Vec<SubmissionRecord> = serde_json::from_slice(res.body()).expect("Should be a json");
+        assert_eq!(submissions.len(), 2, "Only the first rejection of the window is recorded");
+        assert!(submissions[1].error.as_ref().expect("rate limited").starts_with("Too many submissions"));
+        assert_eq!(history.of_team(&"eager_team".into()).await, submissions);
+    }
+
+    #[tokio::test]
+    async fn test_admin_team_management() {
+
+        let teams = TeamsDb::new();
+        let scoreboard = ScoreBoard::new();
+        let history = SubmissionHistory::new();
+        let audit_log = AuditLog::in_memory();
+        let keys = secret_keys();
+        let challenge = ChallengeDate::Qualification(2020);
+        let api = crate::filters::game_api(ServerState {
+            teams: teams.clone(),
+            secret_keys: keys.clone(),
+            scoreboard: scoreboard.clone(),
+            history: history.clone(),
+            audit_log: audit_log.clone(),
+            ..server_state()
+        });
+
+        let spam_token = register(&api, "spam").await;
+        let typo_token = register(&api, "tpyo").await;
+        for (team_name, token) in &[("spam", &spam_token), ("tpyo", &typo_token), ("tpyo", &typo_token)] {
+            let res = warp::test::request().method("POST").path("/submit")
+                .json(&example_submission(team_name, token))
+                .reply(&api).await;
+            assert_eq!(res.status(), http::StatusCode::OK);
+        }
+
+        let admin = |method: &str, path: &str| warp::test::request()
+            .method(method)
+            .path(path)
+            .header("authorization", "Bearer admin");
+
+        let res = warp::test::request().method("DELETE").path("/admin/teams/spam").reply(&api).await;
+        assert_eq!(res.status(), http::StatusCode::UNAUTHORIZED);
+        assert_eq!(admin("DELETE", "/admin/teams/spam").reply(&api).await.status(), http::StatusCode::OK);
+        assert_eq!(admin("DELETE", "/admin/teams/spam").reply(&api).await.status(), http::StatusCode::NOT_FOUND);
+        assert!(!teams.contains(&"spam".into()).await);
+        assert!(history.of_team(&"spam".into()).await.is_empty());
+        let res = warp::test::request().method("POST").path("/submit")
+            .json(&example_submission("spam", &spam_token))
+            .reply(&api).await;
+        assert_ne!(res.status(), http::StatusCode::OK, "A deleted team can't submit");
+        let res = warp::test::request().method("POST").path("/register_team")
+            .json(&Team { name: "Spam".into(), participants: vec![] })
+            .reply(&api).await;
+        assert_eq!(res.body(), "\"ErrorTeamNameRetired\"");
+        assert!(!teams.contains(&"spam".into()).await);
+
+        let res = admin("POST", "/admin/teams/tpyo/rename").json(&serde_json::json!({"new_name": "typo"})).reply(&api).await;
+        assert_eq!(res.status(), http::StatusCode::OK);
+        let new_token: TeamToken = serde_json::from_slice(res.body()).unwrap();
+        assert!(crate::verify_team_token(&keys, &new_token, &"typo".into()));
+        assert!(!crate::verify_team_token(&keys, &typo_token, &"typo".into()));
+        assert_eq!(scoreboard.total_score(&"typo".into(), challenge.clone()).await, 16);
+        assert_eq!(history.of_team(&"typo".into()).await.len(), 2);
+        let res = warp::test::request().method("POST").path("/submit")
+            .json(&example_submission("tpyo", &typo_token))
+            .reply(&api).await;
+        assert_ne!(res.status(), http::StatusCode::OK, "The old name can't submit");
+        let res = admin("POST", "/admin/teams/typo/rename").json(&serde_json::json!({"new_name": "spam"})).reply(&api).await;
+        assert_eq!(res.status(), http::StatusCode::CONFLICT);
+
+        register(&api, "other").await;
+        let res = admin("POST", "/admin/teams/typo/rename").json(&serde_json::json!({"new_name": "Other"})).reply(&api).await;
+        assert_eq!(res.status(), http::StatusCode::CONFLICT);
+
+        assert_eq!(admin("DELETE", "/admin/teams/typo/submissions/0").reply(&api).await.status(), http::StatusCode::OK);
+        assert_eq!(history.of_team(&"typo".into()).await.len(), 1);
+        assert_eq!(scoreboard.total_score(&"typo".into(), challenge.clone()).await, 16, "The other submission has the same score");
+        assert_eq!(admin("DELETE", "/admin/teams/typo/submissions/5").reply(&api).await.status(), http::StatusCode::NOT_FOUND);
+
+        assert_eq!(admin("DELETE", "/admin/teams/typo/scores/qual2020").reply(&api).await.status(), http::StatusCode::OK);
+        assert_eq!(scoreboard.total_score(&"typo".into(), challenge.clone()).await, 0);
+        assert!(history.of_team(&"typo".into()).await.iter().all(|record| record.reset), "Reset submissions are kept");
+
+        let res = admin("POST", "/admin/rejudge/qual2020").reply(&api).await;
+        assert_eq!(res.status(), http::StatusCode::OK);
+        let report: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(report["changes"], serde_json::json!([]), "The scorer didn't change");
+
+        let res = admin("GET", "/admin/audit").reply(&api).await;
+        let entries: Vec<serde_json::Value> = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(entries.len(), 5);
+        assert_eq!(audit_log.entries().await.len(), 5);
+        assert!(entries[1]["action"]["TeamRenamed"].is_object());
+    }
+
+    #[tokio::test]
+    async fn test_admin_workers() {
+        let scoring_pool = ScoringPool::new(1, 1, Duration::from_millis(50));
+        let api = crate::filters::game_api(ServerState { scoring_pool: scoring_pool.clone(), ..server_state() });
+        let workers = || warp::test::request().path("/admin/workers");
+
+        assert_eq!(workers().reply(&api).await.status(), http::StatusCode::UNAUTHORIZED);
+
+        let (release, released) = std::sync::mpsc::channel::<()>();
+        let _ = scoring_pool.run(move || released.recv()).await;
+        let res = workers().header("authorization", "Bearer admin").reply(&api).await;
+        assert_eq!(res.status(), http::StatusCode::OK);
+        let status: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        assert_eq!(status, serde_json::json!({"workers": 1, "stuck_workers": 1}));
+        release.send(()).expect("The stuck worker should be waiting");
+    }
+
+    #[tokio::test]
+    async fn test_background_submission() {
+
+        let scoreboard = ScoreBoard::new();
+        let api = crate::filters::game_api(ServerState { scoreboard: scoreboard.clone(), ..server_state() });
+
+        let team_token = register(&api, "patient_team").await;
+        let res = warp::test::request()
+            .method("POST")
+            .path("/submit?async=true")
+            .json(&example_submission("patient_team", &team_token))
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), http::StatusCode::OK);
+        let job: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+        let job_id = job["job_id"].as_str().expect("A background submission should reply with a job id");
+
+        let mut status = serde_json::Value::Null;
+        for _ in 0..100 {
+            let res = warp::test::request().path(&format!("/jobs/{}", job_id)).reply(&api).await;
+            assert_eq!(res.status(), http::StatusCode::OK);
+            status = serde_json::from_slice(res.body()).unwrap();
+            if status["status"] != "pending" {
+                break;
+            }
+            tokio::time::delay_for(Duration::from_millis(20)).await;
+        }
+        assert_eq!(status, serde_json::json!({"status": "done", "scores": {"a": 16}}));
+        assert_eq!(scoreboard.total_score(&"patient_team".into(), ChallengeDate::Qualification(2020)).await, 16);
+
+        let res = warp::test::request().path("/jobs/0123").reply(&api).await;
+        assert_eq!(res.status(), http::StatusCode::NOT_FOUND);
+    }
+
     #[tokio::test]
     async fn test_team_access() {
         use hex_string::HexString;
-        use crate::scoreboard::ScoreBoard;
 
         let teams_db = TeamsDb::new();
         let scoreboard = ScoreBoard::new();
         let challenge = ChallengeDate::Qualification(2020);
 
-        let api = crate::filters::game_api(teams_db.clone(), scoreboard.clone());
+        let api = crate::filters::game_api(ServerState { teams: teams_db.clone(), scoreboard: scoreboard.clone(), ..server_state() });
 
         let new_team = Team {
             name: "first team בעברית".into(),
@@ -212,18 +800,39 @@ mod tests {
         assert_eq!(scoreboard.total_score(&new_team.name, challenge).await,
         16,
         "The example should score 16 points");
+
+        let submissions_path = format!("/teams/{}/submissions",
+            percent_encoding::utf8_percent_encode(new_team.name.as_str(), percent_encoding::NON_ALPHANUMERIC));
+
+        let res = warp::test::request()
+            .path(&submissions_path)
+            .header("authorization", format!("Bearer {}", HexString::from_bytes(&team_token.token).as_str()))
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), http::StatusCode::OK);
+        let submissions: Vec<SubmissionRecord> = serde_json::from_slice(res.body()).expect("Should be a json");
+        assert_eq!(submissions.len(), 1);
+        assert_eq!((submissions[0].input.as_str(), submissions[0].score), ("a", Some(16)));
+
+        let res = warp::test::request()
+            .path(&submissions_path)
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), http::StatusCode::UNAUTHORIZED, "Submissions are only shown with the team's token");
+
+        let res = warp::test::request().path("/teams/nobody/submissions").reply(&api).await;
+        assert_eq!(res.status(), http::StatusCode::NOT_FOUND);
     }
 
     #[tokio::test]
     async fn test_scoreboard() {
         use hex_string::HexString;
-        use crate::scoreboard::ScoreBoard;
         use crate::models::solution::ChallengeDate;
 
         let teams_db = TeamsDb::new();
         let scoreboard = ScoreBoard::new();
 
-        let api = crate::filters::game_api(teams_db.clone(), scoreboard.clone());
+        let api = crate::filters::game_api(ServerState { teams: teams_db.clone(), scoreboard: scoreboard.clone(), ..server_state() });
 
         let empty_scoreboard = {
             let res = warp::test::request()
@@ -238,7 +847,7 @@ mod tests {
                 "Couldn't retrieve scoreboard"
             );
 
-            serde_json::from_slice::<HashMap<TeamName, Score>>(res.body()).expect("Should be a json")
+            scoreboard_totals(res.body())
         };
 
         assert_eq!(
@@ -314,11 +923,19 @@ mod tests {
                 "Couldn't retrieve scoreboard"
             );
 
-            serde_json::from_slice::<HashMap<TeamName, Score>>(res.body()).expect("Should be a json")
+            scoreboard_totals(res.body())
         };
 
         assert_eq!(score[&new_team.name], 16);
 
+        let res = warp::test::request()
+            .path("/scoreboard/qual2020/history")
+            .method("GET")
+            .reply(&api)
+            .await;
+        let history: HashMap<TeamName, Vec<ScorePoint>> = serde_json::from_slice(res.body()).expect("Should be a json");
+        assert_eq!(history[&new_team.name].iter().map(|point| point.total_score).collect::<Vec<_>>(), vec![16]);
+
 
 
         let score_for_different_challenge = {
@@ -334,7 +951,7 @@ mod tests {
                 "Couldn't retrieve scoreboard"
             );
 
-            serde_json::from_slice::<HashMap<TeamName, Score>>(res.body()).expect("Should be a json")
+            scoreboard_totals(res.body())
         };
 
         assert_eq!(score_for_different_challenge[&new_team.name], 0)
@@ -42,3 +42,10 @@ pub mod solution {
 
 pub use team::Team;
 pub use team_name::TeamName;
+
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug)]
+pub struct TokenRenewRequest {
+    pub team_name: TeamName,
+    #[serde(with="solution::token_from_string")]
+    pub token: hex_string::HexString
+}
@@ -4,7 +4,7 @@ use std::hash::{Hash, Hasher};
 
 use super::team_name::TeamName;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Team {
     pub name: TeamName,
     pub participants: Vec<String>,
@@ -0,0 +1,116 @@
+use std::collections::{HashMap, VecDeque};
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::models::TeamName;
+use crate::models::solution::ChallengeDate;
+use crate::submissions::Timestamp;
+
+const MINUTE: Timestamp = 60 * 1000;
+
+/// The accepted submissions still in the window, and until when a rejection was already reported
+#[derive(Default)]
+struct Recent {
+    times: VecDeque<Timestamp>,
+    rejected_until: Timestamp,
+}
+
+type RecentSubmissions = HashMap<(TeamName, ChallengeDate), Recent>;
+
+/// A submission over the limit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Throttled {
+    /// How long until a submission is allowed
+    pub retry_after: Timestamp,
+    /// The first submission refused since the oldest accepted one, the others repeat it
+    pub first_in_window: bool,
+}
+
+/// Limits how many submissions a team makes to a challenge in a sliding window
+#[derive(Clone)]
+pub struct RateLimiter {
+    /// The number of submissions allowed per window, and the window length
+    limit: Option<(usize, Timestamp)>,
+    recent: Arc<Mutex<RecentSubmissions>>,
+}
+
+impl RateLimiter {
+    pub fn new(submissions: usize, window: Timestamp) -> Self {
+        Self { limit: Some((submissions, window)), recent: Arc::new(Mutex::new(HashMap::new())) }
+    }
+
+    pub fn unlimited() -> Self {
+        Self { limit: None, recent: Arc::new(Mutex::new(HashMap::new())) }
+    }
+
+    /// Limited to `HASHCODE_SUBMISSIONS_PER_MINUTE` when it is set, which can't be 0
+    pub fn from_env() -> Result<Self, std::num::ParseIntError> {
+        match std::env::var("HASHCODE_SUBMISSIONS_PER_MINUTE") {
+            Ok(per_minute) => Ok(Self::new(per_minute.parse::<NonZeroUsize>()?.get(), MINUTE)),
+            Err(_) => Ok(Self::unlimited()),
+        }
+    }
+
+    /// Counts a submission made at `now`, or returns how long until one is allowed
+    pub async fn try_submit(&self, team_name: &TeamName, challenge: &ChallengeDate, now: Timestamp) -> Result<(), Throttled> {
+        let (submissions, window) = match self.limit {
+            Some(limit) => limit,
+            None => return Ok(()),
+        };
+
+        let mut recent = self.recent.lock().await;
+        let Recent { times, rejected_until } = recent.entry((team_name.clone(), challenge.clone())).or_default();
+        while let Some(&oldest) = times.front() {
+            if oldest + window > now {
+                break;
+            }
+            times.pop_front();
+        }
+
+        match times.front() {
+            Some(&oldest) if times.len() >= submissions => {
+                let first_in_window = now >= *rejected_until;
+                *rejected_until = oldest + window;
+                Err(Throttled { retry_after: oldest + window - now, first_in_window })
+            },
+            _ => {
+                times.push_back(now);
+                Ok(())
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RateLimiter, Throttled};
+    use crate::models::TeamName;
+    use crate::models::solution::ChallengeDate;
+
+    #[test]
+    fn zero_submissions_per_minute_are_rejected() {
+        // The only test that reads the variable
+        std::env::set_var("HASHCODE_SUBMISSIONS_PER_MINUTE", "0");
+        assert!(RateLimiter::from_env().is_err());
+        std::env::remove_var("HASHCODE_SUBMISSIONS_PER_MINUTE");
+    }
+
+    #[tokio::test]
+    async fn limits_each_team_and_challenge_separately() {
+        let limiter = RateLimiter::new(2, 1000);
+        let team = TeamName::from("abc");
+        let challenge = ChallengeDate::Qualification(2020);
+
+        assert_eq!(limiter.try_submit(&team, &challenge, 0).await, Ok(()));
+        assert_eq!(limiter.try_submit(&team, &challenge, 400).await, Ok(()));
+        assert_eq!(limiter.try_submit(&team, &challenge, 600).await, Err(Throttled { retry_after: 400, first_in_window: true }));
+        assert_eq!(limiter.try_submit(&team, &challenge, 700).await, Err(Throttled { retry_after: 300, first_in_window: false }));
+        assert_eq!(limiter.try_submit(&"other".into(), &challenge, 600).await, Ok(()));
+        assert_eq!(limiter.try_submit(&team, &ChallengeDate::Qualification(2016), 600).await, Ok(()));
+
+        assert_eq!(limiter.try_submit(&team, &challenge, 1000).await, Ok(()), "The first submission left the window");
+        assert_eq!(limiter.try_submit(&team, &challenge, 1100).await, Err(Throttled { retry_after: 300, first_in_window: true }),
+                   "A new window reports its first rejection again");
+    }
+}
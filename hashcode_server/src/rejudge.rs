@@ -0,0 +1,221 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use hashcode_score_calc::Challenge;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::jobs::{JobError, ScoringPool};
+use crate::models::TeamName;
+use crate::models::solution::{ChallengeDate, InputFileName};
+use crate::scoreboard::{ChallengeScores, Score, ScoreBoard};
+use crate::submission_store::SubmissionStore;
+use crate::submissions::{RejudgedRecord, SubmissionHistory, SubmissionRecord};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScoreChange {
+    pub team_name: TeamName,
+    pub input: InputFileName,
+    pub old_score: Option<Score>,
+    pub new_score: Option<Score>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RejudgeReport {
+    pub challenge: ChallengeDate,
+    /// Submissions scored again
+    pub rejudged: usize,
+    /// Submissions kept as they were, as their text wasn't stored
+    pub skipped: usize,
+    pub changes: Vec<ScoreChange>,
+}
+
+/// Scores every stored submission to `challenge` again with the current scorer, then swaps in the
+/// best scores they give. Only a full scoring queue stops it, a submission that times out or crashes
+/// the scorer is rejected.
+///
+/// Submissions are addressed by their index in the team's history, so the caller keeps admin actions
+/// from removing any until it returns. New submissions may come in meanwhile.
+pub async fn rejudge(challenge: &ChallengeDate, challenges: Arc<Vec<Challenge>>, scoring_pool: &ScoringPool, store: &SubmissionStore,
+                     scoreboard: &ScoreBoard, history: &mut SubmissionHistory) -> Result<RejudgeReport, JobError> {
+    let mut outcomes = HashMap::<(InputFileName, String), Result<Score, String>>::new();
+    let mut rejudged = HashMap::<(TeamName, usize), Option<Score>>::new();
+    let mut rejudged_records = Vec::new();
+    let mut skipped = 0;
+
+    for (index, record) in history.of_challenge(challenge).await {
+        let key = (record.input.clone(), record.submission_hash.clone());
+        if !outcomes.contains_key(&key) {
+            let submission = match store.load(&record.submission_hash).await {
+                Some(submission) => submission,
+                None => {
+                    skipped += 1;
+                    continue;
+                },
+            };
+            let outcome = score_again(challenge, &challenges, scoring_pool, &record, submission).await?;
+            outcomes.insert(key.clone(), outcome);
+        }
+
+        let (score, error) = match &outcomes[&key] {
+            Ok(score) => (Some(*score), None),
+            Err(error) => (None, Some(error.clone())),
+        };
+        if score != record.score || error != record.error {
+            rejudged_records.push(RejudgedRecord { team_name: record.team_name.clone(), index, score, error });
+        }
+        rejudged.insert((record.team_name, index), score);
+    }
+
+    // Submissions made while re-judging keep their scores, new ones wait until the swap is done
+    let swap = scoreboard.swap_scores().await;
+    let old_scores = swap.scores(challenge);
+    let mut new_scores = old_scores.clone();
+    let mut best_per_team = HashMap::<TeamName, HashMap<InputFileName, Option<Score>>>::new();
+    for (index, record) in history.of_challenge(challenge).await {
+        let score = rejudged.get(&(record.team_name.clone(), index)).cloned().unwrap_or(record.score);
+        let best = best_per_team.entry(record.team_name).or_default().entry(record.input).or_default();
+        *best = (*best).max(score);
+    }
+    for (team_name, best_per_input) in best_per_team {
+        let team_scores = new_scores.entry(team_name).or_default();
+        for (input, best) in best_per_input {
+            match best {
+                Some(best) => team_scores.insert(input, best),
+                None => team_scores.remove(&input),
+            };
+        }
+    }
+
+    let changes = score_changes(&old_scores, &new_scores);
+    let rejudged = rejudged.len();
+    let event = swap.replace(challenge.clone(), new_scores, rejudged_records).await;
+    history.apply(&event).await;
+
+    Ok(RejudgeReport { challenge: challenge.clone(), rejudged, skipped, changes })
+}
+
+async fn score_again(challenge: &ChallengeDate, challenges: &Arc<Vec<Challenge>>, scoring_pool: &ScoringPool,
+                     record: &SubmissionRecord, submission: String) -> Result<Result<Score, String>, JobError> {
+    let challenge = challenge.clone();
+    let challenges = challenges.clone();
+    let input = record.input.clone();
+    let scoring = move || {
+        let relevant_challenge = challenges.iter()
+            .find(|c| c.date == challenge)
+            .expect("Only challenges with a scorer have submissions");
+        relevant_challenge.scorer.resolve_case(&input)
+            .and_then(|case_id| relevant_challenge.scorer.score(&submission, &case_id))
+            .map_err(|e| e.to_string())
+    };
+
+    match scoring_pool.run(scoring).await {
+        Ok(outcome) => Ok(outcome),
+        Err(JobError::QueueFull) => Err(JobError::QueueFull),
+        Err(e) => {
+            if e == JobError::Crashed {
+                log::error!("The scorer panicked re-judging case {} of a {} submission by '{}', sha256 {}",
+                            record.input.as_str(), record.challenge, record.team_name, record.submission_hash);
+            }
+            Ok(Err(e.to_string()))
+        },
+    }
+}
+
+/// Every team and input whose score differs, sorted by team and input
+fn score_changes(old_scores: &ChallengeScores, new_scores: &ChallengeScores) -> Vec<ScoreChange> {
+    let score = |scores: &ChallengeScores, team_name: &TeamName, input: &InputFileName|
+        scores.get(team_name).and_then(|team_scores| team_scores.get(input)).cloned();
+
+    let teams_and_inputs: BTreeSet<(&TeamName, &InputFileName)> = old_scores.iter().chain(new_scores.iter())
+        .flat_map(|(team_name, team_scores)| team_scores.keys().map(move |input| (team_name, input)))
+        .collect();
+
+    teams_and_inputs.into_iter()
+        .map(|(team_name, input)| ScoreChange {
+            team_name: team_name.clone(),
+            input: input.clone(),
+            old_score: score(old_scores, team_name, input),
+            new_score: score(new_scores, team_name, input),
+        })
+        .filter(|change| change.old_score != change.new_score)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rejudge, ScoreChange};
+    use crate::jobs::ScoringPool;
+    use crate::models::solution::ChallengeDate;
+    use crate::scoreboard::ScoreBoard;
+    use crate::submission_store::SubmissionStore;
+    use crate::submissions::{SubmissionHistory, SubmissionRecord};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn wrong_scores_are_fixed() {
+        let challenges = Arc::new(hashcode_score_calc::get_challenges().expect("The shipped challenges should load"));
+        let challenge = ChallengeDate::Qualification(2020);
+        let submission = include_str!("../../hashcode_score_calc/assets/2020qual/submissions/example_submission.txt");
+        let team = "abc".into();
+
+        let store = SubmissionStore::in_memory();
+        let mut scoreboard = ScoreBoard::new();
+        let mut history = SubmissionHistory::new();
+
+        // Judged by a buggy scorer as 20, and a submission whose text was lost
+        let record = SubmissionRecord::new(&team, &challenge, &"a".into(), submission, Ok(20));
+        store.save(&record.submission_hash, submission).await;
+        history.record(record).await;
+        history.record(SubmissionRecord::new(&team, &challenge, &"b".into(), "lost", Ok(3))).await;
+        scoreboard.add_team_score(&team, &"a".into(), 20, challenge.clone()).await;
+        scoreboard.add_team_score(&team, &"b".into(), 3, challenge.clone()).await;
+
+        let pool = ScoringPool::new(1, 4, Duration::from_secs(10));
+        let report = rejudge(&challenge, challenges, &pool, &store, &scoreboard, &mut history).await.expect("Should rejudge");
+
+        assert_eq!((report.rejudged, report.skipped), (1, 1));
+        assert_eq!(report.changes, vec![ScoreChange { team_name: team.clone(), input: "a".into(), old_score: Some(20), new_score: Some(16) }]);
+        assert_eq!(scoreboard.total_score(&team, challenge.clone()).await, 19);
+        assert_eq!(history.of_team(&team).await[0].score, Some(16));
+    }
+
+    #[tokio::test]
+    async fn submissions_during_a_rejudge_are_kept() {
+        let challenges = Arc::new(hashcode_score_calc::get_challenges().expect("The shipped challenges should load"));
+        let challenge = ChallengeDate::Qualification(2020);
+        let submission = include_str!("../../hashcode_score_calc/assets/2020qual/submissions/example_submission.txt");
+        let team = "abc".into();
+
+        let store = SubmissionStore::in_memory();
+        let mut scoreboard = ScoreBoard::new();
+        let mut history = SubmissionHistory::new();
+
+        let record = SubmissionRecord::new(&team, &challenge, &"a".into(), submission, Ok(20));
+        store.save(&record.submission_hash, submission).await;
+        history.record(record).await;
+        scoreboard.add_team_score(&team, &"a".into(), 20, challenge.clone()).await;
+
+        // The only worker is busy until the submission is in, so the rejudge waits in the middle
+        let pool = ScoringPool::new(1, 4, Duration::from_secs(10));
+        let (release, released) = std::sync::mpsc::channel::<()>();
+        let busy_worker = {
+            let pool = pool.clone();
+            tokio::spawn(async move { pool.run(move || released.recv()).await })
+        };
+        let rejudging = {
+            let (challenge, pool, store, scoreboard, mut history) = (challenge.clone(), pool.clone(), store.clone(), scoreboard.clone(), history.clone());
+            tokio::spawn(async move { rejudge(&challenge, challenges, &pool, &store, &scoreboard, &mut history).await })
+        };
+        tokio::time::delay_for(Duration::from_millis(50)).await;
+
+        history.record(SubmissionRecord::new(&team, &challenge, &"b".into(), "new", Ok(5))).await;
+        scoreboard.add_team_score(&team, &"b".into(), 5, challenge.clone()).await;
+        release.send(()).expect("The worker should be waiting");
+
+        busy_worker.await.expect("Should not panic").expect("Should run").expect("Should be released");
+        let report = rejudging.await.expect("Should not panic").expect("Should rejudge");
+        assert_eq!(report.changes, vec![ScoreChange { team_name: team.clone(), input: "a".into(), old_score: Some(20), new_score: Some(16) }]);
+        assert_eq!(scoreboard.best_per_input(&team, challenge.clone()).await, vec![("a".into(), 16), ("b".into(), 5)].into_iter().collect());
+        assert_eq!(history.of_team(&team).await.iter().map(|record| record.score).collect::<Vec<_>>(), vec![Some(16), Some(5)]);
+    }
+}
@@ -1,40 +1,266 @@
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock, RwLockWriteGuard};
 use std::collections::HashMap;
+use serde_derive::{Deserialize, Serialize};
 use crate::models::TeamName;
 use crate::models::solution::{InputFileName, ChallengeDate};
+use crate::event_log::{Event, EventLog};
+use crate::submissions::{Progress, RejudgedRecord, Timestamp};
 
 pub type Score = u64;
+/// The best score of every team on each input of a challenge
+pub type ChallengeScores = HashMap<TeamName, HashMap<InputFileName, Score>>;
+
+/// How many updates a slow `/scoreboard/<challenge>/events` client may fall behind before it has to reload
+const UPDATES_BUFFER: usize = 256;
+
+type Scores = HashMap<ChallengeDate, ChallengeScores>;
+
+/// Pushed to the clients following the scoreboard
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScoreboardUpdate {
+    /// A team improved its best score on an input
+    Improved {
+        challenge: ChallengeDate,
+        team_name: TeamName,
+        input: InputFileName,
+        score: Score,
+        total_score: Score,
+        at: Timestamp,
+    },
+    /// The scores changed some other way, by an admin, so the whole scoreboard should be loaded again.
+    /// `None` stands for every challenge
+    Reload {
+        challenge: Option<ChallengeDate>,
+    },
+}
+
+impl ScoreboardUpdate {
+    pub fn concerns(&self, challenge_date: &ChallengeDate) -> bool {
+        match self {
+            ScoreboardUpdate::Improved { challenge, .. } => challenge == challenge_date,
+            ScoreboardUpdate::Reload { challenge: Some(challenge) } => challenge == challenge_date,
+            ScoreboardUpdate::Reload { challenge: None } => true,
+        }
+    }
+}
+
+/// A line of the scoreboard
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RankedTeam {
+    /// Teams with the same total, reached at the same time, share a rank
+    pub rank: usize,
+    pub team_name: TeamName,
+    pub total_score: Score,
+    pub best_per_input: HashMap<InputFileName, Score>,
+    /// When the team reached its total, `None` until it scores
+    pub achieved_at: Option<Timestamp>,
+}
+
+/// A line of the leaderboard of a single input
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct InputRankedTeam {
+    pub rank: usize,
+    pub team_name: TeamName,
+    pub score: Score,
+    /// When the team first reached its best score on the input
+    pub achieved_at: Option<Timestamp>,
+}
+
+/// A line that is ranked by its score, and the time it was reached on a tie
+trait Standing {
+    fn standing(&self) -> (Score, Option<Timestamp>);
+    fn team_name(&self) -> &TeamName;
+    fn set_rank(&mut self, rank: usize);
+}
+
+impl Standing for RankedTeam {
+    fn standing(&self) -> (Score, Option<Timestamp>) { (self.total_score, self.achieved_at) }
+    fn team_name(&self) -> &TeamName { &self.team_name }
+    fn set_rank(&mut self, rank: usize) { self.rank = rank }
+}
+
+impl Standing for InputRankedTeam {
+    fn standing(&self) -> (Score, Option<Timestamp>) { (self.score, self.achieved_at) }
+    fn team_name(&self) -> &TeamName { &self.team_name }
+    fn set_rank(&mut self, rank: usize) { self.rank = rank }
+}
+
+/// Highest score first, then the earliest to reach it, and teams that never scored last.
+/// Teams in the same standing share a rank
+fn sort_and_rank<T: Standing>(teams: &mut [T]) {
+    let order = |team: &T| {
+        let (score, achieved_at) = team.standing();
+        (std::cmp::Reverse(score), achieved_at.is_none(), achieved_at)
+    };
+    teams.sort_by(|a, b| order(a).cmp(&order(b)).then_with(|| a.team_name().cmp(b.team_name())));
+
+    let mut previous = None;
+    for (i, team) in teams.iter_mut().enumerate() {
+        let rank = match previous {
+            Some((previous_order, rank)) if previous_order == order(team) => rank,
+            _ => i + 1,
+        };
+        team.set_rank(rank);
+        previous = Some((order(team), rank));
+    }
+}
+
+/// Orders the teams by total score, and the team that reached it first on a tie
+pub fn rank(teams: Vec<(TeamName, Progress)>) -> Vec<RankedTeam> {
+    let mut ranked: Vec<RankedTeam> = teams.into_iter()
+        .map(|(team_name, progress)| RankedTeam {
+            rank: 0,
+            team_name,
+            total_score: progress.best_per_input.values().sum(),
+            best_per_input: progress.best_per_input,
+            achieved_at: progress.improved_at,
+        })
+        .collect();
+    sort_and_rank(&mut ranked);
+    ranked
+}
+
+/// Orders the teams that scored on `input` by their best score on it
+pub fn rank_on_input(teams: Vec<(TeamName, Progress)>, input: &InputFileName) -> Vec<InputRankedTeam> {
+    let mut ranked: Vec<InputRankedTeam> = teams.into_iter()
+        .filter_map(|(team_name, progress)| Some(InputRankedTeam {
+            rank: 0,
+            score: *progress.best_per_input.get(input)?,
+            achieved_at: progress.best_at.get(input).cloned(),
+            team_name,
+        }))
+        .collect();
+    sort_and_rank(&mut ranked);
+    ranked
+}
 
 #[derive(Clone)]
 pub struct ScoreBoard {
-    db: Arc<RwLock<HashMap<ChallengeDate, HashMap<TeamName, HashMap<InputFileName, Score>>>>>
+    db: Arc<RwLock<Scores>>,
+    event_log: EventLog,
+    updates: broadcast::Sender<ScoreboardUpdate>,
 }
 
 impl ScoreBoard {
+    #[cfg(test)]
     pub fn new() -> Self {
-        Self { db: Arc::new(RwLock::new(HashMap::new())) }
+        Self::with_event_log(EventLog::in_memory())
+    }
+
+    pub fn with_event_log(event_log: EventLog) -> Self {
+        let (updates, _) = broadcast::channel(UPDATES_BUFFER);
+        Self { db: Arc::new(RwLock::new(HashMap::new())), event_log, updates }
+    }
+
+    /// Every update from now on, of all challenges
+    pub fn subscribe(&self) -> broadcast::Receiver<ScoreboardUpdate> {
+        self.updates.subscribe()
+    }
+
+    /// Tells the clients to load the scoreboard of `challenge` again
+    pub fn notify_changed(&self, challenge: Option<ChallengeDate>) {
+        // Fails only when nobody is listening
+        let _ = self.updates.send(ScoreboardUpdate::Reload { challenge });
+    }
+
+    /// Restores the state recorded by `event`, without logging it again
+    pub async fn apply(&mut self, event: &Event) {
+        let changed = match event {
+            Event::ScoreAdded { team_name, challenge, input, score } => {
+                self.record_score(team_name, input, *score, challenge.clone()).await;
+                return;
+            },
+            Event::TeamDeleted { team_name } => {
+                for teams in self.db.write().await.values_mut() {
+                    teams.remove(team_name);
+                }
+                None
+            },
+            Event::TeamRenamed { from, to } => {
+                for teams in self.db.write().await.values_mut() {
+                    if let Some(scores) = teams.remove(from) {
+                        teams.insert(to.clone(), scores);
+                    }
+                }
+                None
+            },
+            Event::ScoresReset { team_name, challenge } => {
+                if let Some(teams) = self.db.write().await.get_mut(challenge) {
+                    teams.remove(team_name);
+                }
+                Some(challenge.clone())
+            },
+            Event::TeamScoresSet { team_name, challenge, scores } => {
+                self.db.write().await
+                    .entry(challenge.clone())
+                    .or_default()
+                    .insert(team_name.clone(), scores.clone());
+                Some(challenge.clone())
+            },
+            Event::Rejudged { challenge, scores, .. } => {
+                self.db.write().await.insert(challenge.clone(), scores.clone());
+                Some(challenge.clone())
+            },
+            _ => return,
+        };
+        self.notify_changed(changed);
     }
 
     pub async fn add_team_score(&mut self, team_name: &TeamName, file_name: &InputFileName, score: Score, challenge: ChallengeDate) {
         log::info!("Challenge {}: Team '{}' scored {} on file {}", challenge, team_name, score, file_name);
-        let mut db_write_access = self.db.write().await;
-        let stored_score = db_write_access
-            .entry(challenge.clone())
-            .or_default()
-            .entry(team_name.clone())
-            .or_default()
-            .entry(file_name.clone())
-            .or_default();
-
-        if score > *stored_score {
-            *stored_score = score;
+        // Logged under the lock, so the log never has a score before a swap that doesn't include it.
+        // The write doesn't block the executor, other requests go on meanwhile
+        let improved = {
+            let mut db = self.db.write().await;
+            self.event_log.append(&Event::ScoreAdded {
+                team_name: team_name.clone(),
+                challenge: challenge.clone(),
+                input: file_name.clone(),
+                score,
+            }).await;
+            improve_score(&mut db, team_name, file_name, score, challenge.clone())
+        };
+        if improved {
+            let total_score = self.total_score(team_name, challenge.clone()).await;
+            let _ = self.updates.send(ScoreboardUpdate::Improved {
+                challenge,
+                team_name: team_name.clone(),
+                input: file_name.clone(),
+                score,
+                total_score,
+                at: crate::submissions::now(),
+            });
         }
+    }
+
+    /// Clears a team's scores on `challenge`
+    pub async fn reset_team_scores(&mut self, team_name: &TeamName, challenge: ChallengeDate) {
+        let event = Event::ScoresReset { team_name: team_name.clone(), challenge };
+        self.event_log.append(&event).await;
+        self.apply(&event).await;
+    }
+
+    /// Replaces a team's best scores on `challenge`
+    pub async fn set_team_scores(&mut self, team_name: &TeamName, challenge: ChallengeDate, scores: HashMap<InputFileName, Score>) {
+        let event = Event::TeamScoresSet { team_name: team_name.clone(), challenge, scores };
+        self.event_log.append(&event).await;
+        self.apply(&event).await;
+    }
 
+    /// Locks every score until [`ScoresSwap::replace`], new scores wait for the swap
+    pub async fn swap_scores(&self) -> ScoresSwap<'_> {
+        ScoresSwap { db: self.db.write().await, scoreboard: self }
+    }
+
+    /// Whether `score` is the team's new best on the input
+    async fn record_score(&mut self, team_name: &TeamName, file_name: &InputFileName, score: Score, challenge: ChallengeDate) -> bool {
+        improve_score(&mut *self.db.write().await, team_name, file_name, score, challenge)
     }
 
     pub async fn best_per_input(&self, team_name: &TeamName, challenge: ChallengeDate) -> HashMap<InputFileName, Score> {
-        if let Some(team_to_input_to_score) = self.db.write().await.get(&challenge) {
+        if let Some(team_to_input_to_score) = self.db.read().await.get(&challenge) {
             if let Some(input_to_score) = team_to_input_to_score.get(&team_name) {
                 input_to_score.clone()
             } else {HashMap::new()}
@@ -47,11 +273,52 @@ impl ScoreBoard {
     }
 }
 
+/// Write access to every score, taken by a rejudge to merge its scores with the ones submitted meanwhile
+pub struct ScoresSwap<'a> {
+    db: RwLockWriteGuard<'a, Scores>,
+    scoreboard: &'a ScoreBoard,
+}
+
+impl ScoresSwap<'_> {
+    pub fn scores(&self, challenge: &ChallengeDate) -> ChallengeScores {
+        self.db.get(challenge).cloned().unwrap_or_default()
+    }
+
+    /// Swaps every score of `challenge` and releases the lock, returning the logged event for the history to apply
+    pub async fn replace(mut self, challenge: ChallengeDate, scores: ChallengeScores, records: Vec<RejudgedRecord>) -> Event {
+        let event = Event::Rejudged { challenge: challenge.clone(), scores: scores.clone(), records };
+        self.scoreboard.event_log.append(&event).await;
+        self.db.insert(challenge.clone(), scores);
+        drop(self.db);
+        self.scoreboard.notify_changed(Some(challenge));
+        event
+    }
+}
+
+/// Whether `score` is the team's new best on the input
+fn improve_score(db: &mut Scores, team_name: &TeamName, file_name: &InputFileName, score: Score, challenge: ChallengeDate) -> bool {
+    let stored_score = db
+        .entry(challenge)
+        .or_default()
+        .entry(team_name.clone())
+        .or_default()
+        .entry(file_name.clone())
+        .or_default();
+
+    if score > *stored_score {
+        *stored_score = score;
+        true
+    } else {
+        false
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::scoreboard::ScoreBoard;
+    use crate::scoreboard::{rank, ScoreBoard, ScoreboardUpdate};
     use crate::models::TeamName;
-    use crate::models::solution::ChallengeDate;
+    use crate::models::solution::{ChallengeDate, InputFileName};
+    use crate::submissions::Progress;
 
     #[tokio::test]
     async fn can_add_team() {
@@ -80,4 +347,53 @@ mod tests {
             120
         )
     }
+
+    #[tokio::test]
+    async fn only_improvements_are_pushed() {
+
+        let team = TeamName::from("abc");
+        let challenge = ChallengeDate::Qualification(2020);
+
+        let mut score_board = ScoreBoard::new();
+        score_board.add_team_score(&team, &"a".into(), 100, challenge.clone()).await;
+        let mut updates = score_board.subscribe();
+
+        score_board.add_team_score(&team, &"b".into(), 20, challenge.clone()).await;
+        score_board.add_team_score(&team, &"b".into(), 10, challenge.clone()).await;
+        score_board.reset_team_scores(&team, challenge.clone()).await;
+
+        match updates.try_recv() {
+            Ok(ScoreboardUpdate::Improved { team_name, input, score, total_score, .. }) =>
+                assert_eq!((team_name, input, score, total_score), (team.clone(), InputFileName::from("b"), 20, 120)),
+            other => panic!("Expected an improvement, got {:?}", other),
+        }
+        assert_eq!(updates.try_recv().ok(), Some(ScoreboardUpdate::Reload { challenge: Some(challenge) }));
+        assert!(updates.try_recv().is_err());
+    }
+
+    #[test]
+    fn ties_go_to_the_earliest_total() {
+        let progress = |score, improved_at| Progress {
+            best_per_input: vec![(InputFileName::from("a"), score)].into_iter().collect(),
+            improved_at,
+            ..Default::default()
+        };
+
+        let ranked = rank(vec![
+            ("late".into(), progress(10, Some(300))),
+            ("idle".into(), progress(0, None)),
+            ("early".into(), progress(10, Some(100))),
+            ("best".into(), progress(20, Some(200))),
+            ("also_idle".into(), progress(0, None)),
+        ]);
+
+        let ranks: Vec<(usize, String)> = ranked.iter().map(|team| (team.rank, team.team_name.to_string())).collect();
+        assert_eq!(ranks, vec![
+            (1, "best".to_owned()),
+            (2, "early".to_owned()),
+            (3, "late".to_owned()),
+            (4, "also_idle".to_owned()),
+            (4, "idle".to_owned()),
+        ]);
+    }
 }
@@ -0,0 +1,196 @@
+use std::convert::TryInto;
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use hex_string::HexString;
+use serde_derive::{Deserialize, Serialize};
+
+pub type SecretKey = [u8; 32];
+
+const DEFAULT_KEY_FILE: &str = "hashcode_secret.key";
+const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The key team tokens are signed with, and the key it replaced while its grace period lasts
+#[derive(Clone)]
+pub struct SecretKeys {
+    current: SecretKey,
+    previous: Option<(SecretKey, SystemTime)>,
+}
+
+/// How the keys are stored in the key file, hex encoded
+#[derive(Serialize, Deserialize)]
+struct KeyFile {
+    current: String,
+    previous: Option<PreviousKey>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PreviousKey {
+    key: String,
+    /// Seconds since the unix epoch
+    valid_until: u64,
+}
+
+impl SecretKeys {
+    pub fn new(current: SecretKey) -> Self {
+        Self { current, previous: None }
+    }
+
+    pub fn generate() -> Self {
+        Self::new(rand::random())
+    }
+
+    pub fn current(&self) -> &SecretKey {
+        &self.current
+    }
+
+    /// The keys a token may be signed with, current first
+    pub fn accepted(&self) -> impl Iterator<Item = &SecretKey> {
+        let now = SystemTime::now();
+        let previous = self.previous.iter()
+            .filter(move |(_, valid_until)| now < *valid_until)
+            .map(|(key, _)| key);
+        std::iter::once(&self.current).chain(previous)
+    }
+
+    /// Replaces the current key with a new one, still accepting the old key for `grace_period`
+    pub fn rotate(&mut self, grace_period: Duration) {
+        let previous = std::mem::replace(&mut self.current, rand::random());
+        self.previous = Some((previous, SystemTime::now() + grace_period));
+    }
+
+    /// Reads the key file at `path`, generating and writing a new key if it doesn't exist
+    pub fn load_or_create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        if path.exists() {
+            let key_file: KeyFile = serde_json::from_str(&std::fs::read_to_string(path)?)
+                .map_err(|e| invalid_data(format!("Bad key file: {}", e)))?;
+            Self::from_key_file(key_file)
+        } else {
+            let keys = Self::generate();
+            keys.save(path)?;
+            log::info!("Generated a new secret key in {}", path.display());
+            Ok(keys)
+        }
+    }
+
+    /// Writes the keys to `path`, readable only by the owner
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let contents = serde_json::to_string_pretty(&self.to_key_file())
+            .expect("Key files are always serializable");
+
+        // Write aside and rename, so a crash never leaves a half written key
+        let temp_path = path.with_extension("tmp");
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options.open(&temp_path)?.write_all(contents.as_bytes())?;
+        std::fs::rename(temp_path, path)
+    }
+
+    /// Loads the keys as configured by the environment:
+    /// - `HASHCODE_SECRET_KEY` (hex) sets the key directly, and `HASHCODE_PREVIOUS_SECRET_KEY`
+    ///   the key it replaced, which is accepted for the grace period after startup
+    /// - Otherwise the keys are kept in `HASHCODE_SECRET_KEY_FILE` (`hashcode_secret.key` by default),
+    ///   and rotated on startup when `HASHCODE_ROTATE_SECRET_KEY` is set
+    ///
+    /// The grace period is `HASHCODE_KEY_GRACE_PERIOD_SECS`, a day by default.
+    pub fn from_env() -> io::Result<Self> {
+        let grace_period = match std::env::var("HASHCODE_KEY_GRACE_PERIOD_SECS") {
+            Ok(secs) => Duration::from_secs(secs.parse()
+                .map_err(|_| invalid_data("HASHCODE_KEY_GRACE_PERIOD_SECS should be a number".to_owned()))?),
+            Err(_) => DEFAULT_GRACE_PERIOD,
+        };
+
+        if let Ok(current) = std::env::var("HASHCODE_SECRET_KEY") {
+            let mut keys = Self::new(parse_key(&current)?);
+            if let Ok(previous) = std::env::var("HASHCODE_PREVIOUS_SECRET_KEY") {
+                keys.previous = Some((parse_key(&previous)?, SystemTime::now() + grace_period));
+            }
+            log::info!("Using the secret key from HASHCODE_SECRET_KEY");
+            return Ok(keys);
+        }
+
+        let path = std::env::var("HASHCODE_SECRET_KEY_FILE")
+            .unwrap_or_else(|_| DEFAULT_KEY_FILE.to_owned());
+        let mut keys = Self::load_or_create(&path)?;
+        if std::env::var("HASHCODE_ROTATE_SECRET_KEY").is_ok() {
+            keys.rotate(grace_period);
+            keys.save(&path)?;
+            log::info!("Rotated the secret key in {}, the previous key is accepted for {:?}", path, grace_period);
+        } else {
+            log::info!("Using the secret key from {}", path);
+        }
+        Ok(keys)
+    }
+
+    fn from_key_file(key_file: KeyFile) -> io::Result<Self> {
+        let previous = match key_file.previous {
+            Some(PreviousKey { key, valid_until }) =>
+                Some((parse_key(&key)?, UNIX_EPOCH + Duration::from_secs(valid_until))),
+            None => None,
+        };
+        Ok(Self { current: parse_key(&key_file.current)?, previous })
+    }
+
+    fn to_key_file(&self) -> KeyFile {
+        KeyFile {
+            current: HexString::from_bytes(&self.current).as_str().to_owned(),
+            previous: self.previous.map(|(key, valid_until)| PreviousKey {
+                key: HexString::from_bytes(&key).as_str().to_owned(),
+                valid_until: valid_until.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            }),
+        }
+    }
+}
+
+fn parse_key(hex: &str) -> io::Result<SecretKey> {
+    HexString::from_str(hex.trim()).ok()
+        .and_then(|hex| hex.as_bytes().as_slice().try_into().ok())
+        .ok_or_else(|| invalid_data("A secret key should be 64 hex digits".to_owned()))
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SecretKeys;
+    use std::time::Duration;
+
+    #[test]
+    fn previous_key_is_accepted_during_grace_period() {
+        let mut keys = SecretKeys::generate();
+        let old_key = *keys.current();
+
+        keys.rotate(Duration::from_secs(60));
+        assert_ne!(keys.current(), &old_key);
+        assert!(keys.accepted().any(|key| key == &old_key));
+
+        keys.rotate(Duration::from_secs(0));
+        assert_eq!(keys.accepted().count(), 1, "Only the current key after the grace period");
+    }
+
+    #[test]
+    fn key_file_survives_a_restart() {
+        let path = std::env::temp_dir().join(format!("hashcode_secret_{}.key", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut created = SecretKeys::load_or_create(&path).expect("Should create the key file");
+        created.rotate(Duration::from_secs(60));
+        created.save(&path).expect("Should save the key file");
+
+        let loaded = SecretKeys::load_or_create(&path).expect("Should load the key file");
+        assert_eq!(loaded.current(), created.current());
+        assert_eq!(loaded.accepted().collect::<Vec<_>>(), created.accepted().collect::<Vec<_>>());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// The text of every judged submission, stored once per sha256 so it can be re-judged
+#[derive(Clone)]
+pub struct SubmissionStore {
+    /// One file per submission, named by its hash. Submissions are kept in memory without it
+    dir: Option<PathBuf>,
+    in_memory: Arc<RwLock<HashMap<String, String>>>,
+}
+
+impl SubmissionStore {
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        Self { dir: None, in_memory: Arc::new(RwLock::new(HashMap::new())) }
+    }
+
+    /// Creates `dir` when it doesn't exist
+    pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir: Some(dir.as_ref().to_owned()), in_memory: Arc::new(RwLock::new(HashMap::new())) })
+    }
+
+    pub async fn save(&self, submission_hash: &str, submission: &str) {
+        match &self.dir {
+            Some(dir) => {
+                let path = dir.join(submission_hash);
+                if tokio::fs::metadata(&path).await.is_err() {
+                    if let Err(e) = write_aside(&path, submission).await {
+                        log::error!("Couldn't store submission {}: {}", submission_hash, e);
+                    }
+                }
+            },
+            None => {
+                self.in_memory.write().await.insert(submission_hash.to_owned(), submission.to_owned());
+            },
+        }
+    }
+
+    pub async fn load(&self, submission_hash: &str) -> Option<String> {
+        match &self.dir {
+            // Hashes are hex, but don't let a bad one leave the directory
+            Some(dir) if submission_hash.chars().all(|c| c.is_ascii_hexdigit()) =>
+                tokio::fs::read_to_string(dir.join(submission_hash)).await.ok(),
+            Some(_) => None,
+            None => self.in_memory.read().await.get(submission_hash).cloned(),
+        }
+    }
+}
+
+/// Writes aside and renames, so a crash never leaves a half written submission to re-judge.
+/// Each write has its own temporary file, as the same submission may be saved twice at once.
+async fn write_aside(path: &Path, contents: &str) -> io::Result<()> {
+    let temp_path = path.with_extension(format!("{:016x}.tmp", rand::random::<u64>()));
+    tokio::fs::write(&temp_path, contents).await?;
+    if let Err(e) = tokio::fs::rename(&temp_path, path).await {
+        let _ = tokio::fs::remove_file(&temp_path).await;
+        return Err(e);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SubmissionStore;
+
+    #[tokio::test]
+    async fn submissions_are_stored_by_hash() {
+        let dir = std::env::temp_dir().join(format!("hashcode_submissions_{}", std::process::id()));
+        let store = SubmissionStore::open(&dir).expect("Should create the directory");
+
+        store.save("ab12", "2\n0 5\n0 1 2 3 4").await;
+        assert_eq!(store.load("ab12").await.as_deref(), Some("2\n0 5\n0 1 2 3 4"));
+        assert_eq!(store.load("cd34").await, None);
+        assert_eq!(store.load("../ab12").await, None);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1, "No temporary file is left behind");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
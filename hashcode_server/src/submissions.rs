@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::RwLock;
+use hex_string::HexString;
+use serde_derive::{Deserialize, Serialize};
+use sha2::Digest;
+
+use crate::models::TeamName;
+use crate::models::solution::{ChallengeDate, InputFileName};
+use crate::scoreboard::Score;
+use crate::event_log::{Event, EventLog};
+
+/// Milliseconds since the unix epoch
+pub type Timestamp = u64;
+
+pub fn now() -> Timestamp {
+    SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_millis() as Timestamp)
+        .unwrap_or(0)
+}
+
+/// A single input case of a submission, accepted with a score or rejected with an error
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SubmissionRecord {
+    pub team_name: TeamName,
+    pub challenge: ChallengeDate,
+    /// The case id, or the name the team used when it isn't a known case
+    pub input: InputFileName,
+    pub score: Option<Score>,
+    pub error: Option<String>,
+    pub submitted_at: Timestamp,
+    /// Hex encoded sha256 of the submission
+    pub submission_hash: String,
+    /// Cleared by an admin, the record is kept but no longer counts
+    #[serde(default)]
+    pub reset: bool,
+}
+
+impl SubmissionRecord {
+    pub fn new(team_name: &TeamName, challenge: &ChallengeDate, input: &InputFileName, submission: &str,
+               outcome: Result<Score, String>) -> Self {
+        let (score, error) = match outcome {
+            Ok(score) => (Some(score), None),
+            Err(error) => (None, Some(error)),
+        };
+
+        Self {
+            team_name: team_name.clone(),
+            challenge: challenge.clone(),
+            input: input.clone(),
+            score,
+            error,
+            submitted_at: now(),
+            submission_hash: HexString::from_bytes(&sha2::Sha256::digest(submission.as_bytes())).as_str().to_owned(),
+            reset: false,
+        }
+    }
+}
+
+/// The new outcome of the submission at `index` of a team's history
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RejudgedRecord {
+    pub team_name: TeamName,
+    pub index: usize,
+    pub score: Option<Score>,
+    pub error: Option<String>,
+}
+
+/// A team's total score on a challenge from `at` until its next point
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScorePoint {
+    pub at: Timestamp,
+    pub total_score: Score,
+}
+
+/// A team's best score on each input of a challenge, and when its total last went up
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Progress {
+    pub best_per_input: HashMap<InputFileName, Score>,
+    /// `None` until the team scores more than 0
+    pub improved_at: Option<Timestamp>,
+    /// When each best score was first reached
+    pub best_at: HashMap<InputFileName, Timestamp>,
+}
+
+/// Every submission of every team, oldest first, scored or rejected. Submissions with a wrong token aren't
+/// recorded, nor the rate limited ones repeating the first of their window
+#[derive(Clone)]
+pub struct SubmissionHistory {
+    inner: Arc<RwLock<HashMap<TeamName, Vec<SubmissionRecord>>>>,
+    event_log: EventLog,
+}
+
+impl SubmissionHistory {
+    #[cfg(test)]
+    pub fn new() -> Self {
+        Self::with_event_log(EventLog::in_memory())
+    }
+
+    pub fn with_event_log(event_log: EventLog) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(HashMap::new())),
+            event_log,
+        }
+    }
+
+    /// Restores the state recorded by `event`, without logging it again
+    pub async fn apply(&mut self, event: &Event) {
+        match event {
+            Event::SubmissionRecorded { record } => self.push(record.clone()).await,
+            Event::TeamDeleted { team_name } => {
+                self.inner.write().await.remove(team_name);
+            },
+            Event::TeamRenamed { from, to } => {
+                let mut inner = self.inner.write().await;
+                if let Some(mut records) = inner.remove(from) {
+                    for record in &mut records {
+                        record.team_name = to.clone();
+                    }
+                    inner.insert(to.clone(), records);
+                }
+            },
+            Event::ScoresReset { team_name, challenge } => {
+                if let Some(records) = self.inner.write().await.get_mut(team_name) {
+                    for record in records.iter_mut().filter(|record| &record.challenge == challenge) {
+                        record.reset = true;
+                    }
+                }
+            },
+            Event::SubmissionRemoved { team_name, index } => {
+                self.remove_at(team_name, *index).await;
+            },
+            Event::Rejudged { records, .. } => {
+                let mut inner = self.inner.write().await;
+                for rejudged in records {
+                    if let Some(record) = inner.get_mut(&rejudged.team_name).and_then(|records| records.get_mut(rejudged.index)) {
+                        record.score = rejudged.score;
+                        record.error = rejudged.error.clone();
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// Removes the submission at `index` of the team's history, as listed by [`Self::of_team`]
+    pub async fn remove(&mut self, team_name: &TeamName, index: usize) -> Option<SubmissionRecord> {
+        let removed = self.remove_at(team_name, index).await;
+        if removed.is_some() {
+            self.event_log.append(&Event::SubmissionRemoved { team_name: team_name.clone(), index }).await;
+        }
+        removed
+    }
+
+    async fn remove_at(&mut self, team_name: &TeamName, index: usize) -> Option<SubmissionRecord> {
+        match self.inner.write().await.get_mut(team_name) {
+            Some(records) if index < records.len() => Some(records.remove(index)),
+            _ => None,
+        }
+    }
+
+    pub async fn record(&mut self, record: SubmissionRecord) {
+        self.event_log.append(&Event::SubmissionRecorded { record: record.clone() }).await;
+        self.push(record).await;
+    }
+
+    pub async fn of_team(&self, team_name: &TeamName) -> Vec<SubmissionRecord> {
+        self.inner.read().await.get(team_name).cloned().unwrap_or_default()
+    }
+
+    /// Every team's submissions to `challenge` that weren't reset, with their index in the team's history
+    pub async fn of_challenge(&self, challenge: &ChallengeDate) -> Vec<(usize, SubmissionRecord)> {
+        self.inner.read().await.values()
+            .flat_map(|records| records.iter().enumerate())
+            .filter(|(_, record)| &record.challenge == challenge && !record.reset)
+            .map(|(index, record)| (index, record.clone()))
+            .collect()
+    }
+
+    /// Every submission of every team, oldest first, scored or rejected. Submissions with a wrong token aren't
+/// recorded, nor the rate limited ones repeating the first of their window
+    pub async fn all(&self) -> Vec<SubmissionRecord> {
+        let mut records: Vec<SubmissionRecord> = self.inner.read().await.values().flatten().cloned().collect();
+        records.sort_by_key(|record| record.submitted_at);
+        records
+    }
+
+    /// The total score of every team that submitted to `challenge`, at each time it improved
+    pub async fn total_score_history(&self, challenge: &ChallengeDate) -> HashMap<TeamName, Vec<ScorePoint>> {
+        let mut history = HashMap::new();
+        for (team_name, records) in self.inner.read().await.iter() {
+            let mut best_per_input = HashMap::<&InputFileName, Score>::new();
+            let mut points = Vec::<ScorePoint>::new();
+
+            for record in records.iter().filter(|record| &record.challenge == challenge && !record.reset) {
+                let score = match record.score {
+                    Some(score) => score,
+                    None => continue,
+                };
+
+                let best = best_per_input.entry(&record.input).or_default();
+                if score > *best {
+                    *best = score;
+                    points.push(ScorePoint { at: record.submitted_at, total_score: best_per_input.values().sum() });
+                }
+            }
+
+            if !points.is_empty() {
+                history.insert(team_name.clone(), points);
+            }
+        }
+        history
+    }
+
+    /// A team's best score on each input of `challenge`
+    pub async fn best_per_input(&self, team_name: &TeamName, challenge: &ChallengeDate) -> HashMap<InputFileName, Score> {
+        self.progress(team_name, challenge, None).await.best_per_input
+    }
+
+    /// A team's progress on `challenge` among the submissions before `before`, or all of them, leaving out reset ones
+    pub async fn progress(&self, team_name: &TeamName, challenge: &ChallengeDate, before: Option<Timestamp>) -> Progress {
+        let mut progress = Progress::default();
+        if let Some(records) = self.inner.read().await.get(team_name) {
+            let in_time = |record: &SubmissionRecord| match before {
+                Some(before) => record.submitted_at < before,
+                None => true,
+            };
+            for record in records.iter().filter(|record| &record.challenge == challenge && !record.reset && in_time(record)) {
+                if let Some(score) = record.score {
+                    let best = progress.best_per_input.get(&record.input).cloned();
+                    // A first score on the input is a best even when it is 0
+                    if Some(score) > best {
+                        progress.best_per_input.insert(record.input.clone(), score);
+                        progress.best_at.insert(record.input.clone(), record.submitted_at);
+                    }
+                    if score > best.unwrap_or(0) {
+                        progress.improved_at = Some(record.submitted_at);
+                    }
+                }
+            }
+        }
+        progress
+    }
+
+    async fn push(&mut self, record: SubmissionRecord) {
+        self.inner.write().await
+            .entry(record.team_name.clone())
+            .or_default()
+            .push(record);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ScorePoint, SubmissionHistory, SubmissionRecord};
+    use crate::event_log::Event;
+    use crate::scoreboard::Score;
+    use crate::models::TeamName;
+    use crate::models::solution::ChallengeDate;
+
+    #[tokio::test]
+    async fn keeps_every_submission_in_order() {
+        let team = TeamName::from("abc");
+        let challenge = ChallengeDate::Qualification(2020);
+
+        let mut history = SubmissionHistory::new();
+        history.record(SubmissionRecord::new(&team, &challenge, &"a".into(), "2", Ok(16))).await;
+        history.record(SubmissionRecord::new(&team, &challenge, &"a".into(), "x", Err("Expected a number".to_owned()))).await;
+
+        let records = history.of_team(&team).await;
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].score, Some(16));
+        assert_eq!(records[1].error.as_deref(), Some("Expected a number"));
+        assert_ne!(records[0].submission_hash, records[1].submission_hash);
+        assert!(history.of_team(&"other".into()).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn total_score_steps_only_on_improvements() {
+        let team = TeamName::from("abc");
+        let challenge = ChallengeDate::Qualification(2020);
+        let at = |submitted_at, input: &str, score| SubmissionRecord {
+            submitted_at,
+            ..SubmissionRecord::new(&team, &challenge, &input.into(), "", Ok(score))
+        };
+
+        let mut history = SubmissionHistory::new();
+        history.record(at(1, "a", 10)).await;
+        history.record(at(2, "b", 5)).await;
+        history.record(at(3, "a", 7)).await;
+        history.record(at(4, "a", 12)).await;
+        history.record(SubmissionRecord::new(&team, &ChallengeDate::Qualification(2016), &"example".into(), "", Ok(3))).await;
+
+        assert_eq!(history.total_score_history(&challenge).await[&team], vec![
+            ScorePoint { at: 1, total_score: 10 },
+            ScorePoint { at: 2, total_score: 15 },
+            ScorePoint { at: 4, total_score: 17 },
+        ]);
+        assert_eq!(history.total_score_history(&ChallengeDate::Qualification(2019)).await.len(), 0);
+
+        let before_4 = history.progress(&team, &challenge, Some(4)).await;
+        assert_eq!((before_4.best_per_input.values().sum::<Score>(), before_4.improved_at), (15, Some(2)));
+        assert_eq!(history.progress(&team, &challenge, Some(1)).await, Default::default());
+    }
+
+    #[tokio::test]
+    async fn reset_submissions_are_kept_but_not_counted() {
+        let team = TeamName::from("abc");
+        let challenge = ChallengeDate::Qualification(2020);
+        let other_challenge = ChallengeDate::Qualification(2016);
+
+        let mut history = SubmissionHistory::new();
+        history.record(SubmissionRecord::new(&team, &challenge, &"a".into(), "2", Ok(16))).await;
+        history.record(SubmissionRecord::new(&team, &other_challenge, &"example".into(), "", Ok(3))).await;
+        history.apply(&Event::ScoresReset { team_name: team.clone(), challenge: challenge.clone() }).await;
+
+        let records = history.of_team(&team).await;
+        assert_eq!(records.iter().map(|record| record.reset).collect::<Vec<_>>(), vec![true, false]);
+        assert!(history.best_per_input(&team, &challenge).await.is_empty());
+        assert!(history.total_score_history(&challenge).await.is_empty());
+        assert!(history.of_challenge(&challenge).await.is_empty());
+        assert_eq!(history.best_per_input(&team, &other_challenge).await[&"example".into()], 3);
+    }
+}
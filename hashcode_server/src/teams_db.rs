@@ -1,32 +1,124 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 use crate::models::{Team, TeamName};
+use crate::event_log::{Event, EventLog};
+
+#[derive(Debug, PartialEq)]
+pub enum RenameError {
+    UnknownTeam,
+    NameTaken,
+    /// A deleted team or a team's old name, its tokens are still valid
+    NameRetired,
+}
+
+#[derive(Default)]
+struct Teams {
+    registered: HashMap<TeamName, Team>,
+    /// Names of deleted teams and names teams were renamed from, which can't be registered again
+    retired: HashSet<TeamName>,
+}
 
 #[derive(Clone)]
 pub struct TeamsDb {
-    inner: Arc<RwLock<HashMap<TeamName, Team>>>,
+    inner: Arc<RwLock<Teams>>,
+    event_log: EventLog,
 }
 
 impl TeamsDb {
+    #[cfg(test)]
     pub fn new() -> Self {
+        Self::with_event_log(EventLog::in_memory())
+    }
+
+    pub fn with_event_log(event_log: EventLog) -> Self {
         Self {
-            inner: Arc::new(RwLock::new(HashMap::new())),
+            inner: Arc::new(RwLock::new(Teams::default())),
+            event_log,
+        }
+    }
+
+    /// Restores the state recorded by `event`, without logging it again
+    pub async fn apply(&mut self, event: &Event) {
+        match event {
+            Event::TeamRegistered { team } => {
+                self.inner.write().await.registered.insert(team.name.clone(), team.clone());
+            },
+            Event::TeamDeleted { team_name } => {
+                self.inner.write().await.delete(team_name);
+            },
+            Event::TeamRenamed { from, to } => rename_team(&mut *self.inner.write().await, from, to),
+            _ => {},
         }
     }
 
     pub async fn contains(&self, team_name: &TeamName) -> bool {
-        self.inner.read().await.contains_key(team_name)
+        self.inner.read().await.registered.contains_key(team_name)
+    }
+
+    /// The name belonged to a team that was deleted or renamed
+    pub async fn is_retired(&self, team_name: &TeamName) -> bool {
+        self.inner.read().await.retired.contains(team_name)
     }
 
     pub async fn insert(&mut self, team: Team) {
+        self.event_log.append(&Event::TeamRegistered { team: team.clone() }).await;
         let key = team.name.clone();
-        self.inner.write().await.insert(key, team);
+        self.inner.write().await.registered.insert(key, team);
+    }
+
+    /// False when there's no such team
+    pub async fn remove(&mut self, team_name: &TeamName) -> bool {
+        let removed = self.inner.write().await.delete(team_name);
+        if removed {
+            self.event_log.append(&Event::TeamDeleted { team_name: team_name.clone() }).await;
+        }
+        removed
+    }
+
+    /// Renaming to the same name with a different case is allowed, to fix typos
+    pub async fn rename(&mut self, from: &TeamName, to: &TeamName) -> Result<(), RenameError> {
+        let mut teams = self.inner.write().await;
+        if !teams.registered.contains_key(from) {
+            return Err(RenameError::UnknownTeam);
+        }
+        if from != to && teams.registered.contains_key(to) {
+            return Err(RenameError::NameTaken);
+        }
+        if from != to && teams.retired.contains(to) {
+            return Err(RenameError::NameRetired);
+        }
+
+        self.event_log.append(&Event::TeamRenamed { from: from.clone(), to: to.clone() }).await;
+        rename_team(&mut teams, from, to);
+        Ok(())
     }
 
     //TODO change to iterator
     pub async fn list_team_names(&self) -> Vec<TeamName> {
-        self.inner.read().await.keys().map(|s| s.clone()).collect()
+        self.inner.read().await.registered.keys().map(|s| s.clone()).collect()
+    }
+}
+
+impl Teams {
+    /// False when there's no such team
+    fn delete(&mut self, team_name: &TeamName) -> bool {
+        let deleted = self.registered.remove(team_name).is_some();
+        if deleted {
+            self.retired.insert(team_name.clone());
+        }
+        deleted
+    }
+}
+
+fn rename_team(teams: &mut Teams, from: &TeamName, to: &TeamName) {
+    if let Some(mut team) = teams.registered.remove(from) {
+        team.name = to.clone();
+        teams.registered.insert(to.clone(), team);
+        // A change of case keeps the name
+        if from != to {
+            teams.retired.insert(from.clone());
+        }
     }
 }
@@ -18,25 +18,145 @@ var submission_structure_per_challenge = {
         "in_files": "qualification_round_2016.in.zip",
         "scoreboard": "/scoreboard/qual2016"
     },
+    "qualification-2017": {
+        "challenge" : {
+            "Qualification": 2017
+        },
+        "files": ["example", "me_at_the_zoo", "videos_worth_spreading", "trending_today", "kittens"],
+        "pdf_file": "hashcode2017_qualification_task.pdf",
+        "in_files": "qualification_round_2017.in.zip",
+        "scoreboard": "/scoreboard/qual2017"
+    },
+    "qualification-2018": {
+        "challenge" : {
+            "Qualification": 2018
+        },
+        "files": ["a", "b", "c", "d", "e"],
+        "pdf_file": "hashcode2018_qualification_task.pdf",
+        "in_files": "qualification_round_2018.in.zip",
+        "scoreboard": "/scoreboard/qual2018"
+    },
+    "qualification-2019": {
+        "challenge" : {
+            "Qualification": 2019
+        },
+        "files": ["a", "b", "c", "d", "e"],
+        "pdf_file": "hashcode2019_qualification_task.pdf",
+        "in_files": "qualification_round_2019.in.zip",
+        "scoreboard": "/scoreboard/qual2019"
+    },
+    "qualification-2021": {
+        "challenge" : {
+            "Qualification": 2021
+        },
+        "files": ["a", "b", "c", "d", "e", "f"],
+        "pdf_file": "hashcode_2021_online_qualification_round.pdf",
+        "in_files": "qualification_round_2021.in.zip",
+        "scoreboard": "/scoreboard/qual2021"
+    },
+    "qualification-2022": {
+        "challenge" : {
+            "Qualification": 2022
+        },
+        "files": ["a", "b", "c", "d", "e", "f"],
+        "pdf_file": "hashcode_2022_qualification_round.pdf",
+        "in_files": "qualification_round_2022.in.zip",
+        "scoreboard": "/scoreboard/qual2022"
+    },
 }
 
 var default_challenge = "qualification-2016";
 
 var scoreboard_should_update = false;
+var scoreboard_events = null;
+// Bumped on every stop, so the timer of an older polling round doesn't keep going
+var scoreboard_refresh_id = 0;
+// How many times to poll before trying to stream the updates again
+var polls_before_streaming_again = 12;
 
+// Follows the pushed scoreboard updates, or polls every 5 seconds while they can't be streamed
 function start_scoreboard_refresh() {
+    stop_scoreboard_refresh();
+    if (window.EventSource === undefined) {
+        start_scoreboard_polling();
+        return;
+    }
+
+    var events_url = submission_structure_per_challenge[$("#challenge-select")[0].value].scoreboard + "/events";
+    var events = new EventSource(events_url);
+    var reconnecting = false;
+    scoreboard_events = events;
+    events.onmessage = function(e) {
+        apply_scoreboard_update(JSON.parse(e.data));
+    };
+    events.onopen = function() {
+        // Updates pushed while disconnected are lost
+        if (reconnecting) {
+            reconnecting = false;
+            load_scoreboard();
+        }
+    };
+    events.onerror = function() {
+        if (events.readyState === EventSource.CLOSED) {
+            console.log("Scoreboard updates failed, polling instead");
+            stop_scoreboard_refresh();
+            start_scoreboard_polling(polls_before_streaming_again);
+        } else {
+            // The browser reconnects by itself
+            reconnecting = true;
+        }
+    };
+}
+
+// Polls `polls` times before streaming the updates again, or forever when `polls` isn't given
+function start_scoreboard_polling(polls) {
     scoreboard_should_update = true;
+    var refresh_id = scoreboard_refresh_id;
     function inner_refresh() {
+        if (!scoreboard_should_update || refresh_id !== scoreboard_refresh_id) {
+            return;
+        }
+        if (polls === 0) {
+            start_scoreboard_refresh();
+            return;
+        }
         load_scoreboard();
-        if (scoreboard_should_update) {
-            window.setTimeout(inner_refresh, 5000);
+        if (polls !== undefined) {
+            polls -= 1;
         }
+        window.setTimeout(inner_refresh, 5000);
     }
     inner_refresh();
 }
 
 function stop_scoreboard_refresh() {
     scoreboard_should_update = false;
+    scoreboard_refresh_id += 1;
+    if (scoreboard_events !== null) {
+        scoreboard_events.close();
+        scoreboard_events = null;
+    }
+}
+
+function apply_scoreboard_update(update) {
+    if (update.type === "improved") {
+        var team = scoreboard_teams.find(function(t) { return same_team(t.team_name, update.team_name); });
+        if (team === undefined) {
+            team = {team_name: update.team_name, best_per_input: {}};
+            scoreboard_teams.push(team);
+        }
+        team.best_per_input[update.input] = update.score;
+        team.total_score = update.total_score;
+        team.achieved_at = update.at;
+        rank_teams(scoreboard_teams);
+        draw_scoreboard();
+        if (shown_input === update.input) {
+            load_input_scoreboard(shown_input);
+        }
+        schedule_score_history_reload();
+    } else if (update.type === "reload") {
+        load_scoreboard();
+    }
 }
 
 $(document).ready( function() {
@@ -51,8 +171,56 @@ $(document).ready( function() {
 
     show_only('home');
     load_scoreboard();
+    load_contests();
 });
 
+var contests = [];
+// Server time minus client time, so the countdown doesn't depend on the client clock
+var server_clock_offset = 0;
+
+function load_contests() {
+    $.ajax({
+        url: '/contest',
+        type:'GET',
+        success:function(res){
+            contests = res.contests;
+            server_clock_offset = res.now - Date.now();
+            update_countdown();
+            window.setInterval(update_countdown, 1000);
+        }
+    });
+}
+
+function format_time_left(ms) {
+    var seconds = Math.floor(ms / 1000);
+    var hours = Math.floor(seconds / 3600);
+    var minutes = Math.floor((seconds % 3600) / 60);
+    return hours + "h " + minutes + "m " + (seconds % 60) + "s";
+}
+
+function update_countdown() {
+    var countdown = $("#contest-countdown");
+    var challenge = JSON.stringify(submission_structure_per_challenge[$("#challenge-select")[0].value].challenge);
+    var contest = contests.find(function(c) { return JSON.stringify(c.challenge) === challenge; });
+    if (contest === undefined) {
+        countdown.hide();
+        return;
+    }
+
+    var now = Date.now() + server_clock_offset;
+    if (now < contest.starts_at) {
+        countdown.text("The contest starts in " + format_time_left(contest.starts_at - now));
+    } else if (now < contest.ends_at) {
+        countdown.text("Time left: " + format_time_left(contest.ends_at - now));
+    } else {
+        countdown.text("The contest has ended");
+    }
+    if (contest.freezes_at !== null && contest.freezes_at <= now && !contest.revealed) {
+        countdown.append(" - the scoreboard is frozen, only your own score is updated");
+    }
+    countdown.show();
+}
+
 function add_table_row(table, values) {
     var tr = document.createElement("tr");
     for (var val of values) {
@@ -62,30 +230,102 @@ function add_table_row(table, values) {
         tr.appendChild(td);
     }
     table.appendChild(tr);
+    return tr;
 }
 
-function load_scoreboard() {
+// Lets the server show the team its own live score while the scoreboard is frozen
+function team_authorization() {
+    var token = document.forms["registration"]["Token"].value;
+    return token ? {"Authorization": "Bearer " + token} : {};
+}
+
+// The teams of the selected challenge, ranked as the server ranks them
+var scoreboard_teams = [];
+
+// Team names are compared like the server does
+function same_team(a, b) {
+    return a.trim().toLowerCase() === b.trim().toLowerCase();
+}
+
+// By total score, and the team that reached it first on a tie
+function rank_teams(teams) {
+    var order = function(a, b) {
+        if (a.total_score !== b.total_score) {
+            return b.total_score - a.total_score;
+        }
+        if (a.achieved_at !== b.achieved_at) {
+            if (a.achieved_at === null) return 1;
+            if (b.achieved_at === null) return -1;
+            return a.achieved_at - b.achieved_at;
+        }
+        return 0;
+    };
+    teams.sort(function(a, b) {
+        return order(a, b) || a.team_name.trim().toLowerCase().localeCompare(b.team_name.trim().toLowerCase());
+    });
+    for (var i=0; i<teams.length; i++) {
+        teams[i].rank = (i > 0 && order(teams[i], teams[i-1]) === 0) ? teams[i-1].rank : i + 1;
+    }
+}
+
+function draw_scoreboard() {
     var scoreboard = $("#scoreboard-table");
+    var files = submission_structure_per_challenge[$("#challenge-select")[0].value].files;
+
+    // The best known score of each input, highlighted in its column
+    var best_known = files.map(function(f) {
+        return Math.max.apply(null, scoreboard_teams.map(function(team) { return team.best_per_input[f] || 0; }));
+    });
+
+    scoreboard.empty();
+    scoreboard.append("<thead><tr><th>#</th><th>Team Name</th>" +
+        files.map(function(f) { return "<th><a href='#' onclick='load_input_scoreboard(\"" + f + "\")'>" + f + "</a></th>"; }).join("") +
+        "<th>Total Score</th></tr><thead>");
+    for (var team of scoreboard_teams) {
+        var best_scores = files.map(function(f) { return team.best_per_input[f] || 0; });
+        var tr = add_table_row(scoreboard[0], [team.rank, team.team_name].concat(best_scores, [team.total_score]));
+        best_scores.forEach(function(score, i) {
+            if (score > 0 && score === best_known[i]) {
+                tr.children[i + 2].className = "table-success";
+                tr.children[i + 2].title = "Best known score";
+            }
+        });
+    }
+}
+
+// The input whose leaderboard is shown under the scoreboard, if any
+var shown_input = null;
+
+function load_input_scoreboard(input) {
+    shown_input = input;
+    var input_scoreboard_url = submission_structure_per_challenge[$("#challenge-select")[0].value].scoreboard + "/" + input;
+    $.ajax({
+        url: input_scoreboard_url,
+        type:'GET',
+        headers: team_authorization(),
+        success:function(res){
+            var table = $("#input-scoreboard-table");
+            $("#input-scoreboard-title").text("Best scores on " + input).show();
+            table.empty();
+            table.append("<thead><tr><th>#</th><th>Team Name</th><th>Score</th></tr><thead>");
+            for (var team of res) {
+                add_table_row(table[0], [team.rank, team.team_name, team.score]);
+            }
+        }
+    });
+}
+
+function load_scoreboard() {
     var scoreboard_url = submission_structure_per_challenge[$("#challenge-select")[0].value].scoreboard;
     $.ajax({
         url: scoreboard_url,
         type:'GET',
+        headers: team_authorization(),
         success:function(res){
-            var scores = new Array();
             console.log(res);
-
-            for (var team in res) {
-                scores.push({name: team, score: res[team]});
-            }
-
-            scores.sort(function(a,b) { return b.score - a.score});
-
-            scoreboard.empty();
-            scoreboard.append("<thead><tr><th>#</th><th>Team Name</th><th>Total Score</th></tr><thead>");
-            for (var i=0; i<scores.length; i++) {
-                var team_score = scores[i];
-                add_table_row(scoreboard[0], [(i+1), team_score.name, team_score.score]);
-            }
+            scoreboard_teams = res;
+            draw_scoreboard();
+            load_score_history();
         },
         error:function(res){
             alert("Bad thing happend! " + res.statusText);
@@ -93,6 +333,87 @@ function load_scoreboard() {
     });
 }
 
+var chart_colors = ["#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd",
+                    "#8c564b", "#e377c2", "#7f7f7f", "#bcbd22", "#17becf"];
+
+var score_history_reload = null;
+
+// Pushed updates come in bursts, the history is loaded at most once every 5 seconds for them
+function schedule_score_history_reload() {
+    if (score_history_reload === null) {
+        score_history_reload = window.setTimeout(function() {
+            score_history_reload = null;
+            load_score_history();
+        }, 5000);
+    }
+}
+
+function load_score_history() {
+    var history_url = submission_structure_per_challenge[$("#challenge-select")[0].value].scoreboard + "/history";
+    $.ajax({
+        url: history_url,
+        type:'GET',
+        success:function(res){
+            draw_score_history($("#score-history-chart")[0], res);
+        }
+    });
+}
+
+// Draws each team's total score as a step function of the hours since the first submission
+function draw_score_history(canvas, history) {
+    var ctx = canvas.getContext("2d");
+    var margin = 50;
+    var width = canvas.width - 2 * margin;
+    var height = canvas.height - 2 * margin;
+    ctx.clearRect(0, 0, canvas.width, canvas.height);
+
+    var teams = Object.keys(history);
+    if (teams.length === 0) {
+        return;
+    }
+
+    var first = Infinity, last = -Infinity, top_score = 1;
+    for (var team of teams) {
+        for (var point of history[team]) {
+            first = Math.min(first, point.at);
+            last = Math.max(last, point.at);
+            top_score = Math.max(top_score, point.total_score);
+        }
+    }
+    var x = function(at) { return margin + width * (at - first) / Math.max(last - first, 1); };
+    var y = function(score) { return margin + height * (1 - score / top_score); };
+
+    ctx.strokeStyle = "#000";
+    ctx.fillStyle = "#000";
+    ctx.beginPath();
+    ctx.moveTo(margin, margin);
+    ctx.lineTo(margin, margin + height);
+    ctx.lineTo(margin + width, margin + height);
+    ctx.stroke();
+    ctx.fillText(top_score, 5, margin);
+    ctx.fillText("0", 5, margin + height);
+    ctx.fillText(((last - first) / 3600000).toFixed(1) + " hours", margin + width - 50, margin + height + 20);
+
+    teams.forEach(function(team, i) {
+        var color = chart_colors[i % chart_colors.length];
+        var previous_y = y(0);
+
+        ctx.strokeStyle = color;
+        ctx.beginPath();
+        ctx.moveTo(x(history[team][0].at), previous_y);
+        for (var point of history[team]) {
+            ctx.lineTo(x(point.at), previous_y);
+            previous_y = y(point.total_score);
+            ctx.lineTo(x(point.at), previous_y);
+        }
+        ctx.lineTo(x(last), previous_y);
+        ctx.stroke();
+
+        ctx.fillStyle = color;
+        ctx.fillText(team, margin + 10, margin + 15 * i);
+    });
+}
+
 
 function show_only(pn) {
     if (pn === 'scoreboard') {
@@ -143,7 +464,16 @@ function change_challenge() {
         "<a href='" + sub_structure.in_files + "'>Input files</a>"
     );
 
+    $("#scoreboard-export-csv").attr("href", sub_structure.scoreboard + "/export?format=csv");
+    $("#scoreboard-export-json").attr("href", sub_structure.scoreboard + "/export?format=json");
+
+    shown_input = null;
+    $("#input-scoreboard-title").hide();
+    $("#input-scoreboard-table").empty();
     load_scoreboard();
+    if (scoreboard_events !== null) {
+        start_scoreboard_refresh();
+    }
 }
 
 function submit_files() {
@@ -204,6 +534,8 @@ function submit_files() {
                 for (var in_file_name in res) {
                     add_table_row(last_submissions_table[0], [datetime, in_file_name, res[in_file_name]])
                 }
+                // Improvements aren't pushed while the scoreboard is frozen, but a team still sees its own
+                load_scoreboard();
             },
             error:function(jqxhr, status){
                 alert("Submission failed: " + jqxhr.responseText)
@@ -270,6 +602,10 @@ function register_team() {
                     alert("This team already exists...");
                     return
                 }
+                if (res === "ErrorTeamNameRetired") {
+                    alert("This name belonged to a deleted team, please pick another one");
+                    return
+                }
                 token_as_hex = array_to_hex(res.token);
                 console.log(token_as_hex);
                 reg_form["Token"].value = token_as_hex;